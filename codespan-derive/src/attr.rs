@@ -0,0 +1,191 @@
+//! Parsing of the attributes used by `#[derive(Diagnostic)]`.
+
+use proc_macro2::Span;
+use syn::{Attribute, Error, Ident, Lit, LitStr, Meta, NestedMeta, Result};
+
+/// Attributes that are placed on structs and enum variants.
+const DIAGNOSTIC_ATTRS: &[&str] = &["error", "warning", "note", "help"];
+/// Attributes that are placed on fields.
+const LABEL_ATTRS: &[&str] = &["primary_label", "secondary_label"];
+
+/// The attributes of a struct or enum variant, describing the diagnostic
+/// that it is converted into.
+pub struct DiagnosticAttrs {
+    /// The variant of `Severity` to use for the diagnostic.
+    pub severity: Ident,
+    pub code: Option<LitStr>,
+    pub message: LitStr,
+    /// The `Note` constructor and message of each note, in order.
+    pub notes: Vec<(Ident, LitStr)>,
+}
+
+/// The attribute of a field, describing the label that points to it.
+pub struct LabelAttr {
+    /// The variant of `LabelStyle` to use for the label.
+    pub style: Ident,
+    pub message: Option<LitStr>,
+}
+
+fn attr_name(attr: &Attribute) -> Option<String> {
+    attr.path.get_ident().map(Ident::to_string)
+}
+
+/// Parse the attributes of a struct or enum variant, using the span to
+/// report a missing severity attribute.
+pub fn parse_diagnostic_attrs(attrs: &[Attribute], span: Span) -> Result<DiagnosticAttrs> {
+    let mut diagnostic = None;
+    let mut notes = Vec::new();
+
+    for attr in attrs {
+        let severity = match attr_name(attr).as_deref() {
+            Some("error") => "Error",
+            Some("warning") => "Warning",
+            Some(name @ "note") | Some(name @ "help") => {
+                let constructor = Ident::new(name, Span::call_site());
+                notes.push((constructor, parse_message_attr(attr, name)?));
+                continue;
+            }
+            Some(name) if LABEL_ATTRS.contains(&name) => {
+                let message = format!("`#[{}]` must be placed on a field", name);
+                return Err(Error::new_spanned(attr, message));
+            }
+            _ => continue,
+        };
+
+        if diagnostic.is_some() {
+            let message = "only one `#[error(...)]` or `#[warning(...)]` attribute is allowed";
+            return Err(Error::new_spanned(attr, message));
+        }
+        let (code, message) = parse_severity_attr(attr)?;
+        diagnostic = Some((Ident::new(severity, Span::call_site()), code, message));
+    }
+
+    match diagnostic {
+        Some((severity, code, message)) => Ok(DiagnosticAttrs {
+            severity,
+            code,
+            message,
+            notes,
+        }),
+        None => Err(Error::new(
+            span,
+            "missing `#[error(...)]` or `#[warning(...)]` attribute",
+        )),
+    }
+}
+
+/// Parse the attributes of a field, returning the label that points to it,
+/// if any.
+pub fn parse_label_attr(attrs: &[Attribute]) -> Result<Option<LabelAttr>> {
+    let mut label = None;
+
+    for attr in attrs {
+        let (name, style) = match attr_name(attr).as_deref() {
+            Some("primary_label") => ("primary_label", "Primary"),
+            Some("secondary_label") => ("secondary_label", "Secondary"),
+            Some(name) if DIAGNOSTIC_ATTRS.contains(&name) => {
+                let message = format!("`#[{}]` must be placed on a struct or enum variant", name);
+                return Err(Error::new_spanned(attr, message));
+            }
+            _ => continue,
+        };
+
+        if label.is_some() {
+            return Err(Error::new_spanned(
+                attr,
+                "only one label is allowed per field",
+            ));
+        }
+        let message = match attr.parse_meta()? {
+            Meta::Path(_) => None,
+            _ => Some(parse_message_attr(attr, name)?),
+        };
+        label = Some(LabelAttr {
+            style: Ident::new(style, Span::call_site()),
+            message,
+        });
+    }
+
+    Ok(label)
+}
+
+/// Reject any of the attributes of `#[derive(Diagnostic)]` on an enum, where
+/// they should have been placed on its variants or their fields instead.
+pub fn reject_enum_attrs(attrs: &[Attribute]) -> Result<()> {
+    for attr in attrs {
+        match attr_name(attr).as_deref() {
+            Some(name) if DIAGNOSTIC_ATTRS.contains(&name) || LABEL_ATTRS.contains(&name) => {
+                let message = format!("`#[{}]` must be placed on enum variants", name);
+                return Err(Error::new_spanned(attr, message));
+            }
+            _ => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Parse an attribute of the form `#[error(code = "...", message = "...")]`.
+fn parse_severity_attr(attr: &Attribute) -> Result<(Option<LitStr>, LitStr)> {
+    let list = match attr.parse_meta()? {
+        Meta::List(list) => list,
+        meta => {
+            let message = "expected `code = \"...\"` and `message = \"...\"` arguments";
+            return Err(Error::new_spanned(meta, message));
+        }
+    };
+
+    let mut code = None;
+    let mut message = None;
+    for nested in &list.nested {
+        let (path, lit) = match nested {
+            NestedMeta::Meta(Meta::NameValue(name_value)) => (&name_value.path, &name_value.lit),
+            _ => {
+                let message = "expected `code = \"...\"` or `message = \"...\"`";
+                return Err(Error::new_spanned(nested, message));
+            }
+        };
+
+        let (key, value) = if path.is_ident("code") {
+            ("code", &mut code)
+        } else if path.is_ident("message") {
+            ("message", &mut message)
+        } else {
+            return Err(Error::new_spanned(path, "expected `code` or `message`"));
+        };
+        if value.is_some() {
+            let message = format!("duplicate `{}` argument", key);
+            return Err(Error::new_spanned(path, message));
+        }
+        *value = Some(string_lit(lit)?);
+    }
+
+    match message {
+        Some(message) => Ok((code, message)),
+        None => Err(Error::new_spanned(
+            list,
+            "missing `message = \"...\"` argument",
+        )),
+    }
+}
+
+/// Parse an attribute of the form `#[name("...")]`.
+fn parse_message_attr(attr: &Attribute, name: &str) -> Result<LitStr> {
+    if let Meta::List(list) = attr.parse_meta()? {
+        if list.nested.len() == 1 {
+            if let NestedMeta::Lit(lit) = &list.nested[0] {
+                return string_lit(lit);
+            }
+        }
+    }
+
+    let message = format!("expected `#[{}(\"...\")]`", name);
+    Err(Error::new_spanned(attr, message))
+}
+
+fn string_lit(lit: &Lit) -> Result<LitStr> {
+    match lit {
+        Lit::Str(lit) => Ok(lit.clone()),
+        _ => Err(Error::new_spanned(lit, "expected a string literal")),
+    }
+}
@@ -0,0 +1,309 @@
+//! Derive macros for converting types into [`codespan_reporting`]
+//! diagnostics.
+//!
+//! `#[derive(Diagnostic)]` implements `From<&T>` for `Diagnostic<FileId>`,
+//! for structs and enums that describe errors or warnings:
+//!
+//! ```rust
+//! use codespan_derive::Diagnostic;
+//! use codespan_reporting::diagnostic::{Diagnostic, Label, Note};
+//! use std::ops::Range;
+//!
+//! #[derive(Diagnostic)]
+//! enum TypeError {
+//!     #[error(code = "E0308", message = "mismatched types")]
+//!     #[help("try converting the value to `{expected}`")]
+//!     Mismatch {
+//!         expected: String,
+//!         found: String,
+//!         #[primary_label("expected `{expected}`, found `{found}`")]
+//!         value: (usize, Range<usize>),
+//!         #[secondary_label("expected due to this")]
+//!         annotation: (usize, Range<usize>),
+//!     },
+//!     #[warning(message = "unused variable `{0}`")]
+//!     UnusedVariable(String, #[primary_label] (usize, Range<usize>)),
+//! }
+//!
+//! let error = TypeError::Mismatch {
+//!     expected: "i32".to_owned(),
+//!     found: "&str".to_owned(),
+//!     value: (0, 16..23),
+//!     annotation: (0, 9..12),
+//! };
+//! let diagnostic = Diagnostic::from(&error);
+//!
+//! assert_eq!(diagnostic.code.as_deref(), Some("E0308"));
+//! assert_eq!(diagnostic.message, "mismatched types");
+//! assert_eq!(
+//!     diagnostic.labels,
+//!     vec![
+//!         Label::primary(0, 16..23).with_message("expected `i32`, found `&str`"),
+//!         Label::secondary(0, 9..12).with_message("expected due to this"),
+//!     ],
+//! );
+//! assert_eq!(
+//!     diagnostic.notes,
+//!     vec![Note::help("try converting the value to `i32`")],
+//! );
+//! ```
+//!
+//! # Attributes
+//!
+//! Each struct or enum variant needs a severity attribute, describing the
+//! diagnostic that it is converted into:
+//!
+//! - `#[error(code = "...", message = "...")]` creates an error diagnostic,
+//!   with an optional code.
+//! - `#[warning(code = "...", message = "...")]` creates a warning
+//!   diagnostic, with an optional code.
+//! - `#[note("...")]` and `#[help("...")]` add a note to the diagnostic, and
+//!   can be repeated.
+//!
+//! Fields can be pointed to by a label:
+//!
+//! - `#[primary_label("...")]` or `#[primary_label]` adds a primary label,
+//!   with an optional message.
+//! - `#[secondary_label("...")]` or `#[secondary_label]` adds a secondary
+//!   label, with an optional message.
+//!
+//! Labels are added in the order of the fields, and the types of labelled
+//! fields must implement [`LabelSpan`], which is implemented for pairs of a
+//! file id and a byte range. The file id of the diagnostic is determined by
+//! the types of the labelled fields, and is generic if there are none.
+//!
+//! # Messages
+//!
+//! Messages are format strings, that can interpolate fields by name, like
+//! `{expected}`, or by index in tuple structs and variants, like `{0}`, with
+//! the usual formatting options of [`format!`]. Braces that should appear in
+//! the message, like those used by [`markup`], are written as `{{` and `}}`.
+//! Markup is only styled if [`Diagnostic::with_markup`] is called on the
+//! converted diagnostic.
+//!
+//! [`LabelSpan`]: codespan_reporting::diagnostic::LabelSpan
+//! [`markup`]: codespan_reporting::markup
+//! [`Diagnostic::with_markup`]: codespan_reporting::diagnostic::Diagnostic::with_markup
+
+extern crate proc_macro;
+
+mod attr;
+mod message;
+
+use proc_macro2::{Span, TokenStream};
+use quote::{format_ident, quote};
+use syn::ext::IdentExt;
+use syn::spanned::Spanned;
+use syn::{parse_macro_input, parse_quote, parse_quote_spanned, Attribute, Data, DeriveInput};
+use syn::{Error, Fields, Ident, Member, Result, Type};
+
+use crate::attr::{DiagnosticAttrs, LabelAttr};
+
+/// Derive a conversion from references to the type into diagnostics. See the
+/// [crate documentation](crate) for the supported attributes.
+#[proc_macro_derive(
+    Diagnostic,
+    attributes(error, warning, note, help, primary_label, secondary_label)
+)]
+pub fn derive_diagnostic(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(&input)
+        .unwrap_or_else(|error| error.to_compile_error())
+        .into()
+}
+
+/// A field of a struct or enum variant.
+struct Field<'a> {
+    /// The name or index used to refer to the field in messages.
+    name: String,
+    member: Member,
+    /// The variable that the field is bound to when matching on the value.
+    binding: Ident,
+    ty: &'a Type,
+    label: Option<LabelAttr>,
+}
+
+/// A struct or enum variant, along with the attributes that describe the
+/// diagnostic it is converted into.
+struct Variant<'a> {
+    /// The path used to match on the struct or enum variant.
+    path: TokenStream,
+    attrs: DiagnosticAttrs,
+    fields: Vec<Field<'a>>,
+}
+
+impl<'a> Variant<'a> {
+    fn new(
+        path: TokenStream,
+        attrs: &[Attribute],
+        fields: &'a Fields,
+        span: Span,
+    ) -> Result<Variant<'a>> {
+        let attrs = attr::parse_diagnostic_attrs(attrs, span)?;
+        let fields = fields
+            .iter()
+            .enumerate()
+            .map(|(index, field)| {
+                let (name, member) = match &field.ident {
+                    Some(ident) => (ident.unraw().to_string(), Member::from(ident.clone())),
+                    None => (index.to_string(), Member::from(index)),
+                };
+                Ok(Field {
+                    binding: format_ident!("__self_{}", name),
+                    name,
+                    member,
+                    ty: &field.ty,
+                    label: attr::parse_label_attr(&field.attrs)?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Variant {
+            path,
+            attrs,
+            fields,
+        })
+    }
+
+    /// Generate the match arm that converts the struct or enum variant into
+    /// a diagnostic.
+    fn expand(&self) -> Result<TokenStream> {
+        let mut used = Vec::new();
+
+        let severity = &self.attrs.severity;
+        let code = self.attrs.code.iter();
+        let message = message::format(&self.attrs.message, &self.fields, &mut used)?;
+
+        let mut statements = Vec::new();
+        for field in &self.fields {
+            let label = match &field.label {
+                Some(label) => label,
+                None => continue,
+            };
+            let style = &label.style;
+            let binding = &field.binding;
+            let message = match &label.message {
+                Some(message) => {
+                    let message = message::format(message, &self.fields, &mut used)?;
+                    quote!(.with_message(#message))
+                }
+                None => quote!(),
+            };
+            statements.push(quote! {
+                diagnostic.labels.push(
+                    ::codespan_reporting::diagnostic::Label::new(
+                        ::codespan_reporting::diagnostic::LabelStyle::#style,
+                        ::codespan_reporting::diagnostic::LabelSpan::file_id(#binding),
+                        ::codespan_reporting::diagnostic::LabelSpan::range(#binding),
+                    )
+                    #message
+                );
+            });
+            if !used.contains(binding) {
+                used.push(binding.clone());
+            }
+        }
+        for (constructor, message) in &self.attrs.notes {
+            let message = message::format(message, &self.fields, &mut used)?;
+            statements.push(quote! {
+                diagnostic.notes.push(
+                    ::codespan_reporting::diagnostic::Note::#constructor(#message)
+                );
+            });
+        }
+
+        let bindings = self
+            .fields
+            .iter()
+            .filter(|field| used.contains(&field.binding));
+        let members = bindings.clone().map(|field| &field.member);
+        let bindings = bindings.map(|field| &field.binding);
+        let path = &self.path;
+        let mutability = if statements.is_empty() {
+            quote!()
+        } else {
+            quote!(mut)
+        };
+
+        Ok(quote! {
+            #path { #(#members: #bindings,)* .. } => {
+                let #mutability diagnostic = ::codespan_reporting::diagnostic::Diagnostic::new(
+                    ::codespan_reporting::diagnostic::Severity::#severity,
+                )
+                #(.with_code(#code))*
+                .with_message(#message);
+                #(#statements)*
+                diagnostic
+            }
+        })
+    }
+}
+
+fn expand(input: &DeriveInput) -> Result<TokenStream> {
+    let ident = &input.ident;
+    let variants = match &input.data {
+        Data::Struct(data) => vec![Variant::new(
+            quote!(#ident),
+            &input.attrs,
+            &data.fields,
+            ident.span(),
+        )?],
+        Data::Enum(data) => {
+            attr::reject_enum_attrs(&input.attrs)?;
+            data.variants
+                .iter()
+                .map(|variant| {
+                    let variant_ident = &variant.ident;
+                    let path = quote!(#ident::#variant_ident);
+                    Variant::new(path, &variant.attrs, &variant.fields, variant_ident.span())
+                })
+                .collect::<Result<_>>()?
+        }
+        Data::Union(data) => {
+            let message = "`#[derive(Diagnostic)]` does not support unions";
+            return Err(Error::new_spanned(data.union_token, message));
+        }
+    };
+
+    let file_id = Ident::new("__FileId", Span::call_site());
+    let mut generics = input.generics.clone();
+    generics.params.push(parse_quote!(#file_id));
+    let where_clause = generics.make_where_clause();
+    for variant in &variants {
+        for field in &variant.fields {
+            if field.label.is_some() {
+                let ty = field.ty;
+                // Spanned so that labelled fields that are not spans are
+                // reported at their types.
+                where_clause
+                    .predicates
+                    .push(parse_quote_spanned! {ty.span()=>
+                        #ty: ::codespan_reporting::diagnostic::LabelSpan<FileId = #file_id>
+                    });
+            }
+        }
+    }
+    let (impl_generics, _, where_clause) = generics.split_for_impl();
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+
+    let arms = variants
+        .iter()
+        .map(Variant::expand)
+        .collect::<Result<Vec<_>>>()?;
+    let body = if arms.is_empty() {
+        quote!(match *value {})
+    } else {
+        quote!(match value { #(#arms)* })
+    };
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::From<&#ident #ty_generics>
+            for ::codespan_reporting::diagnostic::Diagnostic<#file_id>
+        #where_clause
+        {
+            fn from(value: &#ident #ty_generics) -> Self {
+                #body
+            }
+        }
+    })
+}
@@ -0,0 +1,82 @@
+//! Formatting of messages that interpolate the fields of a struct or enum
+//! variant.
+
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::{Error, Ident, LitStr, Result};
+
+use crate::Field;
+
+/// Generate an expression that formats a message, replacing the field names
+/// and indices in its arguments with the bindings of the fields. The
+/// bindings of the interpolated fields are added to `used`.
+pub fn format(
+    message: &LitStr,
+    fields: &[Field<'_>],
+    used: &mut Vec<Ident>,
+) -> Result<TokenStream> {
+    let value = message.value();
+    let mut format = String::with_capacity(value.len());
+    let mut bindings = Vec::new();
+
+    let mut rest = value.as_str();
+    while let Some(start) = rest.find(&['{', '}'][..]) {
+        format.push_str(&rest[..start + 1]);
+        let delimiter = &rest[start..start + 1];
+        rest = &rest[start + 1..];
+
+        if rest.starts_with(delimiter) {
+            format.push_str(delimiter);
+            rest = &rest[1..];
+            continue;
+        } else if delimiter == "}" {
+            let error = "unmatched `}` in message, use `}}` to write a literal `}`";
+            return Err(Error::new(message.span(), error));
+        }
+
+        let end = match rest.find('}') {
+            Some(end) => end,
+            None => {
+                let error = "unmatched `{` in message, use `{{` to write a literal `{`";
+                return Err(Error::new(message.span(), error));
+            }
+        };
+        let argument = &rest[..end];
+        let (name, spec) = match argument.find(':') {
+            Some(colon) => argument.split_at(colon),
+            None => (argument, ""),
+        };
+
+        let field = match fields.iter().find(|field| field.name == name) {
+            Some(field) => field,
+            None if name.is_empty() => {
+                let error = "message arguments must name a field, like `{name}` or `{0}`";
+                return Err(Error::new(message.span(), error));
+            }
+            None => {
+                let error = format!("no field named `{}`", name);
+                return Err(Error::new(message.span(), error));
+            }
+        };
+        format.push_str(&field.binding.to_string());
+        format.push_str(spec);
+        format.push('}');
+        rest = &rest[end + 1..];
+
+        if !bindings.contains(&field.binding) {
+            bindings.push(field.binding.clone());
+        }
+    }
+    format.push_str(rest);
+
+    for binding in &bindings {
+        if !used.contains(binding) {
+            used.push(binding.clone());
+        }
+    }
+
+    let format = LitStr::new(&format, message.span());
+    Ok(quote! {
+        ::codespan_reporting::__private::format!(#format #(, #bindings = #bindings)*)
+    })
+}
@@ -0,0 +1,149 @@
+use codespan_derive::Diagnostic;
+use codespan_reporting::diagnostic::{Diagnostic, Label, LabelSpan, Note, Severity};
+use std::ops::Range;
+
+#[derive(Diagnostic)]
+#[error(code = "E0001", message = "unexpected character `{found}`")]
+#[note("characters must be ASCII")]
+#[help("try removing `{found}`")]
+struct UnexpectedCharacter {
+    found: char,
+    #[primary_label("unexpected character")]
+    span: (usize, Range<usize>),
+}
+
+#[derive(Diagnostic)]
+enum TypeError {
+    #[error(message = "expected `{expected}`, found `{found:?}`")]
+    Mismatch {
+        expected: &'static str,
+        found: &'static str,
+        #[primary_label]
+        value: (usize, Range<usize>),
+        #[secondary_label("expected due to this")]
+        annotation: (usize, Range<usize>),
+    },
+    #[warning(code = "W0001", message = "unused variable `{0}`")]
+    UnusedVariable(
+        String,
+        #[primary_label("`{0}` is never used")] (usize, Range<usize>),
+    ),
+    #[error(message = "cyclic definitions of {{+{0}+}}")]
+    Cycle(String),
+}
+
+#[test]
+fn struct_diagnostic() {
+    let error = UnexpectedCharacter {
+        found: 'λ',
+        span: (1, 4..6),
+    };
+
+    assert_eq!(
+        Diagnostic::from(&error),
+        Diagnostic::error()
+            .with_code("E0001")
+            .with_message("unexpected character `λ`")
+            .with_labels(vec![
+                Label::primary(1, 4..6).with_message("unexpected character")
+            ])
+            .with_typed_notes(vec![
+                Note::note("characters must be ASCII"),
+                Note::help("try removing `λ`"),
+            ]),
+    );
+}
+
+#[test]
+fn enum_diagnostics() {
+    let error = TypeError::Mismatch {
+        expected: "Int",
+        found: "String",
+        value: (0, 10..18),
+        annotation: (0, 4..7),
+    };
+
+    assert_eq!(
+        Diagnostic::from(&error),
+        Diagnostic::error()
+            .with_message("expected `Int`, found `\"String\"`")
+            .with_labels(vec![
+                Label::primary(0, 10..18),
+                Label::secondary(0, 4..7).with_message("expected due to this"),
+            ]),
+    );
+
+    let error = TypeError::UnusedVariable("x".to_owned(), (2, 4..5));
+    let diagnostic = Diagnostic::from(&error);
+
+    assert_eq!(diagnostic.severity, Severity::Warning);
+    assert_eq!(diagnostic.code.as_deref(), Some("W0001"));
+    assert_eq!(diagnostic.message, "unused variable `x`");
+    assert_eq!(
+        diagnostic.labels,
+        vec![Label::primary(2, 4..5).with_message("`x` is never used")],
+    );
+}
+
+#[test]
+fn escaped_braces() {
+    let error = TypeError::Cycle("Nat".to_owned());
+    let diagnostic = Diagnostic::<usize>::from(&error);
+
+    assert_eq!(diagnostic.message, "cyclic definitions of {+Nat+}");
+    assert!(diagnostic.labels.is_empty());
+}
+
+struct Span {
+    file: &'static str,
+    start: usize,
+    end: usize,
+}
+
+impl LabelSpan for Span {
+    type FileId = &'static str;
+
+    fn file_id(&self) -> &'static str {
+        self.file
+    }
+
+    fn range(&self) -> Range<usize> {
+        self.start..self.end
+    }
+}
+
+#[derive(Diagnostic)]
+#[error(message = "duplicate definition of `{name}`")]
+struct DuplicateDefinition<'a, S> {
+    name: &'a str,
+    #[primary_label("redefined here")]
+    duplicate: S,
+    #[secondary_label("first defined here")]
+    original: &'a S,
+}
+
+#[test]
+fn generic_spans() {
+    let original = Span {
+        file: "a.fun",
+        start: 0,
+        end: 3,
+    };
+    let error = DuplicateDefinition {
+        name: "foo",
+        duplicate: Span {
+            file: "b.fun",
+            start: 8,
+            end: 11,
+        },
+        original: &original,
+    };
+
+    assert_eq!(
+        Diagnostic::from(&error).labels,
+        vec![
+            Label::primary("b.fun", 8..11).with_message("redefined here"),
+            Label::secondary("a.fun", 0..3).with_message("first defined here"),
+        ],
+    );
+}
@@ -0,0 +1,10 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+#[error(message = "parse error")]
+enum ParseError {
+    #[error(message = "unexpected end of file")]
+    UnexpectedEof,
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+#[error(message = "unused variable")]
+#[warning(message = "unused variable")]
+struct UnusedVariable;
+
+fn main() {}
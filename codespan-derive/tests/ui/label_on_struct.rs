@@ -0,0 +1,8 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+#[error(message = "unexpected end of file")]
+#[primary_label("end of file")]
+struct UnexpectedEof;
+
+fn main() {}
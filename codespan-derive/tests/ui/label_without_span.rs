@@ -0,0 +1,10 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+#[error(message = "unknown variable `{name}`")]
+struct UnknownVariable {
+    #[primary_label("not found in this scope")]
+    name: String,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+#[error(code = "E0001")]
+struct UnexpectedEof;
+
+fn main() {}
@@ -0,0 +1,10 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+enum Error {
+    #[error(message = "unexpected end of file")]
+    UnexpectedEof,
+    UnknownToken,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+#[error(code = 1, message = "unexpected end of file")]
+struct UnexpectedEof;
+
+fn main() {}
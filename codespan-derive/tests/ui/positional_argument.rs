@@ -0,0 +1,7 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+#[error(message = "unknown variable `{}`")]
+struct UnknownVariable(String);
+
+fn main() {}
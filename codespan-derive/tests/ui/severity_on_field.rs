@@ -0,0 +1,11 @@
+use codespan_derive::Diagnostic;
+use std::ops::Range;
+
+#[derive(Diagnostic)]
+#[error(message = "unexpected end of file")]
+struct UnexpectedEof {
+    #[error(message = "end of file")]
+    span: (usize, Range<usize>),
+}
+
+fn main() {}
@@ -0,0 +1,8 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+union Error {
+    code: u32,
+}
+
+fn main() {}
@@ -0,0 +1,7 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+#[error(message = "unexpected end of file", severity = "bug")]
+struct UnexpectedEof;
+
+fn main() {}
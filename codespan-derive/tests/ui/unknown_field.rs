@@ -0,0 +1,9 @@
+use codespan_derive::Diagnostic;
+
+#[derive(Diagnostic)]
+#[error(message = "unknown variable `{nmae}`")]
+struct UnknownVariable {
+    name: String,
+}
+
+fn main() {}
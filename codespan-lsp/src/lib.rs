@@ -4,11 +4,12 @@ use codespan::{
     ByteIndex, ByteOffset, ColumnIndex, FileId, Files, LineIndex, LineIndexOutOfBoundsError,
     LocationError, RawIndex, RawOffset, Span, SpanOutOfBoundsError,
 };
+use codespan_reporting::diagnostic::Tag;
 // WARNING: Be extremely careful when adding new imports here, as it could break
 // the compatible version range that we claim in our `Cargo.toml`. This could
 // potentially break down-stream builds on a `cargo update`. This is an
 // absolute no-no, breaking much of what we enjoy about Cargo!
-use lsp_types::{Position as LspPosition, Range as LspRange};
+use lsp_types::{DiagnosticTag, Position as LspPosition, Range as LspRange};
 use std::ffi::OsString;
 use std::path::PathBuf;
 use std::{error, fmt};
@@ -169,6 +170,14 @@ pub fn range_to_byte_span<Source: AsRef<str>>(
     ))
 }
 
+/// Convert a diagnostic tag into the equivalent LSP diagnostic tag.
+pub fn tag_to_diagnostic_tag(tag: Tag) -> DiagnosticTag {
+    match tag {
+        Tag::Unnecessary => DiagnosticTag::Unnecessary,
+        Tag::Deprecated => DiagnosticTag::Deprecated,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use codespan::Location;
@@ -0,0 +1,78 @@
+//! Compares building a table of line starts with `files::LineIndex` against
+//! collecting the line starts of a string with `match_indices`, which is how
+//! they were computed before `LineIndex` was added.
+//!
+//! To run this benchmark, with the size of the source in megabytes:
+//!
+//! ```sh
+//! cargo bench --package codespan-reporting --bench line_starts -- 256
+//! ```
+
+use codespan_reporting::files::LineIndex;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 5;
+
+/// Generate a source that looks like a log file, with lines of varying length.
+fn log_source(size: usize) -> String {
+    let mut source = String::with_capacity(size + 128);
+    let mut line = 0_usize;
+    while source.len() < size {
+        let padding = "-".repeat(line % 97);
+        source.push_str(&format!(
+            "2020-03-29T12:00:00Z INFO request {} took {}ms {}\n",
+            line,
+            line % 1000,
+            padding,
+        ));
+        line += 1;
+    }
+    source
+}
+
+fn match_indices_line_starts(source: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect()
+}
+
+/// Return the fastest time taken by the function.
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    (0..ITERATIONS)
+        .map(|_| {
+            let start = Instant::now();
+            let result = f();
+            let elapsed = start.elapsed();
+            drop(result);
+            elapsed
+        })
+        .min()
+        .unwrap()
+}
+
+fn report(name: &str, size: usize, elapsed: Duration) {
+    let megabytes = size as f64 / (1024.0 * 1024.0);
+    println!(
+        "{:<16} {:>10.2}ms {:>10.0}MiB/s",
+        name,
+        elapsed.as_secs_f64() * 1000.0,
+        megabytes / elapsed.as_secs_f64(),
+    );
+}
+
+fn main() {
+    let megabytes = std::env::args()
+        .skip(1)
+        .find_map(|arg| arg.parse::<usize>().ok())
+        .unwrap_or(64);
+    let source = log_source(megabytes * 1024 * 1024);
+
+    let line_count = LineIndex::new(&source).line_count(&source);
+    assert_eq!(line_count, match_indices_line_starts(&source).len());
+    println!("{} MiB, {} lines", megabytes, line_count);
+
+    let elapsed = time(|| match_indices_line_starts(&source));
+    report("match_indices", source.len(), elapsed);
+    let elapsed = time(|| LineIndex::new(&source));
+    report("LineIndex::new", source.len(), elapsed);
+}
@@ -0,0 +1,576 @@
+//! Baselines of existing diagnostics, for reporting only new diagnostics.
+//!
+//! When a new lint is adopted by a large codebase, it can be useful to
+//! accept the diagnostics that it currently reports, and to only report new
+//! diagnostics on later runs. A [`Baseline`] records the current diagnostics,
+//! and can be saved to a file and loaded again on later runs to filter out
+//! the diagnostics that it contains.
+//!
+//! Diagnostics are matched against a baseline by their code, the name of the
+//! file of their first primary label, their message, and the source code
+//! covered by their first primary label, rather than by byte offsets, so
+//! that the entries of a baseline still match after unrelated edits move the
+//! code around. A baseline can contain the same entry more than once, in
+//! which case it matches that many diagnostics.
+//!
+//! Each entry also records the [fingerprint] of its diagnostic, so that it
+//! can be related to the fingerprints shown in other outputs. Fingerprints
+//! are not used for matching, because they also change with the severity of
+//! a diagnostic and with edits elsewhere on the lines of its primary label.
+//!
+//! ```rust
+//! use codespan_reporting::baseline::Baseline;
+//! use codespan_reporting::diagnostic::{Diagnostic, Label};
+//! use codespan_reporting::files::SimpleFile;
+//!
+//! let unused_variable = |start| {
+//!     Diagnostic::warning()
+//!         .with_code("W0001")
+//!         .with_message("unused variable")
+//!         .with_labels(vec![Label::primary((), start..start + 1)])
+//! };
+//!
+//! // Record the diagnostics of the current code
+//! let file = SimpleFile::new("main.rs", "let x = 1;\n");
+//! let baseline = Baseline::from_diagnostics(&file, &[unused_variable(4)]);
+//! let saved = baseline.to_string();
+//!
+//! // Only report new diagnostics after the code has changed
+//! let file = SimpleFile::new("main.rs", "let w = 0;\nlet x = 1;\nlet y = 2;\n");
+//! let mut baseline = Baseline::parse(&saved)?;
+//! let diagnostics = baseline.filter(&file, vec![unused_variable(15), unused_variable(26)]);
+//!
+//! assert_eq!(diagnostics, [unused_variable(26)]);
+//! assert!(baseline.unmatched_entries().is_empty());
+//! # Ok::<(), codespan_reporting::baseline::ParseError>(())
+//! ```
+//!
+//! # File format
+//!
+//! Baselines are written with one entry per line, sorted so that they can be
+//! compared easily under version control. Each entry is made up of its code,
+//! file name, message, source code, and fingerprint, separated by tabs.
+//! Backslashes, tabs, line breaks, and any `#` at the start of an entry are
+//! escaped with a backslash, and an empty code means that the diagnostic has
+//! no code. Empty lines and lines that start with `#` are ignored. For
+//! example, the baseline above is written as a `# codespan baseline` comment,
+//! followed by the line
+//! `W0001\tmain.rs\tunused variable\tx\tae36cf0cc89093fc`, where each `\t`
+//! is a tab.
+//!
+//! [fingerprint]: crate::diagnostic::Diagnostic::fingerprint
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::diagnostic::{Diagnostic, Fingerprint, Label, LabelStyle};
+use crate::files::Files;
+
+/// The part of a diagnostic that is recorded in a baseline.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Entry {
+    /// The code of the diagnostic, if any.
+    pub code: Option<String>,
+    /// The name of the file of the first primary label, or an empty string if
+    /// the diagnostic has no primary labels.
+    pub file: String,
+    /// The message of the diagnostic.
+    pub message: String,
+    /// The source code covered by the first primary label, or an empty string
+    /// if the diagnostic has no primary labels.
+    pub source: String,
+    /// The fingerprint of the diagnostic. This is not used when matching
+    /// diagnostics against the baseline.
+    pub fingerprint: Fingerprint,
+}
+
+impl Entry {
+    /// Create the baseline entry for a diagnostic, looking up the name and
+    /// source code of the file of its first primary label.
+    pub fn new<'files, F>(files: &'files F, diagnostic: &Diagnostic<F::FileId>) -> Entry
+    where
+        F: Files<'files>,
+    {
+        let Key {
+            code,
+            file,
+            message,
+            source,
+        } = Key::new(files, diagnostic);
+
+        Entry {
+            code,
+            file,
+            message,
+            source,
+            fingerprint: diagnostic.fingerprint(files),
+        }
+    }
+
+    fn key(&self) -> Key {
+        Key {
+            code: self.code.clone(),
+            file: self.file.clone(),
+            message: self.message.clone(),
+            source: self.source.clone(),
+        }
+    }
+}
+
+/// The parts of an entry that diagnostics are matched by.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct Key {
+    code: Option<String>,
+    file: String,
+    message: String,
+    source: String,
+}
+
+impl Key {
+    fn new<'files, F>(files: &'files F, diagnostic: &Diagnostic<F::FileId>) -> Key
+    where
+        F: Files<'files>,
+    {
+        let (file, source) = match primary_label(diagnostic) {
+            Some(label) => {
+                let file =
+                    (files.name(label.file_id)).map_or(String::new(), |name| name.to_string());
+                let source = (files.source(label.file_id))
+                    .and_then(|source| {
+                        let source = source.as_ref().get(label.range.clone())?;
+                        Some(source.to_string())
+                    })
+                    .unwrap_or_default();
+                (file, source)
+            }
+            None => (String::new(), String::new()),
+        };
+
+        Key {
+            code: diagnostic.code.clone(),
+            file,
+            message: diagnostic.message.clone(),
+            source,
+        }
+    }
+}
+
+/// The entries that were recorded in a baseline with the same key, sorted by
+/// fingerprint, and the number of diagnostics that they have matched.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Entries {
+    entries: Vec<Entry>,
+    matched: usize,
+}
+
+/// A baseline of diagnostics that should not be reported.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Baseline {
+    entries: BTreeMap<Key, Entries>,
+}
+
+impl Baseline {
+    /// Create a new, empty baseline.
+    pub fn new() -> Baseline {
+        Baseline::default()
+    }
+
+    /// Create a baseline that contains some diagnostics.
+    pub fn from_diagnostics<'files, 'diagnostics, F>(
+        files: &'files F,
+        diagnostics: impl IntoIterator<Item = &'diagnostics Diagnostic<F::FileId>>,
+    ) -> Baseline
+    where
+        F: Files<'files>,
+        F::FileId: 'diagnostics,
+    {
+        let mut baseline = Baseline::new();
+        for diagnostic in diagnostics {
+            baseline.add(Entry::new(files, diagnostic));
+        }
+        baseline
+    }
+
+    /// Parse a baseline that was written with its [`Display`] implementation.
+    ///
+    /// [`Display`]: fmt::Display
+    pub fn parse(source: &str) -> Result<Baseline, ParseError> {
+        let mut baseline = Baseline::new();
+
+        for (line_index, line) in source.lines().enumerate() {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid_line = || ParseError::InvalidLine(line_index + 1, line.to_string());
+            let fields = (line.split('\t'))
+                .map(unescape)
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(invalid_line)?;
+            let (code, file, message, source, fingerprint) = match fields.as_slice() {
+                [code, file, message, source, fingerprint] => {
+                    (code, file, message, source, fingerprint)
+                }
+                _ => return Err(invalid_line()),
+            };
+            let fingerprint = fingerprint.parse().map_err(|_| invalid_line())?;
+
+            baseline.add(Entry {
+                code: if code.is_empty() {
+                    None
+                } else {
+                    Some(code.clone())
+                },
+                file: file.clone(),
+                message: message.clone(),
+                source: source.clone(),
+                fingerprint,
+            });
+        }
+
+        Ok(baseline)
+    }
+
+    /// Add an entry to the baseline.
+    pub fn add(&mut self, entry: Entry) {
+        let entries = &mut self.entries.entry(entry.key()).or_default().entries;
+        let index = match entries.binary_search(&entry) {
+            Ok(index) | Err(index) => index,
+        };
+        entries.insert(index, entry);
+    }
+
+    /// The number of entries in the baseline.
+    pub fn len(&self) -> usize {
+        (self.entries.values())
+            .map(|entries| entries.entries.len())
+            .sum()
+    }
+
+    /// Returns `true` if the baseline contains no entries.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries of the baseline, in sorted order.
+    pub fn entries(&self) -> impl Iterator<Item = &Entry> {
+        (self.entries.values()).flat_map(|entries| entries.entries.iter())
+    }
+
+    /// Returns `true` if the diagnostic is in the baseline, marking one of its
+    /// entries as matched. Each entry only matches a single diagnostic.
+    pub fn is_baselined<'files, F>(
+        &mut self,
+        files: &'files F,
+        diagnostic: &Diagnostic<F::FileId>,
+    ) -> bool
+    where
+        F: Files<'files>,
+    {
+        match self.entries.get_mut(&Key::new(files, diagnostic)) {
+            Some(entries) if entries.matched < entries.entries.len() => {
+                entries.matched += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Remove the diagnostics that are in the baseline, marking the entries
+    /// that match them as matched.
+    pub fn filter<'files, F>(
+        &mut self,
+        files: &'files F,
+        diagnostics: impl IntoIterator<Item = Diagnostic<F::FileId>>,
+    ) -> Vec<Diagnostic<F::FileId>>
+    where
+        F: Files<'files>,
+    {
+        (diagnostics.into_iter())
+            .filter(|diagnostic| !self.is_baselined(files, diagnostic))
+            .collect()
+    }
+
+    /// The entries that have not matched any diagnostics, usually because the
+    /// diagnostics have been fixed. This should be called after all
+    /// diagnostics have been filtered, and can be used to keep the baseline
+    /// up to date.
+    pub fn unmatched_entries(&self) -> Vec<&Entry> {
+        (self.entries.values())
+            .flat_map(|entries| &entries.entries[entries.matched..])
+            .collect()
+    }
+}
+
+impl fmt::Display for Baseline {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "# codespan baseline")?;
+        for entry in self.entries() {
+            let code = entry.code.as_ref().map_or("", String::as_str);
+            if code.starts_with('#') {
+                write!(f, "\\")?;
+            }
+            writeln!(
+                f,
+                "{}\t{}\t{}\t{}\t{}",
+                Escaped(code),
+                Escaped(&entry.file),
+                Escaped(&entry.message),
+                Escaped(&entry.source),
+                entry.fingerprint,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A field of an entry, written with its special characters escaped.
+struct Escaped<'a>(&'a str);
+
+impl fmt::Display for Escaped<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for ch in self.0.chars() {
+            match ch {
+                '\\' => write!(f, "\\\\")?,
+                '\t' => write!(f, "\\t")?,
+                '\n' => write!(f, "\\n")?,
+                '\r' => write!(f, "\\r")?,
+                _ => write!(f, "{}", ch)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Unescape a field of an entry, returning `None` if it contains an invalid
+/// escape sequence.
+fn unescape(field: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(ch) = chars.next() {
+        unescaped.push(match ch {
+            '\\' => match chars.next()? {
+                '\\' => '\\',
+                't' => '\t',
+                'n' => '\n',
+                'r' => '\r',
+                '#' => '#',
+                _ => return None,
+            },
+            ch => ch,
+        });
+    }
+    Some(unescaped)
+}
+
+fn primary_label<FileId>(diagnostic: &Diagnostic<FileId>) -> Option<&Label<FileId>> {
+    (diagnostic.labels.iter()).find(|label| label.style == LabelStyle::Primary)
+}
+
+/// An error produced when parsing a baseline.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// A line did not contain five fields separated by tabs, contained an
+    /// invalid escape sequence, or contained an invalid fingerprint. The line
+    /// number starts at `1`.
+    InvalidLine(usize, String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidLine(line_number, line) => {
+                write!(
+                    f,
+                    "invalid baseline entry on line {}: `{}`",
+                    line_number, line
+                )
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::diagnostic::Severity;
+    use crate::files::SimpleFiles;
+    use alloc::vec;
+
+    fn warning(file_id: usize, code: &str, range: core::ops::Range<usize>) -> Diagnostic<usize> {
+        Diagnostic::warning()
+            .with_code(code)
+            .with_message("unused variable")
+            .with_labels(vec![Label::primary(file_id, range)])
+    }
+
+    #[test]
+    fn entries() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("main.rs", "let x = 1;\n");
+        let diagnostic = warning(file_id, "W0001", 4..5).with_labels(vec![
+            Label::secondary(file_id, 0..3),
+            Label::primary(file_id, 4..5),
+            Label::primary(file_id, 8..9),
+        ]);
+
+        assert_eq!(
+            Entry::new(&files, &diagnostic),
+            Entry {
+                code: Some("W0001".to_string()),
+                file: "main.rs".to_string(),
+                message: "unused variable".to_string(),
+                source: "x".to_string(),
+                fingerprint: diagnostic.fingerprint(&files),
+            },
+        );
+        let diagnostic = Diagnostic::error().with_message("no input");
+        assert_eq!(
+            Entry::new(&files, &diagnostic),
+            Entry {
+                code: None,
+                file: String::new(),
+                message: "no input".to_string(),
+                source: String::new(),
+                fingerprint: diagnostic.fingerprint(&files),
+            },
+        );
+    }
+
+    #[test]
+    fn matches_moved_diagnostics() {
+        let mut files = SimpleFiles::new();
+        let old_id = files.add("main.rs", "let x = 1;\nlet y = 2;\n");
+        let new_id = files.add("main.rs", "let y = 2;\n\nlet x = 1;\nlet z = 3;\n");
+
+        let mut baseline = Baseline::from_diagnostics(
+            &files,
+            &[
+                warning(old_id, "W0001", 4..5),
+                warning(old_id, "W0001", 15..16),
+            ],
+        );
+        let diagnostics = vec![
+            warning(new_id, "W0001", 4..5),
+            warning(new_id, "W0001", 16..17),
+            warning(new_id, "W0001", 27..28),
+            warning(new_id, "W0002", 27..28),
+        ];
+        let remaining = baseline.filter(&files, diagnostics.clone());
+
+        assert_eq!(remaining, [diagnostics[2].clone(), diagnostics[3].clone()]);
+        assert!(baseline.unmatched_entries().is_empty());
+    }
+
+    #[test]
+    fn matches_changed_fingerprints() {
+        let mut files = SimpleFiles::new();
+        let old_id = files.add("main.rs", "let x = 1;\n");
+        let new_id = files.add("main.rs", "let x = 2;\n");
+
+        let mut baseline = Baseline::from_diagnostics(
+            &files,
+            &[
+                warning(old_id, "W0001", 4..5),
+                warning(old_id, "W0002", 4..5),
+            ],
+        );
+        let mut promoted = warning(old_id, "W0001", 4..5);
+        promoted.severity = Severity::Error;
+        let edited = warning(new_id, "W0002", 4..5);
+
+        // Fingerprints change with the severity and the rest of the line, but
+        // diagnostics are still matched by their source code
+        let fingerprint = |diagnostic: &Diagnostic<usize>| diagnostic.fingerprint(&files);
+        assert_ne!(
+            fingerprint(&promoted),
+            fingerprint(&warning(old_id, "W0001", 4..5))
+        );
+        assert_ne!(
+            fingerprint(&edited),
+            fingerprint(&warning(old_id, "W0002", 4..5))
+        );
+        assert!(baseline.is_baselined(&files, &promoted));
+        assert!(baseline.is_baselined(&files, &edited));
+        assert!(baseline.unmatched_entries().is_empty());
+    }
+
+    #[test]
+    fn repeated_entries() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("main.rs", "x + x + x");
+        let diagnostics = vec![
+            warning(file_id, "W0001", 0..1),
+            warning(file_id, "W0001", 4..5),
+        ];
+        let mut baseline = Baseline::from_diagnostics(&files, &diagnostics);
+
+        assert_eq!(baseline.len(), 2);
+        assert_eq!(baseline.entries().count(), 2);
+
+        assert!(baseline.is_baselined(&files, &warning(file_id, "W0001", 8..9)));
+        assert_eq!(baseline.unmatched_entries().len(), 1);
+        assert!(baseline.is_baselined(&files, &warning(file_id, "W0001", 4..5)));
+        assert!(!baseline.is_baselined(&files, &warning(file_id, "W0001", 0..1)));
+        assert!(baseline.unmatched_entries().is_empty());
+    }
+
+    #[test]
+    fn round_trips() {
+        let mut baseline = Baseline::new();
+        baseline.add(Entry {
+            code: Some("#1".to_string()),
+            file: "src/a b.rs".to_string(),
+            message: "tab\tnewline\nbackslash\\".to_string(),
+            source: "\r\n".to_string(),
+            fingerprint: Fingerprint(0xc0ffee0ddba115),
+        });
+        baseline.add(Entry {
+            code: None,
+            file: String::new(),
+            message: "no labels".to_string(),
+            source: String::new(),
+            fingerprint: Fingerprint(1),
+        });
+        let source = baseline.to_string();
+
+        assert_eq!(
+            source,
+            "# codespan baseline\n\
+             \t\tno labels\t\t0000000000000001\n\
+             \\#1\tsrc/a b.rs\ttab\\tnewline\\nbackslash\\\\\t\\r\\n\t00c0ffee0ddba115\n",
+        );
+        assert_eq!(Baseline::parse(&source), Ok(baseline));
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Baseline::parse("# codespan baseline\nW0001\tmain.rs\tunused\n"),
+            Err(ParseError::InvalidLine(
+                2,
+                "W0001\tmain.rs\tunused".to_string(),
+            )),
+        );
+        assert_eq!(
+            Baseline::parse("W0001\tmain.rs\tunused\t\\x\t0000000000000001"),
+            Err(ParseError::InvalidLine(
+                1,
+                "W0001\tmain.rs\tunused\t\\x\t0000000000000001".to_string(),
+            )),
+        );
+        assert_eq!(
+            Baseline::parse("W0001\tmain.rs\tunused\tx\ty"),
+            Err(ParseError::InvalidLine(
+                1,
+                "W0001\tmain.rs\tunused\tx\ty".to_string(),
+            )),
+        );
+    }
+}
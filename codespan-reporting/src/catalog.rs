@@ -0,0 +1,431 @@
+//! Message catalogs, for localizing diagnostics.
+//!
+//! Instead of writing messages in a single language at the call site, the
+//! messages of a diagnostic can be written as message identifiers, with the
+//! values that they refer to added to the diagnostic as named arguments. The
+//! messages are then resolved through a [`Catalog`] when the diagnostic is
+//! [localized], usually just before it is emitted.
+//!
+//! Messages are written as patterns, where `{$name}` is replaced with the
+//! value of the argument called `name`. A [`Bundle`] holds the patterns of a
+//! single locale, and can be parsed from a simple format similar to
+//! [Fluent]:
+//!
+//! ```text
+//! # Comments start with `#`
+//! type-mismatch = mismatched types
+//! expected-found = expected `{$expected}`, found `{$found}`
+//! multi-line =
+//!     Indented lines are continuation lines,
+//!     and are joined with line breaks.
+//! ```
+//!
+//! A slice of catalogs is also a catalog, which looks up messages in each of
+//! its catalogs in order. This can be used to fall back to other locales when
+//! a message has not been translated.
+//!
+//! ```rust
+//! use codespan_reporting::catalog::{self, Bundle};
+//! use codespan_reporting::diagnostic::{Diagnostic, Label};
+//!
+//! let de = Bundle::parse("de", "type-mismatch = Typen stimmen nicht überein")?;
+//! let en = Bundle::parse("en", r#"
+//!     type-mismatch = mismatched types
+//!     expected-found = expected `{$expected}`, found `{$found}`
+//! "#)?;
+//! let catalogs = [de, en];
+//!
+//! let diagnostic = Diagnostic::error()
+//!     .with_message("type-mismatch")
+//!     .with_labels(vec![Label::primary((), 8..15).with_message("expected-found")])
+//!     .with_args(vec![("expected", "Int"), ("found", "String")]);
+//! let diagnostic = catalog::localize(&catalogs[..], diagnostic);
+//!
+//! assert_eq!(diagnostic.message, "Typen stimmen nicht überein");
+//! assert_eq!(diagnostic.labels[0].message, "expected `Int`, found `String`");
+//! # Ok::<(), codespan_reporting::catalog::ParseError>(())
+//! ```
+//!
+//! The names of severities used by the terminal renderer can be localized
+//! with [`term::SeverityNames::from_catalog`], which looks up the messages
+//! with the identifiers `severity-bug`, `severity-error`, `severity-warning`,
+//! `severity-note`, and `severity-help`.
+//!
+//! [localized]: localize
+//! [Fluent]: https://projectfluent.org/
+//! [`term::SeverityNames::from_catalog`]: crate::term::SeverityNames::from_catalog
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::diagnostic::Diagnostic;
+
+/// A source of message patterns, looked up by their identifiers.
+pub trait Catalog {
+    /// Get the pattern of a message, if the catalog contains it.
+    fn pattern(&self, id: &str) -> Option<&str>;
+
+    /// Format a message with some named arguments, if the catalog contains it.
+    fn format(&self, id: &str, args: &[(String, String)]) -> Option<String> {
+        self.pattern(id)
+            .map(|pattern| format_pattern(pattern, args))
+    }
+}
+
+impl<C: Catalog + ?Sized> Catalog for &C {
+    fn pattern(&self, id: &str) -> Option<&str> {
+        (**self).pattern(id)
+    }
+}
+
+impl<C: Catalog> Catalog for [C] {
+    fn pattern(&self, id: &str) -> Option<&str> {
+        self.iter().find_map(|catalog| catalog.pattern(id))
+    }
+}
+
+impl<C: Catalog> Catalog for Vec<C> {
+    fn pattern(&self, id: &str) -> Option<&str> {
+        self[..].pattern(id)
+    }
+}
+
+/// The message patterns of a single locale.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Bundle {
+    locale: String,
+    patterns: BTreeMap<String, String>,
+}
+
+impl Bundle {
+    /// Create a new, empty bundle for a locale, for example `en-US`.
+    pub fn new(locale: impl Into<String>) -> Bundle {
+        Bundle {
+            locale: locale.into(),
+            patterns: BTreeMap::new(),
+        }
+    }
+
+    /// Parse a bundle for a locale from its source.
+    ///
+    /// Each message is written on its own line, as `id = pattern`. Lines that
+    /// are indented further than the messages continue the pattern of the
+    /// previous message, and empty lines and lines that start with `#` are
+    /// ignored.
+    pub fn parse(locale: impl Into<String>, source: &str) -> Result<Bundle, ParseError> {
+        let mut bundle = Bundle::new(locale);
+        let mut first_indent = None;
+        let mut current: Option<(String, String)> = None;
+
+        for (line_index, line) in source.lines().enumerate() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            let invalid_line = || ParseError::InvalidLine(line_index + 1, trimmed.to_string());
+            let indent = line.len() - line.trim_start().len();
+            let message_indent = *first_indent.get_or_insert(indent);
+
+            // Continue the pattern of the previous message
+            if indent > message_indent {
+                let (_, pattern) = current.as_mut().ok_or_else(invalid_line)?;
+                if !pattern.is_empty() {
+                    pattern.push('\n');
+                }
+                pattern.push_str(trimmed);
+                continue;
+            }
+
+            let equals = trimmed.find('=').ok_or_else(invalid_line)?;
+            let id = trimmed[..equals].trim();
+            if indent < message_indent || !is_valid_id(id) {
+                return Err(invalid_line());
+            }
+
+            if let Some((id, pattern)) = current.take() {
+                bundle.add(id, pattern);
+            }
+            if bundle.contains(id) {
+                return Err(ParseError::DuplicateMessage(line_index + 1, id.to_string()));
+            }
+            current = Some((id.to_string(), trimmed[equals + 1..].trim().to_string()));
+        }
+
+        if let Some((id, pattern)) = current {
+            bundle.add(id, pattern);
+        }
+
+        Ok(bundle)
+    }
+
+    /// The locale of the bundle.
+    pub fn locale(&self) -> &str {
+        &self.locale
+    }
+
+    /// Add a message to the bundle, returning the pattern of any message that
+    /// it replaces.
+    pub fn add(&mut self, id: impl Into<String>, pattern: impl Into<String>) -> Option<String> {
+        self.patterns.insert(id.into(), pattern.into())
+    }
+
+    /// Add a message to the bundle.
+    pub fn with_message(mut self, id: impl Into<String>, pattern: impl Into<String>) -> Bundle {
+        self.add(id, pattern);
+        self
+    }
+
+    /// Returns `true` if the bundle contains a message.
+    pub fn contains(&self, id: &str) -> bool {
+        self.patterns.contains_key(id)
+    }
+}
+
+impl Catalog for Bundle {
+    fn pattern(&self, id: &str) -> Option<&str> {
+        self.patterns.get(id).map(String::as_str)
+    }
+}
+
+fn is_valid_id(id: &str) -> bool {
+    let mut chars = id.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() => {}
+        _ => return false,
+    }
+    chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Format a pattern, replacing each `{$name}` placeholder with the value of
+/// the argument with that name. Placeholders that do not refer to any of the
+/// arguments are written as-is.
+///
+/// ```rust
+/// use codespan_reporting::catalog;
+///
+/// let args = vec![("count".to_owned(), "3".to_owned())];
+///
+/// assert_eq!(catalog::format_pattern("{$count} errors", &args), "3 errors");
+/// assert_eq!(catalog::format_pattern("{$other} errors", &args), "{$other} errors");
+/// ```
+pub fn format_pattern(pattern: &str, args: &[(String, String)]) -> String {
+    let mut formatted = String::with_capacity(pattern.len());
+    let mut pattern = pattern;
+
+    while let Some(start) = pattern.find("{$") {
+        let placeholder = &pattern[start..];
+        let (len, value) = match placeholder.find('}') {
+            Some(end) => {
+                let name = placeholder[2..end].trim();
+                let value = args.iter().find(|(arg, _)| arg == name);
+                (end + 1, value.map(|(_, value)| value.as_str()))
+            }
+            None => (placeholder.len(), None),
+        };
+
+        formatted.push_str(&pattern[..start]);
+        formatted.push_str(value.unwrap_or(&placeholder[..len]));
+        pattern = &placeholder[len..];
+    }
+    formatted.push_str(pattern);
+
+    formatted
+}
+
+/// Localize a diagnostic, replacing each of the messages of the diagnostic
+/// that are found in the catalog with their patterns, formatted with the
+/// arguments of the diagnostic. This includes the messages of labels, notes,
+/// sub-diagnostics, and suggestions, along with the custom titles of notes.
+///
+/// Messages that are not found in the catalog are left as-is, so message
+/// identifiers can be introduced gradually.
+pub fn localize<C, FileId>(catalog: &C, mut diagnostic: Diagnostic<FileId>) -> Diagnostic<FileId>
+where
+    C: Catalog + ?Sized,
+{
+    let args = &diagnostic.args;
+    let localize = |message: &mut String| {
+        if let Some(localized) = catalog.format(message, args) {
+            *message = localized;
+        }
+    };
+
+    localize(&mut diagnostic.message);
+    for label in &mut diagnostic.labels {
+        localize(&mut label.message);
+    }
+    for note in &mut diagnostic.notes {
+        if let Some(title) = &mut note.title {
+            localize(title);
+        }
+        localize(&mut note.message);
+    }
+    for child in &mut diagnostic.children {
+        localize(&mut child.message);
+        for label in &mut child.labels {
+            localize(&mut label.message);
+        }
+    }
+    for suggestion in &mut diagnostic.suggestions {
+        localize(&mut suggestion.message);
+    }
+
+    diagnostic
+}
+
+/// An error that occurred while parsing a [`Bundle`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// A line was not of the form `id = pattern`, was indented less than the
+    /// other messages, or was a continuation line that did not follow a
+    /// message. The line number starts at `1`.
+    InvalidLine(usize, String),
+    /// A message was defined more than once. The line number starts at `1`.
+    DuplicateMessage(usize, String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::InvalidLine(line_number, line) => {
+                write!(f, "invalid message on line {}: `{}`", line_number, line)
+            }
+            ParseError::DuplicateMessage(line_number, id) => write!(
+                f,
+                "message `{}` on line {} was already defined",
+                id, line_number,
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec;
+
+    use crate::diagnostic::{Applicability, Label, Note, SubDiagnostic, Suggestion};
+
+    #[test]
+    fn parse_bundle() {
+        let bundle = Bundle::parse(
+            "en",
+            "
+            # Types
+            type-mismatch = mismatched types
+
+            expected-found =
+                expected `{$expected}`,
+                # Comments are ignored in continuation lines too
+                found `{$found}`
+            empty =
+            ",
+        )
+        .unwrap();
+
+        assert_eq!(bundle.locale(), "en");
+        assert_eq!(bundle.pattern("type-mismatch"), Some("mismatched types"));
+        assert_eq!(
+            bundle.pattern("expected-found"),
+            Some("expected `{$expected}`,\nfound `{$found}`"),
+        );
+        assert_eq!(bundle.pattern("empty"), Some(""));
+        assert_eq!(bundle.pattern("missing"), None);
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            Bundle::parse("en", "  a = b\nc = d"),
+            Err(ParseError::InvalidLine(2, "c = d".to_string())),
+        );
+        assert_eq!(
+            Bundle::parse("en", "  continued\na = b"),
+            Err(ParseError::InvalidLine(1, "continued".to_string())),
+        );
+        assert_eq!(
+            Bundle::parse("en", "a = b\n1a = c"),
+            Err(ParseError::InvalidLine(2, "1a = c".to_string())),
+        );
+        assert_eq!(
+            Bundle::parse("en", "a = b\nb = c\na = d"),
+            Err(ParseError::DuplicateMessage(3, "a".to_string())),
+        );
+        assert_eq!(
+            ParseError::DuplicateMessage(3, "a".to_string()).to_string(),
+            "message `a` on line 3 was already defined",
+        );
+    }
+
+    #[test]
+    fn format_placeholders() {
+        let args = vec![
+            ("name".to_string(), "x".to_string()),
+            ("type".to_string(), "{$name}".to_string()),
+        ];
+
+        assert_eq!(format_pattern("`{$name}`: {$type}", &args), "`x`: {$name}");
+        assert_eq!(
+            format_pattern("{ $name } {$other}", &args),
+            "{ $name } {$other}"
+        );
+        assert_eq!(format_pattern("{$ name } {$name", &args), "x {$name");
+    }
+
+    #[test]
+    fn fallback_locales() {
+        let catalogs = vec![
+            Bundle::new("de").with_message("a", "A (de)"),
+            Bundle::new("en")
+                .with_message("a", "A (en)")
+                .with_message("b", "B (en)"),
+        ];
+
+        assert_eq!(catalogs.pattern("a"), Some("A (de)"));
+        assert_eq!(catalogs.pattern("b"), Some("B (en)"));
+        assert_eq!(catalogs.pattern("c"), None);
+    }
+
+    #[test]
+    fn localize_all_messages() {
+        let bundle = Bundle::new("en")
+            .with_message("message", "message {$n}")
+            .with_message("label", "label {$n}")
+            .with_message("title", "title {$n}")
+            .with_message("note", "note {$n}")
+            .with_message("child", "child {$n}")
+            .with_message("suggestion", "suggestion {$n}");
+
+        let diagnostic = Diagnostic::error()
+            .with_message("message")
+            .with_labels(vec![
+                Label::primary((), 0..1).with_message("label"),
+                Label::secondary((), 0..1).with_message("not an id"),
+            ])
+            .with_typed_notes(vec![Note::note("note").with_title("title")])
+            .with_children(vec![SubDiagnostic::note()
+                .with_message("child")
+                .with_labels(vec![Label::primary((), 0..1).with_message("label")])])
+            .with_suggestions(vec![
+                Suggestion::new(Applicability::MachineApplicable).with_message("suggestion")
+            ])
+            .with_args(vec![("n", "1")]);
+        let diagnostic = localize(&bundle, diagnostic);
+
+        assert_eq!(diagnostic.message, "message 1");
+        assert_eq!(diagnostic.labels[0].message, "label 1");
+        assert_eq!(diagnostic.labels[1].message, "not an id");
+        assert_eq!(diagnostic.notes[0].title.as_deref(), Some("title 1"));
+        assert_eq!(diagnostic.notes[0].message, "note 1");
+        assert_eq!(diagnostic.children[0].message, "child 1");
+        assert_eq!(diagnostic.children[0].labels[0].message, "label 1");
+        assert_eq!(diagnostic.suggestions[0].message, "suggestion 1");
+    }
+}
@@ -1,8 +1,16 @@
 //! Diagnostic data structures.
 
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::num::ParseIntError;
+use core::ops::Range;
+use core::str::FromStr;
 #[cfg(feature = "serialization")]
 use serde::{Deserialize, Serialize};
-use std::ops::Range;
+
+use crate::files::Files;
+use crate::markup;
 
 /// A severity level for diagnostic messages.
 ///
@@ -45,7 +53,7 @@ impl Severity {
 }
 
 impl PartialOrd for Severity {
-    fn partial_cmp(&self, other: &Severity) -> Option<std::cmp::Ordering> {
+    fn partial_cmp(&self, other: &Severity) -> Option<core::cmp::Ordering> {
         u8::partial_cmp(&self.to_cmp_int(), &other.to_cmp_int())
     }
 }
@@ -110,6 +118,328 @@ impl<FileId> Label<FileId> {
     }
 }
 
+/// A span of source code in a file, that can be pointed to by a label.
+///
+/// This is used by the labels generated by `#[derive(Diagnostic)]` in the
+/// `codespan-derive` crate, and is implemented for pairs of a file id and a
+/// byte range.
+pub trait LabelSpan {
+    /// The type used to identify the file that the span is in.
+    type FileId;
+
+    /// The file that the span is in.
+    fn file_id(&self) -> Self::FileId;
+
+    /// The byte range of the span in the file.
+    fn range(&self) -> Range<usize>;
+}
+
+impl<FileId: Clone> LabelSpan for (FileId, Range<usize>) {
+    type FileId = FileId;
+
+    fn file_id(&self) -> FileId {
+        self.0.clone()
+    }
+
+    fn range(&self) -> Range<usize> {
+        self.1.clone()
+    }
+}
+
+impl<T: LabelSpan + ?Sized> LabelSpan for &T {
+    type FileId = T::FileId;
+
+    fn file_id(&self) -> T::FileId {
+        T::file_id(self)
+    }
+
+    fn range(&self) -> Range<usize> {
+        T::range(self)
+    }
+}
+
+/// The kind of a note, which determines how its title is rendered.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum NoteKind {
+    /// Plain text, with no title unless one is given.
+    Plain,
+    /// Additional information about the diagnostic, titled `note` by default.
+    Note,
+    /// Advice on how to resolve the diagnostic, titled `help` by default.
+    Help,
+}
+
+impl NoteKind {
+    /// The title used for notes of this kind that do not provide their own.
+    pub fn default_title(self) -> Option<&'static str> {
+        match self {
+            NoteKind::Plain => None,
+            NoteKind::Note => Some("note"),
+            NoteKind::Help => Some("help"),
+        }
+    }
+}
+
+/// A note that is associated with a diagnostic.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Note {
+    /// The kind of the note.
+    pub kind: NoteKind,
+    /// An optional title to use in place of the default title of the kind.
+    pub title: Option<String>,
+    /// The message of the note. This can include line breaks for improved
+    /// formatting.
+    pub message: String,
+}
+
+impl Note {
+    /// Create a new note.
+    pub fn new(kind: NoteKind, message: impl Into<String>) -> Note {
+        Note {
+            kind,
+            title: None,
+            message: message.into(),
+        }
+    }
+
+    /// Create a new note with a kind of [`NoteKind::Plain`].
+    ///
+    /// [`NoteKind::Plain`]: NoteKind::Plain
+    pub fn plain(message: impl Into<String>) -> Note {
+        Note::new(NoteKind::Plain, message)
+    }
+
+    /// Create a new note with a kind of [`NoteKind::Note`].
+    ///
+    /// [`NoteKind::Note`]: NoteKind::Note
+    #[allow(clippy::self_named_constructors)]
+    pub fn note(message: impl Into<String>) -> Note {
+        Note::new(NoteKind::Note, message)
+    }
+
+    /// Create a new note with a kind of [`NoteKind::Help`].
+    ///
+    /// [`NoteKind::Help`]: NoteKind::Help
+    pub fn help(message: impl Into<String>) -> Note {
+        Note::new(NoteKind::Help, message)
+    }
+
+    /// Add a title to the note.
+    pub fn with_title(mut self, title: impl Into<String>) -> Note {
+        self.title = Some(title.into());
+        self
+    }
+
+    /// The title to render before the message, if any.
+    pub fn title(&self) -> Option<&str> {
+        match &self.title {
+            Some(title) => Some(title.as_str()),
+            None => self.kind.default_title(),
+        }
+    }
+}
+
+impl From<String> for Note {
+    fn from(message: String) -> Note {
+        Note::plain(message)
+    }
+}
+
+impl<'a> From<&'a str> for Note {
+    fn from(message: &'a str) -> Note {
+        Note::plain(message)
+    }
+}
+
+/// How confident we are that a suggestion is what the user intended, and so
+/// whether it can be applied automatically by tools.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum Applicability {
+    /// The suggestion is definitely what the user intended, and can be
+    /// applied automatically.
+    MachineApplicable,
+    /// The suggestion may be what the user intended, but it is uncertain, so
+    /// it should be reviewed before it is applied.
+    MaybeIncorrect,
+    /// The suggestion contains placeholders like `(...)` or `{ /* fields */ }`
+    /// that must be filled in by the user before it is applied.
+    HasPlaceholders,
+}
+
+impl Applicability {
+    /// Returns `true` if the suggestion can be applied automatically.
+    pub fn is_machine_applicable(self) -> bool {
+        self == Applicability::MachineApplicable
+    }
+}
+
+/// A replacement of a range of source code with some new text.
+///
+/// Insertions are represented with an empty range, and deletions with an
+/// empty replacement.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Edit<FileId> {
+    /// The file that we are editing.
+    pub file_id: FileId,
+    /// The range of source code to replace.
+    pub range: Range<usize>,
+    /// The text to replace the range with.
+    pub replacement: String,
+}
+
+impl<FileId> Edit<FileId> {
+    /// Create a new edit.
+    pub fn new(
+        file_id: FileId,
+        range: impl Into<Range<usize>>,
+        replacement: impl Into<String>,
+    ) -> Edit<FileId> {
+        Edit {
+            file_id,
+            range: range.into(),
+            replacement: replacement.into(),
+        }
+    }
+}
+
+/// A suggested fix for a diagnostic, made up of edits to the source code.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct Suggestion<FileId> {
+    /// How confident we are in the suggestion.
+    pub applicability: Applicability,
+    /// A message describing the suggestion. These should not include line
+    /// breaks.
+    pub message: String,
+    /// The edits to make to the source code. These should not overlap.
+    pub edits: Vec<Edit<FileId>>,
+}
+
+impl<FileId> Suggestion<FileId> {
+    /// Create a new suggestion.
+    pub fn new(applicability: Applicability) -> Suggestion<FileId> {
+        Suggestion {
+            applicability,
+            message: String::new(),
+            edits: Vec::new(),
+        }
+    }
+
+    /// Create a new suggestion with an applicability of
+    /// [`Applicability::MachineApplicable`].
+    ///
+    /// [`Applicability::MachineApplicable`]: Applicability::MachineApplicable
+    pub fn machine_applicable() -> Suggestion<FileId> {
+        Suggestion::new(Applicability::MachineApplicable)
+    }
+
+    /// Create a new suggestion with an applicability of
+    /// [`Applicability::MaybeIncorrect`].
+    ///
+    /// [`Applicability::MaybeIncorrect`]: Applicability::MaybeIncorrect
+    pub fn maybe_incorrect() -> Suggestion<FileId> {
+        Suggestion::new(Applicability::MaybeIncorrect)
+    }
+
+    /// Create a new suggestion with an applicability of
+    /// [`Applicability::HasPlaceholders`].
+    ///
+    /// [`Applicability::HasPlaceholders`]: Applicability::HasPlaceholders
+    pub fn has_placeholders() -> Suggestion<FileId> {
+        Suggestion::new(Applicability::HasPlaceholders)
+    }
+
+    /// Add a message to the suggestion.
+    pub fn with_message(mut self, message: impl Into<String>) -> Suggestion<FileId> {
+        self.message = message.into();
+        self
+    }
+
+    /// Add some edits to the suggestion.
+    pub fn with_edits(mut self, edits: Vec<Edit<FileId>>) -> Suggestion<FileId> {
+        self.edits = edits;
+        self
+    }
+}
+
+/// A sub-diagnostic that is attached to a [`Diagnostic`], providing additional
+/// context with its own message and source labels. For example, a sub-diagnostic
+/// could point out the previous definition of a duplicated item.
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub struct SubDiagnostic<FileId> {
+    /// The severity of the sub-diagnostic. This is usually one of
+    /// [`Severity::Note`], [`Severity::Help`], or [`Severity::Warning`].
+    ///
+    /// [`Severity::Note`]: Severity::Note
+    /// [`Severity::Help`]: Severity::Help
+    /// [`Severity::Warning`]: Severity::Warning
+    pub severity: Severity,
+    /// The message associated with this sub-diagnostic.
+    pub message: String,
+    /// Source labels that the sub-diagnostic refers to.
+    pub labels: Vec<Label<FileId>>,
+}
+
+impl<FileId> SubDiagnostic<FileId> {
+    /// Create a new sub-diagnostic.
+    pub fn new(severity: Severity) -> SubDiagnostic<FileId> {
+        SubDiagnostic {
+            severity,
+            message: String::new(),
+            labels: Vec::new(),
+        }
+    }
+
+    /// Create a new sub-diagnostic with a severity of [`Severity::Warning`].
+    ///
+    /// [`Severity::Warning`]: Severity::Warning
+    pub fn warning() -> SubDiagnostic<FileId> {
+        SubDiagnostic::new(Severity::Warning)
+    }
+
+    /// Create a new sub-diagnostic with a severity of [`Severity::Note`].
+    ///
+    /// [`Severity::Note`]: Severity::Note
+    pub fn note() -> SubDiagnostic<FileId> {
+        SubDiagnostic::new(Severity::Note)
+    }
+
+    /// Create a new sub-diagnostic with a severity of [`Severity::Help`].
+    ///
+    /// [`Severity::Help`]: Severity::Help
+    pub fn help() -> SubDiagnostic<FileId> {
+        SubDiagnostic::new(Severity::Help)
+    }
+
+    /// Add a message to the sub-diagnostic.
+    pub fn with_message(mut self, message: impl Into<String>) -> SubDiagnostic<FileId> {
+        self.message = message.into();
+        self
+    }
+
+    /// Add some labels to the sub-diagnostic.
+    pub fn with_labels(mut self, labels: Vec<Label<FileId>>) -> SubDiagnostic<FileId> {
+        self.labels = labels;
+        self
+    }
+}
+
+/// Extra information about the code that a diagnostic applies to, which
+/// editors and terminals can use to change how the code is displayed.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum Tag {
+    /// Unused or unnecessary code, which is usually displayed faded out.
+    Unnecessary,
+    /// Deprecated or obsolete code, which is usually displayed struck through.
+    Deprecated,
+}
+
 /// Represents a diagnostic message that can provide information like errors and
 /// warnings to the user.
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
@@ -128,8 +458,24 @@ pub struct Diagnostic<FileId> {
     /// Source labels that describe the cause of the diagnostic.
     pub labels: Vec<Label<FileId>>,
     /// Notes that are associated with the primary cause of the diagnostic.
-    /// These can include line breaks for improved formatting.
-    pub notes: Vec<String>,
+    pub notes: Vec<Note>,
+    /// Sub-diagnostics that provide additional context, each with their own
+    /// message and source labels.
+    pub children: Vec<SubDiagnostic<FileId>>,
+    /// Suggested fixes for the diagnostic.
+    pub suggestions: Vec<Suggestion<FileId>>,
+    /// Tags describing the code covered by the primary labels.
+    pub tags: Vec<Tag>,
+    /// Named arguments, used to format the messages of the diagnostic when
+    /// it is localized with a [`Catalog`].
+    ///
+    /// [`Catalog`]: crate::catalog::Catalog
+    pub args: Vec<(String, String)>,
+    /// Whether the messages of the diagnostic, and of its labels, notes, and
+    /// sub-diagnostics, are written in [`markup`].
+    ///
+    /// [`markup`]: crate::markup
+    pub markup: bool,
 }
 
 impl<FileId> Diagnostic<FileId> {
@@ -141,6 +487,11 @@ impl<FileId> Diagnostic<FileId> {
             message: String::new(),
             labels: Vec::new(),
             notes: Vec::new(),
+            children: Vec::new(),
+            suggestions: Vec::new(),
+            tags: Vec::new(),
+            args: Vec::new(),
+            markup: false,
         }
     }
 
@@ -191,15 +542,371 @@ impl<FileId> Diagnostic<FileId> {
         self
     }
 
+    /// Treat the messages of the diagnostic, and of its labels, notes, and
+    /// sub-diagnostics, as [`markup`]. Without this, messages are written
+    /// as-is.
+    ///
+    /// [`markup`]: crate::markup
+    pub fn with_markup(mut self) -> Diagnostic<FileId> {
+        self.markup = true;
+        self
+    }
+
     /// Add some labels to the diagnostic.
     pub fn with_labels(mut self, labels: Vec<Label<FileId>>) -> Diagnostic<FileId> {
         self.labels = labels;
         self
     }
 
-    /// Add some notes to the diagnostic.
+    /// Add some notes to the diagnostic, with a kind of [`NoteKind::Plain`].
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::{Diagnostic, Note};
+    ///
+    /// let diagnostic = Diagnostic::<()>::error().with_notes(vec![]);
+    /// assert!(diagnostic.notes.is_empty());
+    ///
+    /// let diagnostic = Diagnostic::<()>::error().with_notes(vec!["a note".to_owned()]);
+    /// assert_eq!(diagnostic.notes, [Note::plain("a note")]);
+    /// ```
+    ///
+    /// [`NoteKind::Plain`]: NoteKind::Plain
     pub fn with_notes(mut self, notes: Vec<String>) -> Diagnostic<FileId> {
+        self.notes = notes.into_iter().map(Note::plain).collect();
+        self
+    }
+
+    /// Add some notes of any kind to the diagnostic.
+    pub fn with_typed_notes(mut self, notes: Vec<Note>) -> Diagnostic<FileId> {
         self.notes = notes;
         self
     }
+
+    /// Add some sub-diagnostics to the diagnostic.
+    pub fn with_children(mut self, children: Vec<SubDiagnostic<FileId>>) -> Diagnostic<FileId> {
+        self.children = children;
+        self
+    }
+
+    /// Add some suggested fixes to the diagnostic.
+    pub fn with_suggestions(mut self, suggestions: Vec<Suggestion<FileId>>) -> Diagnostic<FileId> {
+        self.suggestions = suggestions;
+        self
+    }
+
+    /// Add some tags to the diagnostic.
+    pub fn with_tags(mut self, tags: Vec<Tag>) -> Diagnostic<FileId> {
+        self.tags = tags;
+        self
+    }
+
+    /// Add some named arguments to the diagnostic, for formatting its
+    /// messages when it is localized.
+    pub fn with_args(
+        mut self,
+        args: Vec<(impl Into<String>, impl Into<String>)>,
+    ) -> Diagnostic<FileId> {
+        self.args = args
+            .into_iter()
+            .map(|(name, value)| (name.into(), value.into()))
+            .collect();
+        self
+    }
+
+    /// A fingerprint that identifies the diagnostic, for tracking it across
+    /// changes to the source code and for removing duplicate diagnostics.
+    ///
+    /// The fingerprint is a hash of the code, severity, and message of the
+    /// diagnostic, along with the name of the file of its first primary
+    /// label, the source code covered by that label, and the source code of
+    /// the lines that contain it. Markup is removed from the message if the
+    /// diagnostic uses it, and runs of whitespace in the message and source
+    /// code are treated as a single space. Byte offsets and line numbers are
+    /// not included, so the fingerprint does not change when unrelated edits
+    /// move the code around.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::{Diagnostic, Label};
+    /// use codespan_reporting::files::SimpleFile;
+    ///
+    /// let unused_variable = |start| {
+    ///     Diagnostic::warning()
+    ///         .with_code("W0001")
+    ///         .with_message("unused variable")
+    ///         .with_labels(vec![Label::primary((), start..start + 1)])
+    /// };
+    ///
+    /// let file = SimpleFile::new("main.rs", "let x = 1;\n");
+    /// let fingerprint = unused_variable(4).fingerprint(&file);
+    ///
+    /// let moved = SimpleFile::new("main.rs", "let w = 0;\n\n    let x = 1;\n");
+    /// assert_eq!(unused_variable(20).fingerprint(&moved), fingerprint);
+    ///
+    /// let changed = SimpleFile::new("main.rs", "let x = 2;\n");
+    /// assert_ne!(unused_variable(4).fingerprint(&changed), fingerprint);
+    /// ```
+    pub fn fingerprint<'files, F>(&self, files: &'files F) -> Fingerprint
+    where
+        FileId: Copy,
+        F: Files<'files, FileId = FileId>,
+    {
+        let mut hasher = FingerprintHasher::new();
+
+        match &self.code {
+            Some(code) => hasher.write_field(code),
+            None => hasher.write_field(""),
+        }
+        hasher.write_field(match self.severity {
+            Severity::Bug => "bug",
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+            Severity::Help => "help",
+        });
+        let message = match self.markup {
+            true => markup::to_plain(&self.message),
+            false => self.message.clone(),
+        };
+        hasher.write_field(&normalize_whitespace(&message));
+
+        let label = (self.labels.iter()).find(|label| label.style == LabelStyle::Primary);
+        let (name, text, context) = match label {
+            Some(label) => {
+                let file_id = label.file_id;
+                let name = (files.name(file_id)).map_or(String::new(), |name| name.to_string());
+                let source = files.source(file_id);
+                let source = source.as_ref().map_or("", |source| source.as_ref());
+                let line_range = |byte_index| {
+                    let line_index = files.line_index(file_id, byte_index)?;
+                    files.line_range(file_id, line_index)
+                };
+                let context = match (line_range(label.range.start), line_range(label.range.end)) {
+                    (Some(start), Some(end)) => source.get(start.start..end.end),
+                    _ => None,
+                };
+
+                (
+                    name,
+                    normalize_whitespace(source.get(label.range.clone()).unwrap_or("")),
+                    normalize_whitespace(context.unwrap_or("")),
+                )
+            }
+            None => (String::new(), String::new(), String::new()),
+        };
+        hasher.write_field(&name);
+        hasher.write_field(&text);
+        hasher.write_field(&context);
+
+        hasher.finish()
+    }
+}
+
+/// Errors that can contribute a code and labels to a diagnostic, for
+/// pointing to the source code that caused them.
+///
+/// This is used by [`Diagnostic::from_error_with`].
+pub trait AsDiagnostic<FileId> {
+    /// An optional code that identifies the error.
+    fn code(&self) -> Option<String> {
+        None
+    }
+
+    /// Labels that describe the source code that caused the error.
+    fn labels(&self) -> Vec<Label<FileId>> {
+        Vec::new()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<FileId> Diagnostic<FileId> {
+    /// Create an error diagnostic from an error, using the error as the
+    /// message and adding a `caused by` note for each error in its chain of
+    /// sources.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::Diagnostic;
+    /// use std::io;
+    ///
+    /// let error = io::Error::new(io::ErrorKind::NotFound, "config.toml not found");
+    /// let diagnostic = Diagnostic::<()>::from_error(&error);
+    ///
+    /// assert_eq!(diagnostic.message, "config.toml not found");
+    /// ```
+    pub fn from_error(error: &dyn std::error::Error) -> Diagnostic<FileId> {
+        let mut diagnostic = Diagnostic::error().with_message(error.to_string());
+
+        let mut source = error.source();
+        while let Some(error) = source {
+            let note = Note::plain(error.to_string()).with_title("caused by");
+            diagnostic.notes.push(note);
+            source = error.source();
+        }
+
+        diagnostic
+    }
+
+    /// Create an error diagnostic from an error, like
+    /// [`Diagnostic::from_error`], allowing the error and its sources to
+    /// contribute a code and labels through [`AsDiagnostic`].
+    ///
+    /// The `as_diagnostic` function is called with the error and each of its
+    /// sources, and is usually implemented by downcasting to the error types
+    /// that implement [`AsDiagnostic`]. The first code that is found is used
+    /// as the code of the diagnostic, and all of the labels that are found
+    /// are added to it.
+    ///
+    /// ```rust
+    /// use codespan_reporting::diagnostic::{AsDiagnostic, Diagnostic, Label};
+    /// use std::{error, fmt};
+    ///
+    /// #[derive(Debug)]
+    /// struct ParseError(std::ops::Range<usize>);
+    ///
+    /// impl fmt::Display for ParseError {
+    ///     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    ///         write!(f, "unexpected character")
+    ///     }
+    /// }
+    ///
+    /// impl error::Error for ParseError {}
+    ///
+    /// impl AsDiagnostic<()> for ParseError {
+    ///     fn code(&self) -> Option<String> {
+    ///         Some("E0001".to_owned())
+    ///     }
+    ///
+    ///     fn labels(&self) -> Vec<Label<()>> {
+    ///         vec![Label::primary((), self.0.clone())]
+    ///     }
+    /// }
+    ///
+    /// let error = ParseError(4..5);
+    /// let diagnostic = Diagnostic::from_error_with(&error, |error| {
+    ///     error.downcast_ref::<ParseError>().map(|error| error as _)
+    /// });
+    ///
+    /// assert_eq!(diagnostic.code.as_deref(), Some("E0001"));
+    /// assert_eq!(diagnostic.labels, vec![Label::primary((), 4..5)]);
+    /// ```
+    ///
+    /// [`Diagnostic::from_error`]: Diagnostic::from_error
+    pub fn from_error_with<'a>(
+        error: &'a (dyn std::error::Error + 'static),
+        mut as_diagnostic: impl FnMut(
+            &'a (dyn std::error::Error + 'static),
+        ) -> Option<&'a dyn AsDiagnostic<FileId>>,
+    ) -> Diagnostic<FileId>
+    where
+        FileId: 'a,
+    {
+        let mut diagnostic = Diagnostic::from_error(error);
+
+        let mut source = Some(error);
+        while let Some(error) = source {
+            if let Some(error) = as_diagnostic(error) {
+                if diagnostic.code.is_none() {
+                    diagnostic.code = error.code();
+                }
+                diagnostic.labels.extend(error.labels());
+            }
+            source = error.source();
+        }
+
+        diagnostic
+    }
+}
+
+/// A fingerprint that identifies a diagnostic, returned by
+/// [`Diagnostic::fingerprint`].
+///
+/// Fingerprints are written as 16 hexadecimal digits, and are serialized as
+/// strings, so that they can be included in machine-readable outputs. The
+/// hash function that is used is stable across platforms and releases.
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::Fingerprint;
+///
+/// let fingerprint = "00c0ffee0ddba115".parse::<Fingerprint>()?;
+///
+/// assert_eq!(fingerprint, Fingerprint(0xc0ffee0ddba115));
+/// assert_eq!(fingerprint.to_string(), "00c0ffee0ddba115");
+/// # Ok::<(), std::num::ParseIntError>(())
+/// ```
+///
+/// [`Diagnostic::fingerprint`]: Diagnostic::fingerprint
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Fingerprint(pub u64);
+
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for Fingerprint {
+    type Err = ParseIntError;
+
+    fn from_str(src: &str) -> Result<Fingerprint, ParseIntError> {
+        u64::from_str_radix(src, 16).map(Fingerprint)
+    }
+}
+
+#[cfg(feature = "serialization")]
+impl Serialize for Fingerprint {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serialization")]
+impl<'de> Deserialize<'de> for Fingerprint {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Fingerprint, D::Error> {
+        let src = String::deserialize(deserializer)?;
+        src.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// The 64-bit FNV-1a hash function, used for fingerprints because, unlike the
+/// hashers in the standard library, its output is specified and stable.
+struct FingerprintHasher {
+    state: u64,
+}
+
+impl FingerprintHasher {
+    fn new() -> FingerprintHasher {
+        FingerprintHasher {
+            state: 0xcbf2_9ce4_8422_2325,
+        }
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.state ^= u64::from(byte);
+            self.state = self.state.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    /// Write a field, prefixed by its length so that the boundaries between
+    /// fields are unambiguous.
+    fn write_field(&mut self, field: &str) {
+        self.write(&(field.len() as u64).to_le_bytes());
+        self.write(field.as_bytes());
+    }
+
+    fn finish(&self) -> Fingerprint {
+        Fingerprint(self.state)
+    }
+}
+
+/// Replace runs of whitespace with a single space, and remove any leading or
+/// trailing whitespace.
+fn normalize_whitespace(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len());
+    for word in text.split_whitespace() {
+        if !normalized.is_empty() {
+            normalized.push(' ');
+        }
+        normalized.push_str(word);
+    }
+    normalized
 }
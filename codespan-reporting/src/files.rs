@@ -23,7 +23,16 @@
 //!
 //! [`salsa`]: https://crates.io/crates/salsa
 
-use std::ops::Range;
+use alloc::vec::Vec;
+use core::ops::Range;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+// Synchronising the lazy initialisation of `LineIndex` needs `std`, so without
+// it the table can only be shared within a single thread.
+#[cfg(feature = "std")]
+use once_cell::sync::OnceCell;
+#[cfg(not(feature = "std"))]
+use once_cell::unsync::OnceCell;
 
 /// A minimal interface for accessing source files when rendering diagnostics.
 ///
@@ -35,7 +44,7 @@ pub trait Files<'a> {
     /// for rendering `diagnostic::Label`s in the corresponding source files.
     type FileId: 'a + Copy + PartialEq;
     /// The user-facing name of a file, to be displayed in diagnostics.
-    type Name: 'a + std::fmt::Display;
+    type Name: 'a + core::fmt::Display;
     /// The source code of a file.
     type Source: 'a + AsRef<str>;
 
@@ -52,10 +61,12 @@ pub trait Files<'a> {
     /// This can be implemented efficiently by performing a binary search over
     /// a list of line starts that was computed by calling the [`line_starts`]
     /// function that is exported from the [`files`] module. It might be useful
-    /// to pre-compute and cache these line starts.
+    /// to pre-compute and cache these line starts, for example by storing a
+    /// [`LineIndex`] alongside the source.
     ///
     /// [`line_starts`]: crate::files::line_starts
     /// [`files`]: crate::files
+    /// [`LineIndex`]: crate::files::LineIndex
     fn line_index(&'a self, id: Self::FileId, byte_index: usize) -> Option<usize>;
 
     /// The user-facing line number at the given line index.
@@ -106,8 +117,183 @@ pub trait Files<'a> {
         })
     }
 
+    /// The byte index at the given user-facing line and column numbers, as
+    /// returned by [`Files::line_number`] and [`Files::column_number`]. This
+    /// is useful for creating labels from the `line:column` locations that
+    /// are reported by external tools.
+    ///
+    /// ```rust
+    /// use codespan_reporting::files::{Files, SimpleFile};
+    ///
+    /// let file = SimpleFile::new("test", "foo\nbär\nbaz");
+    ///
+    /// assert_eq!(file.byte_index((), 2, 3), Some(7));
+    /// assert_eq!(file.byte_index((), 3, 4), Some(12));
+    /// assert_eq!(file.byte_index((), 3, 5), None);
+    /// assert_eq!(file.byte_index((), 4, 1), None);
+    /// ```
+    ///
+    /// # Note for trait implementors
+    ///
+    /// The default implementation searches for a line and column with the
+    /// given numbers, starting with the line and column that they would refer
+    /// to if they were 1-indexed. This means that it is efficient for the
+    /// default numbering, but might be slow for other numberings.
+    fn byte_index(
+        &'a self,
+        id: Self::FileId,
+        line_number: usize,
+        column_number: usize,
+    ) -> Option<usize> {
+        let is_line = |line_index| self.line_number(id, line_index) == Some(line_number);
+        let line_index = match line_number.checked_sub(1) {
+            Some(line_index) if is_line(line_index) => line_index,
+            _ => (0..)
+                .take_while(|&line_index| self.line_range(id, line_index).is_some())
+                .find(|&line_index| is_line(line_index))?,
+        };
+
+        let source = self.source(id)?;
+        let line_range = self.line_range(id, line_index)?;
+        let line_source = source.as_ref().get(line_range.clone())?;
+
+        // The byte indices that can be pointed to on the line: the start of
+        // each character, including the line terminator, or the end of the
+        // source if this is the last line.
+        let byte_indices = || {
+            let end = match line_source.ends_with('\n') {
+                true => None,
+                false => Some(line_range.end),
+            };
+            let char_starts = line_source
+                .char_indices()
+                .map(|(i, _)| line_range.start + i);
+            char_starts.chain(end)
+        };
+
+        let is_column =
+            |byte_index| self.column_number(id, line_index, byte_index) == Some(column_number);
+        match column_number
+            .checked_sub(1)
+            .and_then(|column_index| byte_indices().nth(column_index))
+        {
+            Some(byte_index) if is_column(byte_index) => Some(byte_index),
+            _ => byte_indices().find(|&byte_index| is_column(byte_index)),
+        }
+    }
+
+    /// The byte range between the given user-facing locations, as returned by
+    /// [`Files::location`].
+    ///
+    /// ```rust
+    /// use codespan_reporting::files::{Files, Location, SimpleFile};
+    ///
+    /// let file = SimpleFile::new("test", "foo\nbar\nbaz");
+    ///
+    /// let start = Location { line_number: 1, column_number: 3 };
+    /// let end = Location { line_number: 3, column_number: 2 };
+    ///
+    /// assert_eq!(file.line_column_range_to_bytes((), start..end), Some(2..9));
+    /// assert_eq!(file.line_column_range_to_bytes((), end..start), None);
+    /// ```
+    fn line_column_range_to_bytes(
+        &'a self,
+        id: Self::FileId,
+        range: Range<Location>,
+    ) -> Option<Range<usize>> {
+        let start = self.byte_index(id, range.start.line_number, range.start.column_number)?;
+        let end = self.byte_index(id, range.end.line_number, range.end.column_number)?;
+
+        match start <= end {
+            true => Some(start..end),
+            false => None,
+        }
+    }
+
     /// The byte range of line in the source of the file.
     fn line_range(&'a self, id: Self::FileId, line_index: usize) -> Option<Range<usize>>;
+
+    /// The macro expansion that produced the given byte range, if any.
+    ///
+    /// This is used to render an expansion backtrace beneath the primary
+    /// labels of a diagnostic, pointing at the code that the user wrote.
+    ///
+    /// # Note for trait implementors
+    ///
+    /// The default implementation assumes that no code is the result of a
+    /// macro expansion.
+    #[allow(unused_variables)]
+    fn expansion(
+        &'a self,
+        id: Self::FileId,
+        range: Range<usize>,
+    ) -> Option<Expansion<Self::FileId>> {
+        None
+    }
+
+    /// The file and byte range of the code that textually included the
+    /// given file, if any.
+    ///
+    /// This is used to render the chain of include sites above the snippets
+    /// of source code in the file, like "in file included from main.c:1:1".
+    ///
+    /// # Note for trait implementors
+    ///
+    /// The default implementation assumes that no files are included by
+    /// other files.
+    #[allow(unused_variables)]
+    fn include_site(&'a self, id: Self::FileId) -> Option<(Self::FileId, Range<usize>)> {
+        None
+    }
+
+    /// The file and byte range of the host file that the given byte range
+    /// was extracted from, if the file is embedded in another file, like a
+    /// query in a string literal.
+    ///
+    /// This is used to render the labels of embedded files in their host
+    /// files, so that snippets show the source code that the user wrote.
+    ///
+    /// # Note for trait implementors
+    ///
+    /// The default implementation assumes that no files are embedded in
+    /// other files.
+    #[allow(unused_variables)]
+    fn host_range(
+        &'a self,
+        id: Self::FileId,
+        range: Range<usize>,
+    ) -> Option<(Self::FileId, Range<usize>)> {
+        None
+    }
+}
+
+/// The kind of code that produced a macro expansion.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Serialize, Deserialize))]
+pub enum ExpansionKind {
+    /// A function-like macro invocation, for example `format!(...)`.
+    Macro,
+    /// An attribute macro, for example `#[test]`.
+    Attribute,
+    /// A derive macro, for example `#[derive(Debug)]`.
+    Derive,
+    /// Compiler desugaring of built-in syntax, for example a `for` loop.
+    Desugaring,
+}
+
+/// A record of the macro expansion that produced some code.
+///
+/// Returned by [`Files::expansion`].
+///
+/// [`Files::expansion`]: Files::expansion
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Expansion<FileId> {
+    /// The kind of the expansion.
+    pub kind: ExpansionKind,
+    /// The file and byte range of the code that invoked the macro.
+    pub call_site: (FileId, Range<usize>),
+    /// The file and byte range where the macro was defined, if known.
+    pub def_site: Option<(FileId, Range<usize>)>,
 }
 
 /// A user-facing location in a source file.
@@ -148,7 +334,7 @@ pub struct Location {
 /// assert_eq!(files::column_index(source, 2..13, 2 + 12), 3);
 /// ```
 pub fn column_index(source: &str, line_range: Range<usize>, byte_index: usize) -> usize {
-    let end_index = std::cmp::min(byte_index, std::cmp::min(line_range.end, source.len()));
+    let end_index = core::cmp::min(byte_index, core::cmp::min(line_range.end, source.len()));
 
     (line_range.start..end_index)
         .filter(|byte_index| source.is_char_boundary(byte_index + 1))
@@ -192,7 +378,143 @@ pub fn column_index(source: &str, line_range: Range<usize>, byte_index: usize) -
 /// assert_eq!(line_index(&line_starts, 5), Some(1));
 /// ```
 pub fn line_starts<'source>(source: &'source str) -> impl 'source + Iterator<Item = usize> {
-    std::iter::once(0).chain(source.match_indices('\n').map(|(i, _)| i + 1))
+    core::iter::once(0).chain(memchr::memchr_iter(b'\n', source.as_bytes()).map(|i| i + 1))
+}
+
+/// A table of the starting byte indices of each line in a source string,
+/// that can be used to implement [`Files::line_index`] and
+/// [`Files::line_range`].
+///
+/// The table can either be built up front with [`LineIndex::new`], or lazily
+/// on the first lookup with [`LineIndex::lazy`], which avoids scanning large
+/// sources that are never reported on. The table does not store the source
+/// itself, so the same source must be passed to every lookup.
+///
+/// Line starts are stored as `u32`s for sources of up to 4 GiB, halving the
+/// size of the table, and as `usize`s for larger sources.
+///
+/// ```rust
+/// use codespan_reporting::files::LineIndex;
+///
+/// let source = "foo\nbar\r\n\nbaz";
+/// let line_index = LineIndex::lazy();
+///
+/// assert_eq!(line_index.line_index(source, 5), 1);
+/// assert_eq!(line_index.line_range(source, 1), Some(4..9));
+/// assert_eq!(line_index.line_range(source, 3), Some(10..13));
+/// assert_eq!(line_index.line_range(source, 4), None);
+/// assert_eq!(line_index.line_starts(source).collect::<Vec<_>>(), [0, 4, 9, 10]);
+/// ```
+///
+/// [`Files::line_index`]: Files::line_index
+/// [`Files::line_range`]: Files::line_range
+/// [`LineIndex::new`]: LineIndex::new
+/// [`LineIndex::lazy`]: LineIndex::lazy
+#[derive(Clone, Debug, Default)]
+pub struct LineIndex {
+    line_starts: OnceCell<LineStarts>,
+}
+
+/// The starting byte indices of each line, using the smallest integer type
+/// that can hold every index in the source.
+#[derive(Clone, Debug)]
+enum LineStarts {
+    U32(Vec<u32>),
+    Usize(Vec<usize>),
+}
+
+impl LineStarts {
+    fn new(source: &str) -> LineStarts {
+        if source.len() <= core::u32::MAX as usize {
+            LineStarts::U32(line_starts(source).map(|start| start as u32).collect())
+        } else {
+            LineStarts::Usize(line_starts(source).collect())
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            LineStarts::U32(line_starts) => line_starts.len(),
+            LineStarts::Usize(line_starts) => line_starts.len(),
+        }
+    }
+
+    fn get(&self, line_index: usize) -> Option<usize> {
+        match self {
+            LineStarts::U32(line_starts) => Some(*line_starts.get(line_index)? as usize),
+            LineStarts::Usize(line_starts) => line_starts.get(line_index).cloned(),
+        }
+    }
+
+    fn binary_search(&self, byte_index: usize) -> Result<usize, usize> {
+        match self {
+            LineStarts::U32(line_starts) => {
+                // Byte indices that do not fit are past the end of the source
+                let byte_index = core::cmp::min(byte_index, core::u32::MAX as usize);
+                line_starts.binary_search(&(byte_index as u32))
+            }
+            LineStarts::Usize(line_starts) => line_starts.binary_search(&byte_index),
+        }
+    }
+}
+
+impl LineIndex {
+    /// Build the table of line starts for the source.
+    pub fn new(source: &str) -> LineIndex {
+        let line_index = LineIndex::lazy();
+        line_index.table(source);
+        line_index
+    }
+
+    /// Create a table of line starts that will be built on the first lookup.
+    pub fn lazy() -> LineIndex {
+        LineIndex::default()
+    }
+
+    fn table(&self, source: &str) -> &LineStarts {
+        self.line_starts.get_or_init(|| LineStarts::new(source))
+    }
+
+    /// The starting byte indices of each line in the source.
+    pub fn line_starts<'a>(&'a self, source: &str) -> impl 'a + Iterator<Item = usize> {
+        let table = self.table(source);
+        (0..table.len()).filter_map(move |line_index| table.get(line_index))
+    }
+
+    /// The number of lines in the source.
+    pub fn line_count(&self, source: &str) -> usize {
+        self.table(source).len()
+    }
+
+    /// The index of the line at the given byte index. Byte indices past the
+    /// end of the source are treated as being on the last line.
+    pub fn line_index(&self, source: &str, byte_index: usize) -> usize {
+        match self.table(source).binary_search(byte_index) {
+            Ok(line) => line,
+            Err(next_line) => next_line - 1,
+        }
+    }
+
+    /// The starting byte index of the line at the given line index. The line
+    /// index one past the last line starts at the end of the source.
+    pub fn line_start(&self, source: &str, line_index: usize) -> Option<usize> {
+        use core::cmp::Ordering;
+
+        let line_starts = self.table(source);
+        match line_index.cmp(&line_starts.len()) {
+            Ordering::Less => line_starts.get(line_index),
+            Ordering::Equal => Some(source.len()),
+            Ordering::Greater => None,
+        }
+    }
+
+    /// The byte range of the line at the given line index.
+    pub fn line_range(&self, source: &str, line_index: usize) -> Option<Range<usize>> {
+        let line_start = self.line_start(source, line_index)?;
+        let next_line_start = self.line_start(source, line_index + 1)?;
+
+        Some(line_start..next_line_start)
+    }
 }
 
 /// A file database that contains a single source file.
@@ -210,19 +532,19 @@ pub struct SimpleFile<Name, Source> {
     /// The source code of the file.
     source: Source,
     /// The starting byte indices in the source code.
-    line_starts: Vec<usize>,
+    line_starts: LineIndex,
 }
 
 impl<Name, Source> SimpleFile<Name, Source>
 where
-    Name: std::fmt::Display,
+    Name: core::fmt::Display,
     Source: AsRef<str>,
 {
     /// Create a new source file.
     pub fn new(name: Name, source: Source) -> SimpleFile<Name, Source> {
         SimpleFile {
             name,
-            line_starts: line_starts(source.as_ref()).collect(),
+            line_starts: LineIndex::lazy(),
             source,
         }
     }
@@ -236,21 +558,11 @@ where
     pub fn source(&self) -> &Source {
         &self.source
     }
-
-    fn line_start(&self, line_index: usize) -> Option<usize> {
-        use std::cmp::Ordering;
-
-        match line_index.cmp(&self.line_starts.len()) {
-            Ordering::Less => self.line_starts.get(line_index).cloned(),
-            Ordering::Equal => Some(self.source.as_ref().len()),
-            Ordering::Greater => None,
-        }
-    }
 }
 
 impl<'a, Name, Source> Files<'a> for SimpleFile<Name, Source>
 where
-    Name: 'a + std::fmt::Display + Clone,
+    Name: 'a + core::fmt::Display + Clone,
     Source: 'a + AsRef<str>,
 {
     type FileId = ();
@@ -266,17 +578,15 @@ where
     }
 
     fn line_index(&self, (): (), byte_index: usize) -> Option<usize> {
-        match self.line_starts.binary_search(&byte_index) {
-            Ok(line) => Some(line),
-            Err(next_line) => Some(next_line - 1),
-        }
+        Some(
+            self.line_starts
+                .line_index(self.source.as_ref(), byte_index),
+        )
     }
 
     fn line_range(&self, (): (), line_index: usize) -> Option<Range<usize>> {
-        let line_start = self.line_start(line_index)?;
-        let next_line_start = self.line_start(line_index + 1)?;
-
-        Some(line_start..next_line_start)
+        self.line_starts
+            .line_range(self.source.as_ref(), line_index)
     }
 }
 
@@ -291,7 +601,7 @@ pub struct SimpleFiles<Name, Source> {
 
 impl<Name, Source> SimpleFiles<Name, Source>
 where
-    Name: std::fmt::Display,
+    Name: core::fmt::Display,
     Source: AsRef<str>,
 {
     /// Create a new files database.
@@ -315,7 +625,7 @@ where
 
 impl<'a, Name, Source> Files<'a> for SimpleFiles<Name, Source>
 where
-    Name: 'a + std::fmt::Display + Clone,
+    Name: 'a + core::fmt::Display + Clone,
     Source: 'a + AsRef<str>,
 {
     type FileId = usize;
@@ -350,7 +660,9 @@ mod test {
         let file = SimpleFile::new("test", TEST_SOURCE);
 
         assert_eq!(
-            file.line_starts,
+            file.line_starts
+                .line_starts(file.source)
+                .collect::<Vec<_>>(),
             [
                 0,  // "foo\n"
                 4,  // "bar\r\n"
@@ -360,6 +672,22 @@ mod test {
         );
     }
 
+    #[test]
+    fn line_starts_tables() {
+        let source = "foo\nbar\r\n\nbaz";
+        let small = LineStarts::new(source);
+        let large = LineStarts::Usize(super::line_starts(source).collect());
+
+        for line_starts in &[small, large] {
+            assert_eq!(line_starts.len(), 4);
+            assert_eq!(line_starts.get(1), Some(4));
+            assert_eq!(line_starts.get(4), None);
+            assert_eq!(line_starts.binary_search(9), Ok(2));
+            assert_eq!(line_starts.binary_search(5), Err(2));
+            assert_eq!(line_starts.binary_search(core::usize::MAX), Err(4));
+        }
+    }
+
     #[test]
     fn line_span_sources() {
         let file = SimpleFile::new("test", TEST_SOURCE);
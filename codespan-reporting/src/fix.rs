@@ -0,0 +1,674 @@
+//! Applying the suggested edits of diagnostics to source files.
+//!
+//! The suggestions attached to a set of diagnostics are first collected into
+//! [`Fixes`], which rejects any suggestion whose edits overlap the edits of a
+//! suggestion that was accepted before it. The remaining edits can then be
+//! applied to produce new source files, or rendered as a unified diff.
+//!
+//! ```rust
+//! use codespan_reporting::diagnostic::{Diagnostic, Edit, Suggestion};
+//! use codespan_reporting::files::SimpleFile;
+//! use codespan_reporting::fix::Fixes;
+//!
+//! let file = SimpleFile::new("main.rs", "let x = 1\nlet y = 2\n");
+//! let diagnostics = vec![
+//!     Diagnostic::error()
+//!         .with_message("missing semicolon")
+//!         .with_suggestions(vec![
+//!             Suggestion::machine_applicable().with_edits(vec![Edit::new((), 9..9, ";")]),
+//!         ]),
+//!     Diagnostic::warning()
+//!         .with_message("unused variable")
+//!         .with_suggestions(vec![
+//!             Suggestion::maybe_incorrect().with_edits(vec![Edit::new((), 14..15, "_y")]),
+//!         ]),
+//! ];
+//!
+//! let fixes = Fixes::from_diagnostics(&file, &diagnostics, |applicability| {
+//!     applicability.is_machine_applicable()
+//! });
+//!
+//! assert!(fixes.conflicts().is_empty());
+//! assert_eq!(
+//!     fixes.apply(&file),
+//!     vec![((), String::from("let x = 1;\nlet y = 2\n"))],
+//! );
+//! ```
+
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{Display, Write};
+use core::ops::Range;
+
+use crate::diagnostic::{Applicability, Diagnostic, Edit, Label, Suggestion};
+use crate::files::{self, Files};
+
+/// The number of unchanged lines to show around the changes in a unified diff.
+const CONTEXT_LINES: usize = 3;
+
+/// The edits of a set of suggestions that can be applied together.
+#[derive(Clone, Debug)]
+pub struct Fixes<'a, FileId> {
+    edits: Vec<&'a Edit<FileId>>,
+    conflicts: Vec<Conflict<'a, FileId>>,
+}
+
+/// A suggestion that could not be applied.
+#[derive(Copy, Clone, Debug)]
+pub struct Conflict<'a, FileId> {
+    /// The suggestion that was rejected.
+    pub suggestion: &'a Suggestion<FileId>,
+    /// The edit of the suggestion that could not be applied.
+    pub edit: &'a Edit<FileId>,
+    /// Why the edit could not be applied.
+    pub reason: ConflictReason<'a, FileId>,
+}
+
+/// The reason that an edit could not be applied.
+#[derive(Copy, Clone, Debug)]
+pub enum ConflictReason<'a, FileId> {
+    /// The edit overlaps with an edit that was accepted before it, or with
+    /// another edit of the same suggestion.
+    Overlap(&'a Edit<FileId>),
+    /// The file does not exist, or the range of the edit is out of the bounds
+    /// of the file or does not fall on character boundaries.
+    InvalidRange,
+}
+
+impl<'a, FileId> Fixes<'a, FileId>
+where
+    FileId: 'a + Copy + PartialEq,
+{
+    /// Collect the suggestions of some diagnostics, keeping only those with
+    /// an applicability that is accepted by the `filter`.
+    pub fn from_diagnostics<'files, F>(
+        files: &'files F,
+        diagnostics: impl IntoIterator<Item = &'a Diagnostic<FileId>>,
+        mut filter: impl FnMut(Applicability) -> bool,
+    ) -> Fixes<'a, FileId>
+    where
+        F: Files<'files, FileId = FileId>,
+    {
+        let suggestions = diagnostics
+            .into_iter()
+            .flat_map(|diagnostic| &diagnostic.suggestions)
+            .filter(move |suggestion| filter(suggestion.applicability));
+
+        Fixes::from_suggestions(files, suggestions)
+    }
+
+    /// Collect some suggestions.
+    ///
+    /// Suggestions are considered in the order that they are given, and a
+    /// suggestion is only accepted if none of its edits overlap the edits of
+    /// the suggestions that were accepted before it. Two edits overlap if
+    /// their ranges intersect, or if they start at the same byte index. Edits
+    /// that are identical to an edit that was already accepted are skipped
+    /// rather than being treated as a conflict.
+    pub fn from_suggestions<'files, F>(
+        files: &'files F,
+        suggestions: impl IntoIterator<Item = &'a Suggestion<FileId>>,
+    ) -> Fixes<'a, FileId>
+    where
+        F: Files<'files, FileId = FileId>,
+    {
+        let mut fixes = Fixes {
+            edits: Vec::new(),
+            conflicts: Vec::new(),
+        };
+
+        for suggestion in suggestions {
+            match fixes.check_suggestion(files, suggestion) {
+                Ok(edits) => fixes.edits.extend(edits),
+                Err(conflict) => fixes.conflicts.push(conflict),
+            }
+        }
+
+        fixes
+    }
+
+    /// Check the edits of a suggestion against the edits that have already
+    /// been accepted, returning the edits that need to be added.
+    fn check_suggestion<'files, F>(
+        &self,
+        files: &'files F,
+        suggestion: &'a Suggestion<FileId>,
+    ) -> Result<Vec<&'a Edit<FileId>>, Conflict<'a, FileId>>
+    where
+        F: Files<'files, FileId = FileId>,
+    {
+        let mut new_edits = Vec::<&'a Edit<FileId>>::new();
+
+        for edit in &suggestion.edits {
+            let conflict = |reason| Conflict {
+                suggestion,
+                edit,
+                reason,
+            };
+
+            let is_valid = match files.source(edit.file_id) {
+                Some(source) => source.as_ref().get(edit.range.clone()).is_some(),
+                None => false,
+            };
+            if !is_valid {
+                return Err(conflict(ConflictReason::InvalidRange));
+            }
+
+            let mut existing_edits = (self.edits.iter())
+                .chain(&new_edits)
+                .filter(|existing| existing.file_id == edit.file_id);
+
+            if (existing_edits.clone()).any(|existing| {
+                existing.range == edit.range && existing.replacement == edit.replacement
+            }) {
+                continue;
+            }
+
+            if let Some(existing) =
+                existing_edits.find(|existing| overlaps(&existing.range, &edit.range))
+            {
+                return Err(conflict(ConflictReason::Overlap(existing)));
+            }
+
+            new_edits.push(edit);
+        }
+
+        Ok(new_edits)
+    }
+
+    /// The edits that will be applied, in the order that they were accepted.
+    pub fn edits(&self) -> &[&'a Edit<FileId>] {
+        &self.edits
+    }
+
+    /// The suggestions that were rejected.
+    pub fn conflicts(&self) -> &[Conflict<'a, FileId>] {
+        &self.conflicts
+    }
+
+    /// The files that will be changed, in the order that they were first
+    /// edited.
+    pub fn file_ids(&self) -> Vec<FileId> {
+        let mut file_ids = Vec::new();
+        for edit in &self.edits {
+            if !file_ids.contains(&edit.file_id) {
+                file_ids.push(edit.file_id);
+            }
+        }
+        file_ids
+    }
+
+    /// The edits to a file, sorted by their position in the file.
+    fn file_edits(&self, file_id: FileId) -> Vec<&'a Edit<FileId>> {
+        let mut edits = (self.edits.iter().cloned())
+            .filter(|edit| edit.file_id == file_id)
+            .collect::<Vec<_>>();
+        // Overlapping edits were rejected, so no two edits start at the same
+        // byte index and this ordering is total.
+        edits.sort_by_key(|edit| edit.range.start);
+        edits
+    }
+
+    /// Apply the edits, returning the new source of each of the changed
+    /// files, in the order that they were first edited.
+    ///
+    /// # Panics
+    ///
+    /// This may panic if `files` is not the same as the files that the
+    /// fixes were collected with.
+    pub fn apply<'files, F>(&self, files: &'files F) -> Vec<(FileId, String)>
+    where
+        F: Files<'files, FileId = FileId>,
+    {
+        (self.file_ids().into_iter())
+            .map(|file_id| {
+                let source = files.source(file_id).expect("file_id");
+                let source = source.as_ref();
+                let edits = self.file_edits(file_id);
+
+                (file_id, apply_edits(source, 0..source.len(), &edits))
+            })
+            .collect()
+    }
+
+    /// Render the changes as a unified diff, in the format accepted by
+    /// `patch -p1` and `git apply`.
+    ///
+    /// # Panics
+    ///
+    /// This may panic if `files` is not the same as the files that the
+    /// fixes were collected with.
+    pub fn unified_diff<'files, F>(&self, files: &'files F) -> String
+    where
+        F: Files<'files, FileId = FileId>,
+    {
+        let mut diff = String::new();
+
+        for file_id in self.file_ids() {
+            let name = files.name(file_id).expect("file_id");
+            let source = files.source(file_id).expect("file_id");
+            let edits = self.file_edits(file_id);
+
+            write_file_diff(&mut diff, name, source.as_ref(), &edits);
+        }
+
+        diff
+    }
+}
+
+impl<'a, FileId> Conflict<'a, FileId>
+where
+    FileId: Copy,
+{
+    /// Describe the conflict as a warning diagnostic.
+    pub fn to_diagnostic(&self) -> Diagnostic<FileId> {
+        let message = match self.suggestion.message.as_str() {
+            "" => String::from("could not apply suggestion"),
+            message => format!("could not apply suggestion: {}", message),
+        };
+        let diagnostic = Diagnostic::warning().with_message(message);
+
+        match self.reason {
+            ConflictReason::Overlap(existing) => diagnostic.with_labels(alloc::vec![
+                Label::primary(self.edit.file_id, self.edit.range.clone())
+                    .with_message("this edit"),
+                Label::secondary(existing.file_id, existing.range.clone())
+                    .with_message("overlaps with this edit"),
+            ]),
+            ConflictReason::InvalidRange => diagnostic.with_notes(alloc::vec![format!(
+                "the edit range {}..{} is not valid for the file",
+                self.edit.range.start, self.edit.range.end,
+            )]),
+        }
+    }
+}
+
+/// Returns `true` if the two ranges intersect, or if they start at the same
+/// position, in which case the order they should be applied in is ambiguous.
+fn overlaps(a: &Range<usize>, b: &Range<usize>) -> bool {
+    (a.start < b.end && b.start < a.end) || a.start == b.start
+}
+
+/// Apply some sorted edits to a range of the source, returning the new text
+/// of that range.
+fn apply_edits<FileId>(source: &str, range: Range<usize>, edits: &[&Edit<FileId>]) -> String {
+    let mut text = String::with_capacity(range.end - range.start);
+    let mut cursor = range.start;
+    for edit in edits {
+        text.push_str(&source[cursor..edit.range.start]);
+        text.push_str(&edit.replacement);
+        cursor = edit.range.end;
+    }
+    text.push_str(&source[cursor..range.end]);
+    text
+}
+
+/// The lines of some text, including their line terminators.
+fn lines(text: &str) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    core::iter::from_fn(move || {
+        if rest.is_empty() {
+            return None;
+        }
+        let end = rest.find('\n').map_or(rest.len(), |end| end + 1);
+        let (line, remaining) = rest.split_at(end);
+        rest = remaining;
+        Some(line)
+    })
+}
+
+/// A set of edits that touch the same lines.
+struct Group<'a, FileId> {
+    first_line_index: usize,
+    last_line_index: usize,
+    edits: Vec<&'a Edit<FileId>>,
+}
+
+fn write_file_diff<FileId>(
+    diff: &mut String,
+    name: impl Display,
+    source: &str,
+    edits: &[&Edit<FileId>],
+) {
+    let line_starts = files::line_starts(source).collect::<Vec<_>>();
+    let line_count = lines(source).count();
+    let line_index = |byte_index| match line_starts.binary_search(&byte_index) {
+        Ok(line_index) => line_index,
+        Err(next_line_index) => next_line_index - 1,
+    };
+    let line_range = |first_line_index: usize, last_line_index: usize| {
+        let start = line_starts.get(first_line_index).cloned();
+        let end = line_starts.get(last_line_index + 1).cloned();
+        start.unwrap_or(source.len())..end.unwrap_or(source.len())
+    };
+
+    // Group the edits by the lines they touch
+    let mut groups = Vec::<Group<'_, FileId>>::new();
+    for &edit in edits {
+        let first_line_index = line_index(edit.range.start);
+        let last_line_index = match edit.range.end {
+            end if end > edit.range.start => line_index(end - 1),
+            end => line_index(end),
+        };
+
+        match groups.last_mut() {
+            Some(group) if first_line_index <= group.last_line_index => {
+                group.last_line_index = usize::max(group.last_line_index, last_line_index);
+                group.edits.push(edit);
+            }
+            _ => groups.push(Group {
+                first_line_index,
+                last_line_index,
+                edits: alloc::vec![edit],
+            }),
+        }
+    }
+
+    // Gather the groups into hunks, merging them when their context lines
+    // would touch or overlap
+    let mut hunks = Vec::<Vec<Group<'_, FileId>>>::new();
+    for group in groups {
+        match hunks.last_mut() {
+            Some(hunk)
+                if group.first_line_index
+                    <= hunk.last().unwrap().last_line_index + 2 * CONTEXT_LINES + 1 =>
+            {
+                hunk.push(group)
+            }
+            _ => hunks.push(alloc::vec![group]),
+        }
+    }
+
+    writeln!(diff, "--- a/{}", name).unwrap();
+    writeln!(diff, "+++ b/{}", name).unwrap();
+
+    let mut line_offset = 0_isize;
+    for hunk in hunks {
+        let first_line_index = hunk[0].first_line_index.saturating_sub(CONTEXT_LINES);
+        let last_line_index = hunk.last().unwrap().last_line_index + CONTEXT_LINES;
+        let last_line_index = usize::min(last_line_index, line_count.saturating_sub(1));
+
+        let mut body = String::new();
+        let mut old_count = 0;
+        let mut new_count = 0;
+        let mut cursor = first_line_index;
+
+        let write_context = |body: &mut String, first_line_index, last_line_index| {
+            let range = line_range(first_line_index, last_line_index);
+            let mut count = 0;
+            for line in lines(&source[range]) {
+                write_line(body, ' ', line);
+                count += 1;
+            }
+            count
+        };
+
+        for group in &hunk {
+            if cursor < group.first_line_index {
+                let count = write_context(&mut body, cursor, group.first_line_index - 1);
+                old_count += count;
+                new_count += count;
+            }
+
+            let range = line_range(group.first_line_index, group.last_line_index);
+            for line in lines(&source[range.clone()]) {
+                write_line(&mut body, '-', line);
+                old_count += 1;
+            }
+            for line in lines(&apply_edits(source, range, &group.edits)) {
+                write_line(&mut body, '+', line);
+                new_count += 1;
+            }
+
+            cursor = group.last_line_index + 1;
+        }
+        if cursor <= last_line_index {
+            let count = write_context(&mut body, cursor, last_line_index);
+            old_count += count;
+            new_count += count;
+        }
+
+        // Empty ranges are numbered from the line before them
+        let old_start = match old_count {
+            0 => first_line_index,
+            _ => first_line_index + 1,
+        };
+        let new_start = match new_count {
+            0 => first_line_index as isize + line_offset,
+            _ => first_line_index as isize + line_offset + 1,
+        };
+        line_offset += new_count as isize - old_count as isize;
+
+        writeln!(
+            diff,
+            "@@ -{},{} +{},{} @@",
+            old_start, old_count, new_start, new_count,
+        )
+        .unwrap();
+        diff.push_str(&body);
+    }
+}
+
+fn write_line(body: &mut String, kind: char, line: &str) {
+    body.push(kind);
+    body.push_str(line);
+    if !line.ends_with('\n') {
+        body.push_str("\n\\ No newline at end of file\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::files::SimpleFiles;
+    use alloc::vec;
+
+    fn suggestion(edits: Vec<Edit<usize>>) -> Suggestion<usize> {
+        Suggestion::machine_applicable().with_edits(edits)
+    }
+
+    #[test]
+    fn filters_by_applicability() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "foo bar\n");
+
+        let diagnostics = vec![Diagnostic::error().with_suggestions(vec![
+            Suggestion::maybe_incorrect().with_edits(vec![Edit::new(file_id, 0..3, "baz")]),
+            Suggestion::machine_applicable().with_edits(vec![Edit::new(file_id, 4..7, "qux")]),
+        ])];
+
+        let fixes = Fixes::from_diagnostics(&files, &diagnostics, |applicability| {
+            applicability.is_machine_applicable()
+        });
+
+        assert_eq!(fixes.edits().len(), 1);
+        assert_eq!(
+            fixes.apply(&files),
+            vec![(file_id, String::from("foo qux\n"))],
+        );
+    }
+
+    #[test]
+    fn applies_edits_in_source_order() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "a b c");
+
+        let suggestions = vec![
+            suggestion(vec![Edit::new(file_id, 4..5, "z")]),
+            suggestion(vec![
+                Edit::new(file_id, 0..1, "x"),
+                Edit::new(file_id, 5..5, "!"),
+            ]),
+            suggestion(vec![Edit::new(file_id, 2..3, "y")]),
+        ];
+
+        let fixes = Fixes::from_suggestions(&files, &suggestions);
+
+        assert!(fixes.conflicts().is_empty());
+        assert_eq!(fixes.apply(&files), vec![(file_id, String::from("x y z!"))]);
+    }
+
+    #[test]
+    fn rejects_overlapping_suggestions() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "let x = 1;");
+
+        let suggestions = vec![
+            suggestion(vec![Edit::new(file_id, 4..5, "_x")]),
+            suggestion(vec![
+                Edit::new(file_id, 8..9, "2"),
+                Edit::new(file_id, 0..5, "let y"),
+            ]),
+            suggestion(vec![Edit::new(file_id, 4..4, "mut ")]),
+        ];
+
+        let fixes = Fixes::from_suggestions(&files, &suggestions);
+
+        assert_eq!(fixes.conflicts().len(), 2);
+        for (conflict, suggestion) in fixes.conflicts().iter().zip(&suggestions[1..]) {
+            assert_eq!(conflict.suggestion, suggestion);
+            match conflict.reason {
+                ConflictReason::Overlap(edit) => assert_eq!(edit, &suggestions[0].edits[0]),
+                ConflictReason::InvalidRange => panic!("unexpected invalid range"),
+            }
+        }
+        assert_eq!(
+            fixes.apply(&files),
+            vec![(file_id, String::from("let _x = 1;"))],
+        );
+    }
+
+    #[test]
+    fn skips_duplicate_edits() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "x");
+
+        let suggestions = vec![
+            suggestion(vec![Edit::new(file_id, 1..1, ";")]),
+            suggestion(vec![Edit::new(file_id, 1..1, ";")]),
+        ];
+
+        let fixes = Fixes::from_suggestions(&files, &suggestions);
+
+        assert!(fixes.conflicts().is_empty());
+        assert_eq!(fixes.apply(&files), vec![(file_id, String::from("x;"))]);
+    }
+
+    #[test]
+    fn rejects_invalid_ranges() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "héllo");
+
+        let suggestions = vec![
+            suggestion(vec![Edit::new(file_id, 0..2, "")]),
+            suggestion(vec![Edit::new(file_id, 4..10, "")]),
+            suggestion(vec![Edit::new(file_id + 1, 0..0, "")]),
+        ];
+
+        let fixes = Fixes::from_suggestions(&files, &suggestions);
+
+        assert!(fixes.edits().is_empty());
+        assert_eq!(fixes.conflicts().len(), 3);
+        assert!(fixes
+            .conflicts()
+            .iter()
+            .all(|conflict| match conflict.reason {
+                ConflictReason::InvalidRange => true,
+                ConflictReason::Overlap(_) => false,
+            }));
+    }
+
+    #[test]
+    fn applying_is_stable() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "one two three");
+
+        let suggestions = vec![
+            suggestion(vec![Edit::new(file_id, 8..13, "3")]),
+            suggestion(vec![Edit::new(file_id, 0..3, "1")]),
+        ];
+        let reversed = suggestions.iter().rev();
+
+        let fixes = Fixes::from_suggestions(&files, &suggestions);
+        let reversed_fixes = Fixes::from_suggestions(&files, reversed);
+
+        assert_eq!(fixes.apply(&files), reversed_fixes.apply(&files));
+        assert_eq!(
+            fixes.unified_diff(&files),
+            reversed_fixes.unified_diff(&files)
+        );
+    }
+
+    #[test]
+    fn unified_diff() {
+        let mut files = SimpleFiles::new();
+        let source = "1\n2\n3\n4\n5\n6\n7\n8\n9\n10\n11\n12\n13\n14\n15\n16\n";
+        let file_id = files.add("numbers.txt", source);
+        let other_file_id = files.add("other.txt", "no newline");
+
+        let suggestions = vec![
+            suggestion(vec![
+                Edit::new(file_id, 2..3, "two"),
+                Edit::new(file_id, 10..12, ""),
+            ]),
+            suggestion(vec![Edit::new(file_id, 33..35, "15\n15.5")]),
+            suggestion(vec![Edit::new(other_file_id, 10..10, "!\n")]),
+        ];
+
+        let fixes = Fixes::from_suggestions(&files, &suggestions);
+
+        assert_eq!(
+            fixes.unified_diff(&files),
+            [
+                "--- a/numbers.txt\n",
+                "+++ b/numbers.txt\n",
+                "@@ -1,9 +1,8 @@\n",
+                " 1\n",
+                "-2\n",
+                "+two\n",
+                " 3\n",
+                " 4\n",
+                " 5\n",
+                "-6\n",
+                " 7\n",
+                " 8\n",
+                " 9\n",
+                "@@ -12,5 +11,6 @@\n",
+                " 12\n",
+                " 13\n",
+                " 14\n",
+                "-15\n",
+                "+15\n",
+                "+15.5\n",
+                " 16\n",
+                "--- a/other.txt\n",
+                "+++ b/other.txt\n",
+                "@@ -1,1 +1,1 @@\n",
+                "-no newline\n",
+                "\\ No newline at end of file\n",
+                "+no newline!\n",
+            ]
+            .concat(),
+        );
+    }
+
+    #[test]
+    fn conflict_diagnostic() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "foo");
+
+        let suggestions = vec![
+            suggestion(vec![Edit::new(file_id, 0..3, "bar")]),
+            suggestion(vec![Edit::new(file_id, 1..2, "x")]).with_message("use x"),
+        ];
+
+        let fixes = Fixes::from_suggestions(&files, &suggestions);
+        let diagnostic = fixes.conflicts()[0].to_diagnostic();
+
+        assert_eq!(diagnostic.message, "could not apply suggestion: use x");
+        assert_eq!(diagnostic.labels.len(), 2);
+        assert_eq!(diagnostic.labels[1].range, 0..3);
+    }
+}
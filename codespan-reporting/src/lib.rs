@@ -1,5 +1,36 @@
 //! Diagnostic reporting support for the codespan crate.
+//!
+//! # Optional Features
+//!
+//! Extra functionality is accessible by enabling feature flags. The features
+//! currently available are:
+//!
+//! - **std** - Adds the [`term`] module for rendering diagnostics to a
+//!   terminal. This is enabled by default. Without it, the crate only depends
+//!   on `core` and `alloc`.
+//! - **serialization** - Adds `Serialize` and `Deserialize` implementations
+//!   for use with `serde`
 
+#![no_std]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+pub mod baseline;
+pub mod catalog;
 pub mod diagnostic;
 pub mod files;
+pub mod fix;
+pub mod lint;
+pub mod markup;
+pub mod registry;
+pub mod suppress;
+#[cfg(feature = "std")]
 pub mod term;
+
+// Used by the code generated by `codespan-derive`. Not public API.
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::format;
+}
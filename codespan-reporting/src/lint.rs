@@ -0,0 +1,512 @@
+//! Configurable levels for diagnostics, in the style of lint levels.
+//!
+//! Levels are set for diagnostic codes, or for groups of codes. Codes are
+//! grouped hierarchically using `::` as a separator, so a level set for
+//! `style` applies to `style::naming` and `style::naming::snake_case`. When
+//! more than one setting applies to a code, the last one wins, unless the
+//! code was set to [`Level::Forbid`] by an earlier setting.
+//!
+//! ```rust
+//! use codespan_reporting::diagnostic::{Diagnostic, Severity};
+//! use codespan_reporting::lint::{Level, LintLevels};
+//!
+//! let mut levels = LintLevels::new().with_level("style", Level::Warn);
+//! levels.parse_args("-A unused -D style::naming".split_whitespace())?;
+//! levels.parse_config(r#"
+//!     "style::naming::acronyms" = "warn"
+//! "#)?;
+//!
+//! assert_eq!(levels.level("unused::variables"), Some(Level::Allow));
+//! assert_eq!(levels.level("style::naming::snake_case"), Some(Level::Deny));
+//! assert_eq!(levels.level("style::naming::acronyms"), Some(Level::Warn));
+//! assert_eq!(levels.level("correctness"), None);
+//!
+//! let diagnostic = Diagnostic::<()>::warning()
+//!     .with_code("style::naming::snake_case")
+//!     .with_message("variable `fooBar` should have a snake case name");
+//! let diagnostic = levels.apply(diagnostic).unwrap();
+//! assert_eq!(diagnostic.severity, Severity::Error);
+//!
+//! let diagnostic = Diagnostic::<()>::warning()
+//!     .with_code("unused::variables")
+//!     .with_message("unused variable: `x`");
+//! assert!(levels.apply(diagnostic).is_none());
+//! # Ok::<(), codespan_reporting::lint::ParseError>(())
+//! ```
+
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use core::str::FromStr;
+
+use crate::diagnostic::{Diagnostic, Note, Severity};
+
+/// The level of a diagnostic code.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Level {
+    /// Diagnostics are not emitted.
+    Allow,
+    /// Diagnostics are emitted as warnings.
+    Warn,
+    /// Diagnostics are emitted as errors.
+    Deny,
+    /// Diagnostics are emitted as errors, and later settings can not change
+    /// the level.
+    Forbid,
+}
+
+impl Level {
+    /// The name of the level, as used in configuration.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Level::Allow => "allow",
+            Level::Warn => "warn",
+            Level::Deny => "deny",
+            Level::Forbid => "forbid",
+        }
+    }
+
+    /// The command line flag used to set this level.
+    pub fn flag(self) -> &'static str {
+        match self {
+            Level::Allow => "-A",
+            Level::Warn => "-W",
+            Level::Deny => "-D",
+            Level::Forbid => "-F",
+        }
+    }
+
+    /// The severity that diagnostics at this level are emitted with, or `None`
+    /// if they are not emitted.
+    pub fn severity(self) -> Option<Severity> {
+        match self {
+            Level::Allow => None,
+            Level::Warn => Some(Severity::Warning),
+            Level::Deny | Level::Forbid => Some(Severity::Error),
+        }
+    }
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Level {
+    type Err = ParseError;
+
+    fn from_str(src: &str) -> Result<Level, ParseError> {
+        match src {
+            "allow" => Ok(Level::Allow),
+            "warn" => Ok(Level::Warn),
+            "deny" => Ok(Level::Deny),
+            "forbid" => Ok(Level::Forbid),
+            _ => Err(ParseError::InvalidLevel(src.to_string())),
+        }
+    }
+}
+
+/// Where a level was set.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum LevelSource {
+    /// Set using [`LintLevels::with_level`] or [`LintLevels::set_level`].
+    ///
+    /// [`LintLevels::with_level`]: LintLevels::with_level
+    /// [`LintLevels::set_level`]: LintLevels::set_level
+    Builder,
+    /// Set using [`LintLevels::parse_args`].
+    ///
+    /// [`LintLevels::parse_args`]: LintLevels::parse_args
+    CommandLine,
+    /// Set using [`LintLevels::parse_config`].
+    ///
+    /// [`LintLevels::parse_config`]: LintLevels::parse_config
+    Config,
+}
+
+/// A level that was set for a code or group of codes.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Setting {
+    name: String,
+    level: Level,
+    source: LevelSource,
+}
+
+impl Setting {
+    fn applies_to(&self, code: &str) -> bool {
+        code.starts_with(self.name.as_str())
+            && (code.len() == self.name.len() || code[self.name.len()..].starts_with("::"))
+    }
+
+    /// Describe the setting in the same way that it was written.
+    fn describe(&self) -> String {
+        match self.source {
+            LevelSource::Builder => format!("`{}({})`", self.level, self.name),
+            LevelSource::CommandLine => {
+                format!("`{} {}` on the command line", self.level.flag(), self.name)
+            }
+            LevelSource::Config => format!(
+                "`\"{}\" = \"{}\"` in the configuration",
+                self.name, self.level
+            ),
+        }
+    }
+}
+
+/// The levels of diagnostic codes and groups of codes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct LintLevels {
+    settings: Vec<Setting>,
+}
+
+impl LintLevels {
+    /// Create a new set of levels, with no levels set.
+    pub fn new() -> LintLevels {
+        LintLevels::default()
+    }
+
+    /// Set the level of a code or group of codes.
+    pub fn with_level(mut self, name: impl Into<String>, level: Level) -> LintLevels {
+        self.set_level(name, level);
+        self
+    }
+
+    /// Set the level of a code or group of codes.
+    pub fn set_level(&mut self, name: impl Into<String>, level: Level) {
+        self.push(name.into(), level, LevelSource::Builder);
+    }
+
+    fn push(&mut self, name: String, level: Level, source: LevelSource) {
+        self.settings.push(Setting {
+            name,
+            level,
+            source,
+        });
+    }
+
+    /// Set levels from command line arguments, like `-W unused -D style`.
+    ///
+    /// The flags `-A`, `-W`, `-D`, and `-F` set codes to `allow`, `warn`,
+    /// `deny`, and `forbid` respectively. Their long forms `--allow`,
+    /// `--warn`, `--deny`, and `--forbid` are also accepted. Names can be
+    /// given as the next argument, or joined to the flag as in `-Wunused` or
+    /// `--warn=unused`.
+    pub fn parse_args<'a>(
+        &mut self,
+        args: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), ParseError> {
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            let (level, joined_name) = parse_flag(arg)?;
+            let name = match joined_name {
+                Some(name) => name,
+                None => match args.next() {
+                    Some(name) if !name.starts_with('-') => name,
+                    _ => return Err(ParseError::MissingName(arg.to_string())),
+                },
+            };
+            if name.is_empty() {
+                return Err(ParseError::MissingName(arg.to_string()));
+            }
+            self.push(name.to_string(), level, LevelSource::CommandLine);
+        }
+
+        Ok(())
+    }
+
+    /// Set levels from the lines of a configuration file section, where each
+    /// line has the form `name = "level"`. Names and levels may be quoted,
+    /// and blank lines and lines starting with `#` are ignored.
+    ///
+    /// ```toml
+    /// # Catch naming mistakes early
+    /// "style::naming" = "deny"
+    /// unused = "allow"
+    /// ```
+    pub fn parse_config(&mut self, section: &str) -> Result<(), ParseError> {
+        for (line_index, line) in section.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid_line = || ParseError::InvalidLine(line_index + 1, line.to_string());
+            let equals = line.find('=').ok_or_else(invalid_line)?;
+            let name = unquote(&line[..equals]);
+            let level = unquote(&line[equals + 1..]);
+            if name.is_empty() {
+                return Err(invalid_line());
+            }
+
+            self.push(name.to_string(), level.parse()?, LevelSource::Config);
+        }
+
+        Ok(())
+    }
+
+    /// The setting that determines the level of a code, if any.
+    fn setting(&self, code: &str) -> Option<&Setting> {
+        let mut current = None::<&Setting>;
+        for setting in &self.settings {
+            if !setting.applies_to(code) {
+                continue;
+            }
+            match current {
+                Some(forbidden) if forbidden.level == Level::Forbid => {
+                    if setting.level == Level::Forbid {
+                        current = Some(setting);
+                    }
+                }
+                _ => current = Some(setting),
+            }
+        }
+        current
+    }
+
+    /// The level of a code, or `None` if no level was set for it.
+    pub fn level(&self, code: &str) -> Option<Level> {
+        self.setting(code).map(|setting| setting.level)
+    }
+
+    /// Apply the level of the code of a diagnostic, returning `None` if the
+    /// diagnostic is allowed.
+    ///
+    /// If a level was set for the code, the severity of the diagnostic is
+    /// changed to match it, and a note is added describing the setting that
+    /// caused it. Diagnostics without codes, or with codes that have no level
+    /// set, are returned unchanged.
+    pub fn apply<FileId>(&self, mut diagnostic: Diagnostic<FileId>) -> Option<Diagnostic<FileId>> {
+        let setting = match &diagnostic.code {
+            Some(code) => match self.setting(code) {
+                Some(setting) => setting,
+                None => return Some(diagnostic),
+            },
+            None => return Some(diagnostic),
+        };
+
+        diagnostic.severity = setting.level.severity()?;
+        let code = diagnostic.code.as_ref().unwrap();
+        let message = if *code == setting.name {
+            format!(
+                "`{}` is set to `{}` by {}",
+                code,
+                setting.level,
+                setting.describe()
+            )
+        } else {
+            format!(
+                "`{}` is set to `{}` by {}, which applies to the group `{}`",
+                code,
+                setting.level,
+                setting.describe(),
+                setting.name,
+            )
+        };
+        diagnostic.notes.push(Note::note(message));
+
+        Some(diagnostic)
+    }
+}
+
+/// Parse a level flag, along with a name that is joined to it.
+fn parse_flag(arg: &str) -> Result<(Level, Option<&str>), ParseError> {
+    // Long flags are joined to names with `=`, and short flags are joined
+    // to names directly.
+    let (flag, joined_name) = if arg.starts_with("--") {
+        let mut parts = arg.splitn(2, '=');
+        (parts.next().unwrap(), parts.next())
+    } else {
+        match (arg.get(..2), arg.get(2..)) {
+            (Some(flag), Some(name)) if !name.is_empty() => (flag, Some(name)),
+            _ => (arg, None),
+        }
+    };
+
+    let level = match flag {
+        "-A" | "--allow" => Level::Allow,
+        "-W" | "--warn" => Level::Warn,
+        "-D" | "--deny" => Level::Deny,
+        "-F" | "--forbid" => Level::Forbid,
+        _ => return Err(ParseError::UnknownFlag(arg.to_string())),
+    };
+
+    Ok((level, joined_name))
+}
+
+fn unquote(src: &str) -> &str {
+    let src = src.trim();
+    if src.len() >= 2 && src.starts_with('"') && src.ends_with('"') {
+        &src[1..src.len() - 1]
+    } else {
+        src
+    }
+}
+
+/// An error that occurred while parsing levels.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ParseError {
+    /// An argument was not a level flag.
+    UnknownFlag(String),
+    /// A level flag was not followed by a name.
+    MissingName(String),
+    /// A level was not one of `allow`, `warn`, `deny`, or `forbid`.
+    InvalidLevel(String),
+    /// A line of configuration was not of the form `name = "level"`. The line
+    /// number starts at `1`.
+    InvalidLine(usize, String),
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnknownFlag(arg) => write!(f, "unknown level flag `{}`", arg),
+            ParseError::MissingName(flag) => write!(f, "expected a name after `{}`", flag),
+            ParseError::InvalidLevel(level) => write!(
+                f,
+                "invalid level `{}`, expected one of `allow`, `warn`, `deny`, or `forbid`",
+                level,
+            ),
+            ParseError::InvalidLine(line_number, line) => write!(
+                f,
+                "invalid level configuration on line {}: `{}`",
+                line_number, line,
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn groups_are_hierarchical() {
+        let levels = LintLevels::new().with_level("style::naming", Level::Deny);
+
+        assert_eq!(levels.level("style::naming"), Some(Level::Deny));
+        assert_eq!(levels.level("style::naming::snake_case"), Some(Level::Deny));
+        assert_eq!(levels.level("style::naming_conventions"), None);
+        assert_eq!(levels.level("style"), None);
+    }
+
+    #[test]
+    fn later_settings_override_earlier_ones() {
+        let levels = LintLevels::new()
+            .with_level("unused", Level::Allow)
+            .with_level("unused::variables", Level::Warn)
+            .with_level("unused::imports", Level::Deny)
+            .with_level("unused::imports", Level::Allow);
+
+        assert_eq!(levels.level("unused::variables"), Some(Level::Warn));
+        assert_eq!(levels.level("unused::imports"), Some(Level::Allow));
+        assert_eq!(levels.level("unused::labels"), Some(Level::Allow));
+    }
+
+    #[test]
+    fn forbid_can_not_be_overridden() {
+        let levels = LintLevels::new()
+            .with_level("unsafe", Level::Forbid)
+            .with_level("unsafe::blocks", Level::Allow)
+            .with_level("unsafe", Level::Warn);
+
+        assert_eq!(levels.level("unsafe::blocks"), Some(Level::Forbid));
+        assert_eq!(levels.level("unsafe"), Some(Level::Forbid));
+    }
+
+    #[test]
+    fn parse_args() {
+        let mut levels = LintLevels::new();
+        let args = ["-W", "a", "-Db", "--allow", "c", "--forbid=d"];
+        levels.parse_args(args.iter().cloned()).unwrap();
+
+        assert_eq!(levels.level("a"), Some(Level::Warn));
+        assert_eq!(levels.level("b"), Some(Level::Deny));
+        assert_eq!(levels.level("c"), Some(Level::Allow));
+        assert_eq!(levels.level("d"), Some(Level::Forbid));
+    }
+
+    #[test]
+    fn parse_args_errors() {
+        let mut levels = LintLevels::new();
+
+        assert_eq!(
+            levels.parse_args(["-X", "a"].iter().cloned()),
+            Err(ParseError::UnknownFlag("-X".to_string())),
+        );
+        assert_eq!(
+            levels.parse_args(["-W", "-D", "a"].iter().cloned()),
+            Err(ParseError::MissingName("-W".to_string())),
+        );
+        assert_eq!(
+            levels.parse_args(["--deny="].iter().cloned()),
+            Err(ParseError::MissingName("--deny=".to_string())),
+        );
+    }
+
+    #[test]
+    fn parse_config() {
+        let mut levels = LintLevels::new();
+        levels
+            .parse_config(
+                r#"
+                    # comment
+                    "style::naming" = "deny"
+                    unused = allow
+                "#,
+            )
+            .unwrap();
+
+        assert_eq!(levels.level("style::naming::acronyms"), Some(Level::Deny));
+        assert_eq!(levels.level("unused"), Some(Level::Allow));
+
+        assert_eq!(
+            levels.parse_config("a = \"deny\"\nb = \"error\""),
+            Err(ParseError::InvalidLevel("error".to_string())),
+        );
+        assert_eq!(
+            levels.parse_config("\nunused allow"),
+            Err(ParseError::InvalidLine(2, "unused allow".to_string())),
+        );
+    }
+
+    #[test]
+    fn apply_adds_notes() {
+        let mut levels = LintLevels::new().with_level("style", Level::Warn);
+        levels
+            .parse_args(["-D", "style::naming"].iter().cloned())
+            .unwrap();
+        levels.parse_config("unused = \"allow\"").unwrap();
+
+        let diagnostic = Diagnostic::<()>::note().with_code("style::naming");
+        let diagnostic = levels.apply(diagnostic).unwrap();
+        assert_eq!(diagnostic.severity, Severity::Error);
+        assert_eq!(
+            diagnostic.notes,
+            [Note::note(
+                "`style::naming` is set to `deny` by `-D style::naming` on the command line",
+            )],
+        );
+
+        let diagnostic = Diagnostic::<()>::error().with_code("style::braces");
+        let diagnostic = levels.apply(diagnostic).unwrap();
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(
+            diagnostic.notes,
+            [Note::note(
+                "`style::braces` is set to `warn` by `warn(style)`, which applies to the group `style`",
+            )],
+        );
+
+        let diagnostic = Diagnostic::<()>::warning().with_code("unused::variables");
+        assert_eq!(levels.apply(diagnostic), None);
+
+        let diagnostic = Diagnostic::<()>::warning().with_code("correctness");
+        assert_eq!(levels.apply(diagnostic.clone()), Some(diagnostic));
+    }
+}
@@ -0,0 +1,274 @@
+//! Markup for styling parts of diagnostic messages.
+//!
+//! Diagnostics that opt in with [`Diagnostic::with_markup`] can use a small
+//! amount of markup in their messages, and in the messages of their labels,
+//! notes, and sub-diagnostics. Markup is styled by the terminal renderer when
+//! colors are enabled, and is written as plain text otherwise. The messages of
+//! other diagnostics are always written as-is.
+//!
+//! | Markup          | Style                 | Plain text     |
+//! |-----------------|-----------------------|----------------|
+//! | `` `code` ``    | [`Style::Code`]       | `` `code` ``   |
+//! | `*emphasis*`    | [`Style::Emphasis`]   | `emphasis`     |
+//! | `{+expected+}`  | [`Style::Expected`]   | `expected`     |
+//! | `{-found-}`     | [`Style::Found`]      | `found`        |
+//!
+//! The backticks around code are kept in the plain text, so that code can
+//! still be told apart from the rest of the message when colors are disabled.
+//! Emphasis must start and end next to non-whitespace characters, and can
+//! not start or end inside a word, so that messages like `a * b` and `2*3*4`
+//! are left as-is. Delimiters without a matching closing delimiter are
+//! written as-is, and styles can not be nested. A backslash can be used to
+//! escape `` ` ``, `*`, `{`, and `\` outside of styled text.
+//!
+//! ```rust
+//! use codespan_reporting::diagnostic::Diagnostic;
+//! use codespan_reporting::markup;
+//!
+//! let message = format!(
+//!     "expected {}, found {}",
+//!     markup::expected("Int"),
+//!     markup::found("String"),
+//! );
+//! let diagnostic = Diagnostic::<()>::error()
+//!     .with_message(&message)
+//!     .with_markup();
+//!
+//! assert_eq!(diagnostic.message, "expected {+Int+}, found {-String-}");
+//! assert_eq!(markup::to_plain(&message), "expected Int, found String");
+//! ```
+//!
+//! [`Diagnostic::with_markup`]: crate::diagnostic::Diagnostic::with_markup
+
+use alloc::format;
+use alloc::string::String;
+
+/// The style of a segment of marked up text.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Style {
+    /// Code, written between backticks.
+    Code,
+    /// Emphasized text, written between asterisks.
+    Emphasis,
+    /// The expected part of a mismatch, written between `{+` and `+}`.
+    Expected,
+    /// The part of a mismatch that was found instead, written between `{-`
+    /// and `-}`.
+    Found,
+}
+
+/// An iterator over the segments of marked up text, returned by [`segments`].
+#[derive(Clone, Debug)]
+pub struct Segments<'a> {
+    text: &'a str,
+    /// The character before the rest of the text.
+    prev: Option<char>,
+}
+
+impl<'a> Iterator for Segments<'a> {
+    type Item = (Option<Style>, &'a str);
+
+    fn next(&mut self) -> Option<(Option<Style>, &'a str)> {
+        let text = self.text;
+        let (segment, len) = match text.find(&['\\', '`', '*', '{'][..]) {
+            None if text.is_empty() => return None,
+            None => ((None, text), text.len()),
+            Some(start) if start > 0 => ((None, &text[..start]), start),
+            Some(_) => styled_segment(text, self.prev),
+        };
+        self.text = &text[len..];
+        self.prev = text[..len].chars().next_back();
+        Some(segment)
+    }
+}
+
+fn is_word_char(c: Option<char>) -> bool {
+    c.map_or(false, char::is_alphanumeric)
+}
+
+/// Parse a segment at the start of the text, which starts with one of the
+/// markup characters, returning the segment and the length of text that it
+/// was parsed from. The character before the text is used to tell if the
+/// segment starts inside a word.
+fn styled_segment(text: &str, prev: Option<char>) -> ((Option<Style>, &str), usize) {
+    let (style, open, close) = match text {
+        _ if text.starts_with('\\') => {
+            return match text[1..].chars().next() {
+                Some('\\') | Some('`') | Some('*') | Some('{') => ((None, &text[1..2]), 2),
+                _ => ((None, &text[..1]), 1),
+            };
+        }
+        _ if text.starts_with('`') => (Style::Code, "`", "`"),
+        _ if text.starts_with("{+") => (Style::Expected, "{+", "+}"),
+        _ if text.starts_with("{-") => (Style::Found, "{-", "-}"),
+        _ if text.starts_with('*') => (Style::Emphasis, "*", "*"),
+        _ => return ((None, &text[..1]), 1),
+    };
+
+    let content = &text[open.len()..];
+    let content = match content.find(close) {
+        Some(content_len) if content_len > 0 => &content[..content_len],
+        _ => return ((None, &text[..1]), 1),
+    };
+    let len = open.len() + content.len() + close.len();
+    let is_valid = match style {
+        Style::Emphasis => {
+            !content.starts_with(char::is_whitespace)
+                && !content.ends_with(char::is_whitespace)
+                && !is_word_char(prev)
+                && !is_word_char(text[len..].chars().next())
+        }
+        _ => true,
+    };
+    match style {
+        _ if !is_valid => ((None, &text[..1]), 1),
+        Style::Code => ((Some(style), &text[..len]), len),
+        _ => ((Some(style), content), len),
+    }
+}
+
+/// Split marked up text into segments of plain or styled text, with the
+/// markup removed.
+///
+/// ```rust
+/// use codespan_reporting::markup::{self, Style};
+///
+/// let segments = markup::segments("found *unit* `()`").collect::<Vec<_>>();
+///
+/// assert_eq!(
+///     segments,
+///     vec![
+///         (None, "found "),
+///         (Some(Style::Emphasis), "unit"),
+///         (None, " "),
+///         (Some(Style::Code), "`()`"),
+///     ],
+/// );
+/// ```
+pub fn segments(text: &str) -> Segments<'_> {
+    Segments { text, prev: None }
+}
+
+/// Convert marked up text into plain text, for use in outputs that do not
+/// support styling.
+pub fn to_plain(text: &str) -> String {
+    segments(text).map(|(_, text)| text).collect()
+}
+
+/// Escape any markup characters in the text, so that it is written as-is.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if let '\\' | '`' | '*' | '{' = c {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Mark up text as code. The text should not contain any backticks.
+pub fn code(text: &str) -> String {
+    format!("`{}`", text)
+}
+
+/// Mark up text as emphasized. The text should not contain any asterisks, or
+/// start or end with whitespace.
+pub fn emphasis(text: &str) -> String {
+    format!("*{}*", text)
+}
+
+/// Mark up text as the expected part of a mismatch. The text should not
+/// contain `+}`.
+pub fn expected(text: &str) -> String {
+    format!("{{+{}+}}", text)
+}
+
+/// Mark up text as the part of a mismatch that was found instead. The text
+/// should not contain `-}`.
+pub fn found(text: &str) -> String {
+    format!("{{-{}-}}", text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec;
+    use alloc::vec::Vec;
+
+    fn parse(text: &str) -> Vec<(Option<Style>, &str)> {
+        segments(text).collect()
+    }
+
+    #[test]
+    fn plain_text() {
+        assert_eq!(parse(""), vec![]);
+        assert_eq!(parse("unused variable"), vec![(None, "unused variable")]);
+    }
+
+    #[test]
+    fn styled_text() {
+        assert_eq!(
+            parse("expected {+`Int`+}, found {-*String*-}"),
+            vec![
+                (None, "expected "),
+                (Some(Style::Expected), "`Int`"),
+                (None, ", found "),
+                (Some(Style::Found), "*String*"),
+            ],
+        );
+    }
+
+    #[test]
+    fn code_is_written_as_is() {
+        assert_eq!(
+            parse("`*x*` and `{+y+}`"),
+            vec![
+                (Some(Style::Code), "`*x*`"),
+                (None, " and "),
+                (Some(Style::Code), "`{+y+}`"),
+            ],
+        );
+    }
+
+    #[test]
+    fn unmatched_delimiters() {
+        assert_eq!(to_plain("a * b * c"), "a * b * c");
+        assert_eq!(to_plain("2*3*4"), "2*3*4");
+        assert_eq!(to_plain("*a*b"), "*a*b");
+        assert_eq!(to_plain("*const T"), "*const T");
+        assert_eq!(to_plain("`` and `"), "`` and `");
+        assert_eq!(to_plain("{ {+} {--}"), "{ {+} {--}");
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(to_plain(r"\*x\* \{+y+} \`z\` \\ \n"), r"*x* {+y+} `z` \ \n");
+        assert_eq!(to_plain(r"trailing \"), r"trailing \");
+    }
+
+    #[test]
+    fn escape_round_trips() {
+        let text = r"`*x*` {+y+} {-z-} \ \n";
+        assert!(parse(&escape(text))
+            .iter()
+            .all(|(style, _)| style.is_none()));
+        assert_eq!(to_plain(&escape(text)), text);
+    }
+
+    #[test]
+    fn helpers() {
+        let message = [code("x"), emphasis("y"), expected("Int"), found("Bool")].concat();
+
+        assert_eq!(
+            parse(&message),
+            vec![
+                (Some(Style::Code), "`x`"),
+                (Some(Style::Emphasis), "y"),
+                (Some(Style::Expected), "Int"),
+                (Some(Style::Found), "Bool"),
+            ],
+        );
+    }
+}
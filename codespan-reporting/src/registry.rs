@@ -0,0 +1,196 @@
+//! A registry of diagnostic codes, along with their long-form explanations.
+//!
+//! ```rust
+//! use codespan_reporting::diagnostic::Severity;
+//! use codespan_reporting::registry::{CodeInfo, Registry};
+//!
+//! let mut registry = Registry::new();
+//! registry.add(
+//!     CodeInfo::new("E0308", Severity::Error, "mismatched types")
+//!         .with_explanation("Expected type did not match the received type."),
+//! );
+//!
+//! // Check that all of the codes that the tool can emit are registered
+//! assert!(registry.check(vec!["E0308"]).is_ok());
+//! assert!(registry.check(vec!["E0308", "E0599"]).is_err());
+//!
+//! assert_eq!(registry.get("E0308").unwrap().title, "mismatched types");
+//! ```
+
+use alloc::collections::BTreeMap;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::diagnostic::{Diagnostic, Severity};
+
+/// Metadata describing a diagnostic code.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CodeInfo {
+    /// The code, for example `E0308`.
+    pub code: String,
+    /// The severity that diagnostics with this code are reported at by default.
+    pub severity: Severity,
+    /// A short title, describing the problem in a way that makes sense on its
+    /// own.
+    pub title: String,
+    /// A long-form explanation of the problem, written in Markdown. This
+    /// should describe the causes of the problem, along with examples of
+    /// erroneous code and how to fix it.
+    pub explanation: String,
+}
+
+impl CodeInfo {
+    /// Create metadata for a new diagnostic code.
+    pub fn new(code: impl Into<String>, severity: Severity, title: impl Into<String>) -> CodeInfo {
+        CodeInfo {
+            code: code.into(),
+            severity,
+            title: title.into(),
+            explanation: String::new(),
+        }
+    }
+
+    /// Add a long-form explanation to the code.
+    pub fn with_explanation(mut self, explanation: impl Into<String>) -> CodeInfo {
+        self.explanation = explanation.into();
+        self
+    }
+
+    /// Create a new diagnostic with this code, its default severity, and its
+    /// title as the message.
+    pub fn to_diagnostic<FileId>(&self) -> Diagnostic<FileId> {
+        Diagnostic::new(self.severity)
+            .with_code(self.code.as_str())
+            .with_message(self.title.as_str())
+    }
+}
+
+/// A registry that maps diagnostic codes to their metadata.
+#[derive(Clone, Debug, Default)]
+pub struct Registry {
+    codes: BTreeMap<String, CodeInfo>,
+}
+
+impl Registry {
+    /// Create a new, empty registry.
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// Add a code to the registry, returning the metadata of any code that
+    /// it replaces.
+    pub fn add(&mut self, info: CodeInfo) -> Option<CodeInfo> {
+        self.codes.insert(info.code.clone(), info)
+    }
+
+    /// Get the metadata of a code.
+    pub fn get(&self, code: &str) -> Option<&CodeInfo> {
+        self.codes.get(code)
+    }
+
+    /// An iterator over the metadata of the registered codes, ordered by code.
+    pub fn iter(&self) -> impl Iterator<Item = &CodeInfo> {
+        self.codes.values()
+    }
+
+    /// Check that each of the given codes is registered. This is intended to
+    /// be called on startup with all of the codes that a tool can emit.
+    pub fn check<'a>(
+        &self,
+        codes: impl IntoIterator<Item = &'a str>,
+    ) -> Result<(), UnregisteredCodes> {
+        let mut unregistered = Vec::<String>::new();
+        for code in codes {
+            if !self.codes.contains_key(code) && !unregistered.iter().any(|c| c == code) {
+                unregistered.push(code.to_string());
+            }
+        }
+
+        if unregistered.is_empty() {
+            Ok(())
+        } else {
+            Err(UnregisteredCodes {
+                codes: unregistered,
+            })
+        }
+    }
+}
+
+/// An error returned when some codes were not found in a [`Registry`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnregisteredCodes {
+    /// The codes that were not registered, in the order they were checked.
+    pub codes: Vec<String>,
+}
+
+impl fmt::Display for UnregisteredCodes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "unregistered diagnostic codes: ")?;
+        for (index, code) in self.codes.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", code)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for UnregisteredCodes {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use alloc::vec;
+
+    fn registry() -> Registry {
+        let mut registry = Registry::new();
+        registry.add(CodeInfo::new("E0599", Severity::Error, "no method found"));
+        registry.add(CodeInfo::new("W0001", Severity::Warning, "unused variable"));
+        registry
+    }
+
+    #[test]
+    fn add_replaces_existing_codes() {
+        let mut registry = registry();
+        let previous = registry.add(CodeInfo::new("W0001", Severity::Note, "unused binding"));
+
+        assert_eq!(previous.unwrap().title, "unused variable");
+        assert_eq!(registry.get("W0001").unwrap().severity, Severity::Note);
+    }
+
+    #[test]
+    fn iter_is_ordered_by_code() {
+        let registry = registry();
+        let codes = registry.iter().map(|info| info.code.as_str());
+
+        assert_eq!(codes.collect::<Vec<_>>(), vec!["E0599", "W0001"]);
+    }
+
+    #[test]
+    fn check_reports_unregistered_codes() {
+        let registry = registry();
+        let error = registry
+            .check(vec!["E0599", "E0308", "W0001", "E0308", "E0425"])
+            .unwrap_err();
+
+        assert_eq!(error.codes, vec!["E0308", "E0425"]);
+        assert_eq!(
+            error.to_string(),
+            "unregistered diagnostic codes: E0308, E0425",
+        );
+    }
+
+    #[test]
+    fn to_diagnostic() {
+        let registry = registry();
+        let diagnostic = registry.get("W0001").unwrap().to_diagnostic::<()>();
+
+        assert_eq!(diagnostic.severity, Severity::Warning);
+        assert_eq!(diagnostic.code.as_deref(), Some("W0001"));
+        assert_eq!(diagnostic.message, "unused variable");
+    }
+}
@@ -0,0 +1,436 @@
+//! Suppressing diagnostics with comments in source files.
+//!
+//! Suppression comments contain a directive, followed by an optional list of
+//! the diagnostic codes that they suppress. If no codes are given, diagnostics
+//! with any code are suppressed. With the default [`SuppressionSyntax`]:
+//!
+//! ```text
+//! // codespan-ignore: E0042
+//! let x = 1; // the line after a comment on its own line is suppressed
+//! let y = 2; // codespan-ignore: E0042, E0043 (the same line is suppressed)
+//! // codespan-ignore-file: W0001 (the whole file is suppressed)
+//! ```
+//!
+//! Diagnostics are matched against suppressions by the location of their first
+//! primary label.
+//!
+//! ```rust
+//! use codespan_reporting::diagnostic::{Diagnostic, Label};
+//! use codespan_reporting::files::SimpleFile;
+//! use codespan_reporting::suppress::{SuppressionSyntax, Suppressions};
+//!
+//! let file = SimpleFile::new("main.rs", "let x = 1; // codespan-ignore: W0001\nlet y = 2;\n");
+//! let diagnostics = vec![
+//!     Diagnostic::warning()
+//!         .with_code("W0001")
+//!         .with_labels(vec![Label::primary((), 4..5)]),
+//!     Diagnostic::warning()
+//!         .with_code("W0001")
+//!         .with_labels(vec![Label::primary((), 41..42)]),
+//! ];
+//!
+//! let mut suppressions = Suppressions::scan(&file, vec![()], &SuppressionSyntax::default());
+//! let diagnostics = suppressions.filter(diagnostics);
+//!
+//! assert_eq!(diagnostics.len(), 1);
+//! assert!(suppressions.unused_diagnostics().is_empty());
+//! ```
+
+use alloc::borrow::ToOwned;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::ops::Range;
+
+use crate::diagnostic::{Diagnostic, Label, LabelStyle};
+use crate::files::{self, Files};
+
+/// The syntax of suppression comments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SuppressionSyntax {
+    /// The prefixes that start line comments, for example `//` or `#`.
+    /// Defaults to `["//", "#"]`.
+    pub comment_prefixes: Vec<String>,
+    /// The directive that follows the comment prefix. Directives that are
+    /// followed by `-file` suppress diagnostics in the whole file.
+    /// Defaults to `codespan-ignore`.
+    pub directive: String,
+}
+
+impl Default for SuppressionSyntax {
+    fn default() -> SuppressionSyntax {
+        SuppressionSyntax {
+            comment_prefixes: alloc::vec!["//".to_owned(), "#".to_owned()],
+            directive: "codespan-ignore".to_owned(),
+        }
+    }
+}
+
+/// The region of source code that a suppression applies to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Scope {
+    /// The line containing the suppression comment, when the comment follows
+    /// some code.
+    SameLine,
+    /// The line after the suppression comment, when the comment is on a line
+    /// of its own. Lines containing only comments are skipped.
+    NextLine,
+    /// The whole file.
+    File,
+}
+
+/// A suppression comment.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suppression<FileId> {
+    /// The file containing the comment.
+    pub file_id: FileId,
+    /// The range of the comment in the file.
+    pub range: Range<usize>,
+    /// The codes that are suppressed, or an empty list if all codes are
+    /// suppressed.
+    pub codes: Vec<String>,
+    /// The region that the suppression applies to.
+    pub scope: Scope,
+    /// The byte range of the source code that is suppressed.
+    pub region: Range<usize>,
+    used: bool,
+}
+
+impl<FileId> Suppression<FileId>
+where
+    FileId: PartialEq,
+{
+    /// Returns `true` if the suppression applies to the given diagnostic.
+    pub fn matches(&self, diagnostic: &Diagnostic<FileId>) -> bool {
+        let label = match primary_label(diagnostic) {
+            Some(label) => label,
+            None => return false,
+        };
+        let is_code_suppressed = self.codes.is_empty()
+            || match &diagnostic.code {
+                Some(code) => self.codes.iter().any(|c| c == code),
+                None => false,
+            };
+
+        is_code_suppressed
+            && label.file_id == self.file_id
+            && self.region.start <= label.range.start
+            && label.range.start <= self.region.end
+    }
+
+    /// Returns `true` if the suppression matched any diagnostics.
+    pub fn is_used(&self) -> bool {
+        self.used
+    }
+}
+
+/// The suppression comments found in a set of files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Suppressions<FileId> {
+    suppressions: Vec<Suppression<FileId>>,
+}
+
+impl<FileId> Suppressions<FileId>
+where
+    FileId: Copy + PartialEq,
+{
+    /// Scan some files for suppression comments.
+    pub fn scan<'files, F>(
+        files: &'files F,
+        file_ids: impl IntoIterator<Item = FileId>,
+        syntax: &SuppressionSyntax,
+    ) -> Suppressions<FileId>
+    where
+        F: Files<'files, FileId = FileId>,
+    {
+        let mut suppressions = Vec::new();
+        for file_id in file_ids {
+            if let Some(source) = files.source(file_id) {
+                scan_source(&mut suppressions, file_id, source.as_ref(), syntax);
+            }
+        }
+
+        Suppressions { suppressions }
+    }
+
+    /// The suppression comments, in the order they were found.
+    pub fn suppressions(&self) -> &[Suppression<FileId>] {
+        &self.suppressions
+    }
+
+    /// Returns `true` if the diagnostic is suppressed, marking the suppressions
+    /// that match it as used.
+    pub fn is_suppressed(&mut self, diagnostic: &Diagnostic<FileId>) -> bool {
+        let mut is_suppressed = false;
+        for suppression in &mut self.suppressions {
+            if suppression.matches(diagnostic) {
+                suppression.used = true;
+                is_suppressed = true;
+            }
+        }
+        is_suppressed
+    }
+
+    /// Remove the suppressed diagnostics, marking the suppressions that match
+    /// them as used.
+    pub fn filter(
+        &mut self,
+        diagnostics: impl IntoIterator<Item = Diagnostic<FileId>>,
+    ) -> Vec<Diagnostic<FileId>> {
+        (diagnostics.into_iter())
+            .filter(|diagnostic| !self.is_suppressed(diagnostic))
+            .collect()
+    }
+
+    /// Warnings for the suppressions that have not matched any diagnostics.
+    /// This should be called after all diagnostics have been filtered.
+    pub fn unused_diagnostics(&self) -> Vec<Diagnostic<FileId>> {
+        (self.suppressions.iter())
+            .filter(|suppression| !suppression.used)
+            .map(|suppression| {
+                let message = match suppression.codes.as_slice() {
+                    [] => "unused suppression".to_owned(),
+                    codes => format!("unused suppression for `{}`", codes.join("`, `")),
+                };
+
+                Diagnostic::warning()
+                    .with_message(message)
+                    .with_labels(alloc::vec![Label::primary(
+                        suppression.file_id,
+                        suppression.range.clone(),
+                    )
+                    .with_message("no diagnostics were suppressed by this comment")])
+            })
+            .collect()
+    }
+}
+
+fn primary_label<FileId>(diagnostic: &Diagnostic<FileId>) -> Option<&Label<FileId>> {
+    (diagnostic.labels.iter()).find(|label| label.style == LabelStyle::Primary)
+}
+
+/// The range of the line with the given index, excluding its line terminator.
+fn line_range(source: &str, line_starts: &[usize], line_index: usize) -> Range<usize> {
+    let start = line_starts[line_index];
+    let end = line_starts
+        .get(line_index + 1)
+        .map_or(source.len(), |&end| end);
+    start..start + source[start..end].trim_end().len()
+}
+
+/// Find a suppression directive in a line, returning the start of its
+/// comment, along with its scope and codes. Every occurrence of the comment
+/// prefixes is tried in order, because they can also appear earlier in the
+/// line, for example in string literals or attributes.
+fn find_directive<'a>(
+    line: &'a str,
+    syntax: &SuppressionSyntax,
+) -> Option<(usize, (bool, Vec<&'a str>))> {
+    let mut comment_starts = (syntax.comment_prefixes.iter())
+        .flat_map(|prefix| line.match_indices(prefix.as_str()))
+        .map(|(comment_start, _)| comment_start)
+        .collect::<Vec<_>>();
+    comment_starts.sort_unstable();
+
+    (comment_starts.into_iter()).find_map(|comment_start| {
+        let directive = parse_directive(&line[comment_start..], syntax)?;
+        Some((comment_start, directive))
+    })
+}
+
+/// Parse a suppression directive in a comment, returning its scope and codes.
+fn parse_directive<'a>(
+    comment: &'a str,
+    syntax: &SuppressionSyntax,
+) -> Option<(bool, Vec<&'a str>)> {
+    let prefix =
+        (syntax.comment_prefixes.iter()).find(|prefix| comment.starts_with(prefix.as_str()))?;
+    let text = comment[prefix.len()..].trim_start();
+    if !text.starts_with(syntax.directive.as_str()) {
+        return None;
+    }
+
+    let text = &text[syntax.directive.len()..];
+    let is_file = text.starts_with("-file");
+    let text = if is_file {
+        &text["-file".len()..]
+    } else {
+        text
+    };
+    let codes = match text.trim_end() {
+        "" => Vec::new(),
+        text if text.starts_with(':') => (text[1..]
+            .split(|ch: char| ch == ',' || ch.is_whitespace()))
+        .filter(|code| !code.is_empty())
+        .collect(),
+        _ => return None,
+    };
+
+    Some((is_file, codes))
+}
+
+fn scan_source<FileId: Copy>(
+    suppressions: &mut Vec<Suppression<FileId>>,
+    file_id: FileId,
+    source: &str,
+    syntax: &SuppressionSyntax,
+) {
+    let line_starts = files::line_starts(source).collect::<Vec<_>>();
+    let lines = (0..line_starts.len())
+        .map(|line_index| line_range(source, &line_starts, line_index))
+        .collect::<Vec<_>>();
+    let is_comment_line = |range: &Range<usize>| {
+        let line = source[range.clone()].trim_start();
+        (syntax.comment_prefixes.iter()).any(|prefix| line.starts_with(prefix.as_str()))
+    };
+
+    for (line_index, range) in lines.iter().enumerate() {
+        let line = &source[range.clone()];
+        let (comment_start, (is_file, codes)) = match find_directive(line, syntax) {
+            Some(directive) => directive,
+            None => continue,
+        };
+
+        let (scope, region) = if is_file {
+            (Scope::File, 0..source.len())
+        } else if line[..comment_start].trim().is_empty() {
+            let next_line = (lines[line_index + 1..].iter()).find(|range| !is_comment_line(range));
+            match next_line {
+                Some(next_line) => (Scope::NextLine, next_line.clone()),
+                None => (Scope::NextLine, source.len()..source.len()),
+            }
+        } else {
+            (Scope::SameLine, range.clone())
+        };
+
+        suppressions.push(Suppression {
+            file_id,
+            range: range.start + comment_start..range.end,
+            codes: codes.into_iter().map(ToOwned::to_owned).collect(),
+            scope,
+            region,
+            used: false,
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::files::SimpleFiles;
+    use alloc::vec;
+
+    fn warning(file_id: usize, code: &str, range: Range<usize>) -> Diagnostic<usize> {
+        Diagnostic::warning()
+            .with_code(code)
+            .with_labels(vec![Label::primary(file_id, range)])
+    }
+
+    fn scan(source: &'static str) -> (usize, Suppressions<usize>) {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", source);
+        let suppressions = Suppressions::scan(&files, vec![file_id], &SuppressionSyntax::default());
+        (file_id, suppressions)
+    }
+
+    #[test]
+    fn scopes() {
+        let (_, suppressions) = scan(
+            "a // codespan-ignore: A\n// codespan-ignore: B, C\n# codespan-ignore\nd\n// codespan-ignore-file:D\n",
+        );
+        let suppressions = suppressions.suppressions();
+
+        assert_eq!(suppressions.len(), 4);
+        assert_eq!(suppressions[0].scope, Scope::SameLine);
+        assert_eq!(suppressions[0].codes, ["A"]);
+        assert_eq!(suppressions[0].region, 0..23);
+        assert_eq!(suppressions[0].range, 2..23);
+        // Skips to the next line that is not a comment
+        assert_eq!(suppressions[1].scope, Scope::NextLine);
+        assert_eq!(suppressions[1].codes, ["B", "C"]);
+        assert_eq!(suppressions[1].region, 67..68);
+        assert_eq!(suppressions[2].scope, Scope::NextLine);
+        assert!(suppressions[2].codes.is_empty());
+        assert_eq!(suppressions[2].region, 67..68);
+        assert_eq!(suppressions[3].scope, Scope::File);
+        assert_eq!(suppressions[3].codes, ["D"]);
+        assert_eq!(suppressions[3].region, 0..95);
+    }
+
+    #[test]
+    fn prefixes_before_comments() {
+        let (_, suppressions) = scan(
+            "let u = \"http://x\"; // codespan-ignore: A\n#[derive(Debug)] // codespan-ignore\n",
+        );
+        let suppressions = suppressions.suppressions();
+
+        assert_eq!(suppressions.len(), 2);
+        assert_eq!(suppressions[0].scope, Scope::SameLine);
+        assert_eq!(suppressions[0].codes, ["A"]);
+        assert_eq!(suppressions[0].range, 20..41);
+        assert_eq!(suppressions[1].scope, Scope::SameLine);
+        assert!(suppressions[1].codes.is_empty());
+        assert_eq!(suppressions[1].range, 59..77);
+    }
+
+    #[test]
+    fn ignores_other_comments() {
+        let (_, suppressions) = scan("// codespan-ignored: A\n// see codespan-ignore: B\nx\n");
+
+        assert!(suppressions.suppressions().is_empty());
+    }
+
+    #[test]
+    fn custom_syntax() {
+        let mut files = SimpleFiles::new();
+        let file_id = files.add("test", "x -- lint-allow: A\n");
+        let syntax = SuppressionSyntax {
+            comment_prefixes: vec!["--".to_owned()],
+            directive: "lint-allow".to_owned(),
+        };
+        let suppressions = Suppressions::scan(&files, vec![file_id], &syntax);
+
+        assert_eq!(suppressions.suppressions().len(), 1);
+        assert_eq!(suppressions.suppressions()[0].codes, ["A"]);
+    }
+
+    #[test]
+    fn filters_diagnostics() {
+        let (file_id, mut suppressions) =
+            scan("// codespan-ignore: A\nlet x = 1;\nlet y = 2; // codespan-ignore\nlet z = 3;\n");
+
+        let diagnostics = vec![
+            warning(file_id, "A", 26..27),
+            warning(file_id, "B", 26..27),
+            warning(file_id, "B", 37..38),
+            warning(file_id, "A", 67..68),
+            Diagnostic::warning().with_code("A"),
+        ];
+        let remaining = suppressions.filter(diagnostics.clone());
+
+        assert_eq!(
+            remaining,
+            [
+                diagnostics[1].clone(),
+                diagnostics[3].clone(),
+                diagnostics[4].clone(),
+            ],
+        );
+        assert!(suppressions.unused_diagnostics().is_empty());
+    }
+
+    #[test]
+    fn reports_unused_suppressions() {
+        let (file_id, mut suppressions) =
+            scan("// codespan-ignore: A, B\nx\n// codespan-ignore\ny\n");
+
+        let remaining = suppressions.filter(vec![warning(file_id, "A", 25..26)]);
+        let unused = suppressions.unused_diagnostics();
+
+        assert!(remaining.is_empty());
+        assert_eq!(unused.len(), 1);
+        assert_eq!(unused[0].message, "unused suppression");
+        assert_eq!(unused[0].labels[0].range, 27..45);
+    }
+}
@@ -6,14 +6,17 @@ use termcolor::{ColorChoice, WriteColor};
 
 use crate::diagnostic::Diagnostic;
 use crate::files::Files;
+use crate::registry::CodeInfo;
 
 mod config;
+mod emitter;
 mod renderer;
 mod views;
 
 pub use termcolor;
 
-pub use self::config::{Chars, Config, DisplayStyle, Styles};
+pub use self::config::{Chars, Config, DisplayStyle, SeverityNames, Styles};
+pub use self::emitter::Emitter;
 
 /// A command line argument that configures the coloring of the output.
 ///
@@ -89,21 +92,36 @@ pub fn emit<'files, F: Files<'files>>(
     diagnostic: &Diagnostic<F::FileId>,
 ) -> io::Result<()> {
     use self::renderer::Renderer;
-    use self::views::{RichDiagnostic, ShortDiagnostic};
+    use self::views::{host_labels, RichDiagnostic, ShortDiagnostic};
 
+    let diagnostic = host_labels(files, diagnostic);
     let mut renderer = Renderer::new(writer, config);
+    renderer.set_markup(diagnostic.markup);
     match config.display_style {
-        DisplayStyle::Rich => RichDiagnostic::new(diagnostic).render(files, &mut renderer),
-        DisplayStyle::Short => ShortDiagnostic::new(diagnostic).render(files, &mut renderer),
+        DisplayStyle::Rich => RichDiagnostic::new(&diagnostic).render(files, &mut renderer),
+        DisplayStyle::Short => ShortDiagnostic::new(&diagnostic).render(files, &mut renderer),
     }
 }
 
+/// Emit the long-form explanation of a diagnostic code, for example in response
+/// to a command line flag like `--explain E0308`.
+pub fn explain(writer: &mut dyn WriteColor, config: &Config, info: &CodeInfo) -> io::Result<()> {
+    use self::renderer::Renderer;
+
+    let mut renderer = Renderer::new(writer, config);
+    renderer.render_header(None, info.severity, Some(&info.code), &info.title)?;
+    renderer.render_empty()?;
+    renderer.render_explanation(&info.explanation)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     use crate::diagnostic::Label;
     use crate::files::SimpleFiles;
+    use std::vec;
+    use std::vec::Vec;
 
     #[test]
     fn unsized_emit() {
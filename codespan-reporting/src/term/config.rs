@@ -1,7 +1,10 @@
 use std::io;
+use std::string::String;
 use termcolor::{Color, ColorSpec};
 
-use crate::diagnostic::{LabelStyle, Severity};
+use crate::catalog::Catalog;
+use crate::diagnostic::{LabelStyle, NoteKind, Severity, Tag};
+use crate::markup;
 
 /// Configures how a diagnostic is rendered.
 #[derive(Clone, Debug)]
@@ -14,10 +17,22 @@ pub struct Config {
     /// Column width of tabs.
     /// Defaults to: `4`.
     pub tab_width: usize,
+    /// The maximum number of macro expansions to show when rendering the
+    /// expansion backtrace of a diagnostic.
+    /// Defaults to: `8`.
+    pub max_expansion_depth: usize,
+    /// Whether to show the [fingerprint] of each diagnostic, for tracking
+    /// diagnostics across changes to the source code.
+    /// Defaults to: `false`.
+    ///
+    /// [fingerprint]: crate::diagnostic::Diagnostic::fingerprint
+    pub show_fingerprints: bool,
     /// Styles to use when rendering the diagnostic.
     pub styles: Styles,
     /// Characters to use when rendering the diagnostic.
     pub chars: Chars,
+    /// Names to use for severities when rendering the diagnostic.
+    pub severity_names: SeverityNames,
 }
 
 impl Default for Config {
@@ -25,8 +40,11 @@ impl Default for Config {
         Config {
             display_style: DisplayStyle::Rich,
             tab_width: 4,
+            max_expansion_depth: 8,
+            show_fingerprints: false,
             styles: Styles::default(),
             chars: Chars::default(),
+            severity_names: SeverityNames::default(),
         }
     }
 }
@@ -162,6 +180,62 @@ pub struct Styles {
     /// The style to use when rendering the note bullets.
     /// Defaults `fg:blue` (or `fg:cyan` on windows).
     pub note_bullet: ColorSpec,
+    /// The style to use when rendering the titles of plain notes.
+    /// Defaults to `bold intense`.
+    pub note_title_plain: ColorSpec,
+    /// The style to use when rendering the titles of `note` notes.
+    /// Defaults to `fg:green bold intense`.
+    pub note_title_note: ColorSpec,
+    /// The style to use when rendering the titles of `help` notes.
+    /// Defaults to `fg:cyan bold intense`.
+    pub note_title_help: ColorSpec,
+
+    /// The style to use when rendering source code covered by the primary
+    /// labels of diagnostics tagged with [`Tag::Unnecessary`].
+    /// Defaults to `dimmed`.
+    ///
+    /// [`Tag::Unnecessary`]: crate::diagnostic::Tag::Unnecessary
+    pub tag_unnecessary: ColorSpec,
+    /// The style to use when rendering source code covered by the primary
+    /// labels of diagnostics tagged with [`Tag::Deprecated`].
+    /// Defaults to `strikethrough`.
+    ///
+    /// [`Tag::Deprecated`]: crate::diagnostic::Tag::Deprecated
+    pub tag_deprecated: ColorSpec,
+
+    /// The style to use when rendering text inserted by a suggestion.
+    /// Defaults to `fg:green`.
+    pub suggestion_insertion: ColorSpec,
+    /// The style to use when rendering text deleted by a suggestion.
+    /// Defaults to `fg:red`.
+    pub suggestion_deletion: ColorSpec,
+
+    /// The style to use when rendering headings in explanations.
+    /// Defaults to `bold intense`.
+    pub explanation_heading: ColorSpec,
+    /// The style to use when rendering emphasized text in explanations.
+    /// Defaults to `bold`.
+    pub explanation_emphasis: ColorSpec,
+    /// The style to use when rendering code in explanations.
+    /// Defaults to `fg:yellow`.
+    pub explanation_code: ColorSpec,
+
+    /// The style to use when rendering code in messages, on top of the style
+    /// of the surrounding message.
+    /// Defaults to `fg:yellow`.
+    pub message_code: ColorSpec,
+    /// The style to use when rendering emphasized text in messages, on top of
+    /// the style of the surrounding message.
+    /// Defaults to `bold`.
+    pub message_emphasis: ColorSpec,
+    /// The style to use when rendering the expected part of a mismatch in
+    /// messages, on top of the style of the surrounding message.
+    /// Defaults to `fg:green bold`.
+    pub message_expected: ColorSpec,
+    /// The style to use when rendering the part of a mismatch that was found
+    /// instead in messages, on top of the style of the surrounding message.
+    /// Defaults to `fg:red bold`.
+    pub message_found: ColorSpec,
 }
 
 impl Styles {
@@ -188,6 +262,34 @@ impl Styles {
         }
     }
 
+    /// The style used to mark the title of a note of a given kind.
+    pub fn note_title(&self, kind: NoteKind) -> &ColorSpec {
+        match kind {
+            NoteKind::Plain => &self.note_title_plain,
+            NoteKind::Note => &self.note_title_note,
+            NoteKind::Help => &self.note_title_help,
+        }
+    }
+
+    /// The style used to mark source code covered by the primary labels of
+    /// tagged diagnostics.
+    pub fn tag(&self, tag: Tag) -> &ColorSpec {
+        match tag {
+            Tag::Unnecessary => &self.tag_unnecessary,
+            Tag::Deprecated => &self.tag_deprecated,
+        }
+    }
+
+    /// The style used to mark a segment of marked up text in a message.
+    pub fn message(&self, style: markup::Style) -> &ColorSpec {
+        match style {
+            markup::Style::Code => &self.message_code,
+            markup::Style::Emphasis => &self.message_emphasis,
+            markup::Style::Expected => &self.message_expected,
+            markup::Style::Found => &self.message_found,
+        }
+    }
+
     #[doc(hidden)]
     pub fn with_blue(blue: Color) -> Styles {
         let header = ColorSpec::new().set_bold(true).set_intense(true).clone();
@@ -198,7 +300,7 @@ impl Styles {
             header_warning: header.clone().set_fg(Some(Color::Yellow)).clone(),
             header_note: header.clone().set_fg(Some(Color::Green)).clone(),
             header_help: header.clone().set_fg(Some(Color::Cyan)).clone(),
-            header_message: header,
+            header_message: header.clone(),
 
             primary_label_bug: ColorSpec::new().set_fg(Some(Color::Red)).clone(),
             primary_label_error: ColorSpec::new().set_fg(Some(Color::Red)).clone(),
@@ -210,6 +312,30 @@ impl Styles {
             line_number: ColorSpec::new().set_fg(Some(blue)).clone(),
             source_border: ColorSpec::new().set_fg(Some(blue)).clone(),
             note_bullet: ColorSpec::new().set_fg(Some(blue)).clone(),
+            note_title_plain: header.clone(),
+            note_title_note: header.clone().set_fg(Some(Color::Green)).clone(),
+            note_title_help: header.clone().set_fg(Some(Color::Cyan)).clone(),
+
+            tag_unnecessary: ColorSpec::new().set_dimmed(true).clone(),
+            tag_deprecated: ColorSpec::new().set_strikethrough(true).clone(),
+
+            suggestion_insertion: ColorSpec::new().set_fg(Some(Color::Green)).clone(),
+            suggestion_deletion: ColorSpec::new().set_fg(Some(Color::Red)).clone(),
+
+            explanation_heading: header.clone(),
+            explanation_emphasis: ColorSpec::new().set_bold(true).clone(),
+            explanation_code: ColorSpec::new().set_fg(Some(Color::Yellow)).clone(),
+
+            message_code: ColorSpec::new().set_fg(Some(Color::Yellow)).clone(),
+            message_emphasis: ColorSpec::new().set_bold(true).clone(),
+            message_expected: ColorSpec::new()
+                .set_fg(Some(Color::Green))
+                .set_bold(true)
+                .clone(),
+            message_found: ColorSpec::new()
+                .set_fg(Some(Color::Red))
+                .set_bold(true)
+                .clone(),
         }
     }
 }
@@ -280,6 +406,15 @@ pub struct Chars {
     /// The character to use for the left of a multi-line label.
     /// Defaults to: `'│'`.
     pub multi_left: char,
+
+    /// The character to use for marking text inserted by a suggestion, and
+    /// for the left border of lines added by a suggestion.
+    /// Defaults to: `'+'`.
+    pub suggestion_insertion: char,
+    /// The character to use for the left border of lines removed by a
+    /// suggestion.
+    /// Defaults to: `'-'`.
+    pub suggestion_deletion: char,
 }
 
 impl Chars {
@@ -327,6 +462,95 @@ impl Default for Chars {
             multi_bottom_left: '╰',
             multi_bottom: '─',
             multi_left: '│',
+
+            suggestion_insertion: '+',
+            suggestion_deletion: '-',
+        }
+    }
+}
+
+/// Names to use for severities, in the headers of diagnostics and the titles
+/// of notes.
+#[derive(Clone, Debug)]
+pub struct SeverityNames {
+    /// The name of [`Severity::Bug`].
+    /// Defaults to: `"bug"`.
+    ///
+    /// [`Severity::Bug`]: crate::diagnostic::Severity::Bug
+    pub bug: String,
+    /// The name of [`Severity::Error`].
+    /// Defaults to: `"error"`.
+    ///
+    /// [`Severity::Error`]: crate::diagnostic::Severity::Error
+    pub error: String,
+    /// The name of [`Severity::Warning`].
+    /// Defaults to: `"warning"`.
+    ///
+    /// [`Severity::Warning`]: crate::diagnostic::Severity::Warning
+    pub warning: String,
+    /// The name of [`Severity::Note`], which is also the default title of
+    /// [`NoteKind::Note`] notes.
+    /// Defaults to: `"note"`.
+    ///
+    /// [`Severity::Note`]: crate::diagnostic::Severity::Note
+    /// [`NoteKind::Note`]: crate::diagnostic::NoteKind::Note
+    pub note: String,
+    /// The name of [`Severity::Help`], which is also the default title of
+    /// [`NoteKind::Help`] notes.
+    /// Defaults to: `"help"`.
+    ///
+    /// [`Severity::Help`]: crate::diagnostic::Severity::Help
+    /// [`NoteKind::Help`]: crate::diagnostic::NoteKind::Help
+    pub help: String,
+}
+
+impl SeverityNames {
+    /// Look up the names of severities in a catalog, using the messages with
+    /// the identifiers `severity-bug`, `severity-error`, `severity-warning`,
+    /// `severity-note`, and `severity-help`. The default names are used for
+    /// any messages that are not found in the catalog.
+    pub fn from_catalog<C: Catalog + ?Sized>(catalog: &C) -> SeverityNames {
+        let defaults = SeverityNames::default();
+        let name = |id: &str, default: String| catalog.pattern(id).map_or(default, String::from);
+
+        SeverityNames {
+            bug: name("severity-bug", defaults.bug),
+            error: name("severity-error", defaults.error),
+            warning: name("severity-warning", defaults.warning),
+            note: name("severity-note", defaults.note),
+            help: name("severity-help", defaults.help),
+        }
+    }
+
+    /// The name of a severity.
+    pub fn name(&self, severity: Severity) -> &str {
+        match severity {
+            Severity::Bug => &self.bug,
+            Severity::Error => &self.error,
+            Severity::Warning => &self.warning,
+            Severity::Note => &self.note,
+            Severity::Help => &self.help,
+        }
+    }
+
+    /// The default title of a note of a given kind.
+    pub fn note_title(&self, kind: NoteKind) -> Option<&str> {
+        match kind {
+            NoteKind::Plain => None,
+            NoteKind::Note => Some(&self.note),
+            NoteKind::Help => Some(&self.help),
+        }
+    }
+}
+
+impl Default for SeverityNames {
+    fn default() -> SeverityNames {
+        SeverityNames {
+            bug: String::from("bug"),
+            error: String::from("error"),
+            warning: String::from("warning"),
+            note: String::from("note"),
+            help: String::from("help"),
         }
     }
 }
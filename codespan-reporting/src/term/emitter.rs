@@ -0,0 +1,411 @@
+use std::borrow::Cow;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::format;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::string::{String, ToString};
+use std::vec;
+
+use termcolor::WriteColor;
+
+use crate::catalog::{self, Catalog};
+use crate::diagnostic::{Diagnostic, Severity};
+use crate::files::Files;
+use crate::term::{Config, SeverityNames};
+
+/// Emits diagnostics to a writer, keeping track of how many diagnostics of
+/// each severity have been emitted.
+///
+/// ```rust
+/// use codespan_reporting::diagnostic::Diagnostic;
+/// use codespan_reporting::files::SimpleFile;
+/// use codespan_reporting::term::termcolor::{ColorChoice, StandardStream};
+/// use codespan_reporting::term::{Config, Emitter};
+///
+/// let file = SimpleFile::new("main.rs", "fn main() {}");
+/// let writer = StandardStream::stderr(ColorChoice::Auto);
+/// let mut emitter = Emitter::new(writer, Config::default(), &file)
+///     .with_max_errors(10)
+///     .with_warnings_as_errors(true);
+///
+/// emitter.emit(&Diagnostic::warning().with_message("unused function `main`"))?;
+/// emitter.finish()?;
+///
+/// assert_eq!(emitter.error_count(), 1);
+/// assert_eq!(emitter.exit_code(), 1);
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub struct Emitter<'files, W, F> {
+    writer: W,
+    config: Config,
+    files: &'files F,
+    catalog: Option<&'files dyn Catalog>,
+    counts: [usize; 5],
+    max_errors: Option<usize>,
+    warnings_as_errors: bool,
+    panic_on_error: Option<usize>,
+    emitted: HashSet<u64>,
+    suppressed: usize,
+}
+
+impl<'files, W, F> Emitter<'files, W, F>
+where
+    W: WriteColor,
+    F: Files<'files>,
+    F::FileId: Hash,
+{
+    /// Create a new emitter.
+    pub fn new(writer: W, config: Config, files: &'files F) -> Emitter<'files, W, F> {
+        Emitter {
+            writer,
+            config,
+            files,
+            catalog: None,
+            counts: [0; 5],
+            max_errors: None,
+            warnings_as_errors: false,
+            panic_on_error: None,
+            emitted: HashSet::new(),
+            suppressed: 0,
+        }
+    }
+
+    /// Stop emitting diagnostics once this many errors have been emitted.
+    /// Later diagnostics are still counted, and a notice saying how many were
+    /// not shown is emitted by [`Emitter::finish`].
+    ///
+    /// [`Emitter::finish`]: Emitter::finish
+    pub fn with_max_errors(mut self, max_errors: usize) -> Emitter<'files, W, F> {
+        self.max_errors = Some(max_errors);
+        self
+    }
+
+    /// Promote warnings to errors, as with a `--deny-warnings` flag.
+    pub fn with_warnings_as_errors(mut self, warnings_as_errors: bool) -> Emitter<'files, W, F> {
+        self.warnings_as_errors = warnings_as_errors;
+        self
+    }
+
+    /// Panic after emitting the error with this number, starting from `1`.
+    /// This is useful for finding where an error was reported, by getting a
+    /// backtrace with `RUST_BACKTRACE=1`.
+    pub fn with_panic_on_error(mut self, error_number: usize) -> Emitter<'files, W, F> {
+        self.panic_on_error = Some(error_number);
+        self
+    }
+
+    /// Localize diagnostics with a catalog before emitting them, and use the
+    /// catalog for the names of severities and for the notice emitted by
+    /// [`Emitter::finish`].
+    ///
+    /// The notice is looked up with the identifier `too-many-errors`, with the
+    /// argument `max`, and its note with `diagnostic-not-shown` when one
+    /// diagnostic was not shown, or `diagnostics-not-shown` with the argument
+    /// `count` otherwise.
+    ///
+    /// [`Emitter::finish`]: Emitter::finish
+    pub fn with_catalog(mut self, catalog: &'files dyn Catalog) -> Emitter<'files, W, F> {
+        self.catalog = Some(catalog);
+        self.config.severity_names = SeverityNames::from_catalog(catalog);
+        self
+    }
+
+    /// Emit a diagnostic.
+    ///
+    /// Diagnostics that are exactly the same as one that was emitted earlier
+    /// are ignored, and are not counted.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the diagnostic is the error set by
+    /// [`Emitter::with_panic_on_error`].
+    ///
+    /// [`Emitter::with_panic_on_error`]: Emitter::with_panic_on_error
+    pub fn emit(&mut self, diagnostic: &Diagnostic<F::FileId>) -> io::Result<()> {
+        let mut hasher = DefaultHasher::new();
+        diagnostic.hash(&mut hasher);
+        if !self.emitted.insert(hasher.finish()) {
+            return Ok(());
+        }
+
+        let mut diagnostic = match self.catalog {
+            Some(catalog) => Cow::Owned(catalog::localize(catalog, diagnostic.clone())),
+            None => Cow::Borrowed(diagnostic),
+        };
+        if diagnostic.severity == Severity::Warning && self.warnings_as_errors {
+            diagnostic.to_mut().severity = Severity::Error;
+        }
+
+        let is_limit_reached = self.is_limit_reached();
+        self.counts[severity_index(diagnostic.severity)] += 1;
+        if is_limit_reached {
+            self.suppressed += 1;
+            return Ok(());
+        }
+
+        super::emit(&mut self.writer, &self.config, self.files, &diagnostic)?;
+
+        if diagnostic.severity >= Severity::Error && self.panic_on_error == Some(self.error_count())
+        {
+            panic!("error number {} was emitted", self.error_count());
+        }
+
+        Ok(())
+    }
+
+    /// Finish emitting diagnostics, emitting a notice if any diagnostics were
+    /// not shown because the maximum number of errors was reached.
+    pub fn finish(&mut self) -> io::Result<()> {
+        let max_errors = match self.max_errors {
+            Some(max_errors) if self.suppressed > 0 => max_errors,
+            _ => return Ok(()),
+        };
+
+        let args = vec![
+            (String::from("max"), max_errors.to_string()),
+            (String::from("count"), self.suppressed.to_string()),
+        ];
+        let message = |id: &str, default: String| match self.catalog {
+            Some(catalog) => catalog.format(id, &args).unwrap_or(default),
+            None => default,
+        };
+
+        let not_shown = match self.suppressed {
+            1 => message(
+                "diagnostic-not-shown",
+                String::from("1 more diagnostic was not shown"),
+            ),
+            suppressed => message(
+                "diagnostics-not-shown",
+                format!("{} more diagnostics were not shown", suppressed),
+            ),
+        };
+        let diagnostic = Diagnostic::error()
+            .with_message(message(
+                "too-many-errors",
+                format!("too many errors, stopping after {}", max_errors),
+            ))
+            .with_notes(vec![not_shown]);
+
+        super::emit(&mut self.writer, &self.config, self.files, &diagnostic)
+    }
+
+    fn is_limit_reached(&self) -> bool {
+        match self.max_errors {
+            Some(max_errors) => self.error_count() >= max_errors,
+            None => false,
+        }
+    }
+}
+
+impl<'files, W, F> Emitter<'files, W, F> {
+    /// The number of diagnostics of a given severity that have been emitted,
+    /// after promoting warnings to errors. This includes diagnostics that were
+    /// not shown because the maximum number of errors was reached.
+    pub fn count(&self, severity: Severity) -> usize {
+        self.counts[severity_index(severity)]
+    }
+
+    /// The number of bugs and errors that have been emitted.
+    pub fn error_count(&self) -> usize {
+        self.count(Severity::Bug) + self.count(Severity::Error)
+    }
+
+    /// The number of warnings that have been emitted.
+    pub fn warning_count(&self) -> usize {
+        self.count(Severity::Warning)
+    }
+
+    /// Returns `true` if any bugs or errors have been emitted.
+    pub fn has_errors(&self) -> bool {
+        self.error_count() > 0
+    }
+
+    /// The exit code that the process should finish with: `1` if any bugs or
+    /// errors were emitted, and `0` otherwise.
+    pub fn exit_code(&self) -> i32 {
+        if self.has_errors() {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Get a reference to the underlying writer.
+    pub fn writer(&self) -> &W {
+        &self.writer
+    }
+
+    /// Consume the emitter, returning the underlying writer.
+    pub fn into_writer(self) -> W {
+        self.writer
+    }
+}
+
+fn severity_index(severity: Severity) -> usize {
+    match severity {
+        Severity::Bug => 0,
+        Severity::Error => 1,
+        Severity::Warning => 2,
+        Severity::Note => 3,
+        Severity::Help => 4,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::vec::Vec;
+    use termcolor::NoColor;
+
+    use crate::catalog::Bundle;
+    use crate::diagnostic::Label;
+    use crate::files::SimpleFile;
+
+    fn output(emitter: Emitter<'_, NoColor<Vec<u8>>, SimpleFile<&str, &str>>) -> String {
+        String::from_utf8(emitter.into_writer().into_inner()).unwrap()
+    }
+
+    #[test]
+    fn counts_by_severity() {
+        let file = SimpleFile::new("test", "");
+        let mut emitter = Emitter::new(NoColor::new(Vec::new()), Config::default(), &file);
+
+        emitter
+            .emit(&Diagnostic::error().with_message("a"))
+            .unwrap();
+        emitter.emit(&Diagnostic::bug().with_message("b")).unwrap();
+        emitter
+            .emit(&Diagnostic::warning().with_message("c"))
+            .unwrap();
+        emitter.emit(&Diagnostic::note().with_message("d")).unwrap();
+
+        assert_eq!(emitter.error_count(), 2);
+        assert_eq!(emitter.warning_count(), 1);
+        assert_eq!(emitter.count(Severity::Note), 1);
+        assert_eq!(emitter.count(Severity::Help), 0);
+        assert_eq!(emitter.exit_code(), 1);
+    }
+
+    #[test]
+    fn suppresses_duplicates() {
+        let file = SimpleFile::new("test", "let x = 1;");
+        let mut emitter = Emitter::new(NoColor::new(Vec::new()), Config::default(), &file);
+
+        let diagnostic = Diagnostic::warning()
+            .with_message("unused variable")
+            .with_labels(vec![Label::primary((), 4..5)]);
+        emitter.emit(&diagnostic).unwrap();
+        emitter.emit(&diagnostic).unwrap();
+        emitter
+            .emit(&diagnostic.clone().with_message("unused binding"))
+            .unwrap();
+
+        assert_eq!(emitter.warning_count(), 2);
+        assert_eq!(emitter.exit_code(), 0);
+        assert_eq!(output(emitter).matches("warning:").count(), 2);
+    }
+
+    #[test]
+    fn promotes_warnings_to_errors() {
+        let file = SimpleFile::new("test", "");
+        let mut emitter = Emitter::new(NoColor::new(Vec::new()), Config::default(), &file)
+            .with_warnings_as_errors(true);
+
+        emitter
+            .emit(&Diagnostic::warning().with_message("a"))
+            .unwrap();
+        emitter.emit(&Diagnostic::note().with_message("b")).unwrap();
+
+        assert_eq!(emitter.error_count(), 1);
+        assert_eq!(emitter.warning_count(), 0);
+        assert_eq!(output(emitter), "error: a\n\nnote: b\n\n");
+    }
+
+    #[test]
+    fn stops_after_max_errors() {
+        let file = SimpleFile::new("test", "");
+        let mut emitter =
+            Emitter::new(NoColor::new(Vec::new()), Config::default(), &file).with_max_errors(2);
+
+        for message in &["a", "b", "c", "d"] {
+            emitter
+                .emit(&Diagnostic::error().with_message(*message))
+                .unwrap();
+        }
+        emitter
+            .emit(&Diagnostic::warning().with_message("e"))
+            .unwrap();
+        emitter.finish().unwrap();
+
+        assert_eq!(emitter.error_count(), 4);
+        assert_eq!(emitter.warning_count(), 1);
+        assert_eq!(
+            output(emitter),
+            [
+                "error: a\n\n",
+                "error: b\n\n",
+                "error: too many errors, stopping after 2\n",
+                "  = 3 more diagnostics were not shown\n\n",
+            ]
+            .concat(),
+        );
+    }
+
+    #[test]
+    fn localizes_with_catalog() {
+        let file = SimpleFile::new("test", "");
+        let catalog = Bundle::new("de")
+            .with_message("severity-error", "Fehler")
+            .with_message("unknown-name", "unbekannter Name `{$name}`")
+            .with_message("too-many-errors", "zu viele Fehler, Abbruch nach {$max}")
+            .with_message(
+                "diagnostic-not-shown",
+                "1 weitere Meldung wurde nicht angezeigt",
+            );
+        let mut emitter = Emitter::new(NoColor::new(Vec::new()), Config::default(), &file)
+            .with_catalog(&catalog)
+            .with_max_errors(1);
+
+        for name in &["x", "y"] {
+            emitter
+                .emit(
+                    &Diagnostic::error()
+                        .with_message("unknown-name")
+                        .with_args(vec![("name", *name)]),
+                )
+                .unwrap();
+        }
+        emitter.finish().unwrap();
+
+        assert_eq!(
+            output(emitter),
+            [
+                "Fehler: unbekannter Name `x`\n\n",
+                "Fehler: zu viele Fehler, Abbruch nach 1\n",
+                "  = 1 weitere Meldung wurde nicht angezeigt\n\n",
+            ]
+            .concat(),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "error number 2 was emitted")]
+    fn panics_on_error() {
+        let file = SimpleFile::new("test", "");
+        let mut emitter =
+            Emitter::new(NoColor::new(Vec::new()), Config::default(), &file).with_panic_on_error(2);
+
+        emitter
+            .emit(&Diagnostic::warning().with_message("a"))
+            .unwrap();
+        emitter
+            .emit(&Diagnostic::error().with_message("b"))
+            .unwrap();
+        emitter
+            .emit(&Diagnostic::error().with_message("c"))
+            .unwrap();
+    }
+}
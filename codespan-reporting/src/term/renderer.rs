@@ -1,9 +1,11 @@
 use std::io::{self, Write};
 use std::ops::{Range, RangeTo};
+use std::string::String;
 use termcolor::{ColorSpec, WriteColor};
 
-use crate::diagnostic::{LabelStyle, Severity};
+use crate::diagnostic::{LabelStyle, Note, Severity, Tag};
 use crate::files::Location;
+use crate::markup;
 use crate::term::{Chars, Config, Styles};
 
 /// The 'location focus' of a source code snippet.
@@ -51,6 +53,13 @@ pub enum MultiLabel<'diagnostic> {
     Bottom(LabelStyle, RangeTo<usize>, &'diagnostic str),
 }
 
+/// Whether a line of a suggestion was removed from, or added to the source.
+#[derive(Copy, Clone)]
+pub enum Change {
+    Deletion,
+    Insertion,
+}
+
 #[derive(Copy, Clone)]
 enum VerticalBound {
     Top,
@@ -107,6 +116,8 @@ type Underline = (LabelStyle, VerticalBound);
 pub struct Renderer<'writer, 'config> {
     writer: &'writer mut dyn WriteColor,
     config: &'config Config,
+    /// Whether messages are written in markup.
+    markup: bool,
 }
 
 impl<'writer, 'config> Renderer<'writer, 'config> {
@@ -115,7 +126,21 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         writer: &'writer mut dyn WriteColor,
         config: &'config Config,
     ) -> Renderer<'writer, 'config> {
-        Renderer { writer, config }
+        Renderer {
+            writer,
+            config,
+            markup: false,
+        }
+    }
+
+    /// Set whether messages are written in markup.
+    pub fn set_markup(&mut self, markup: bool) {
+        self.markup = markup;
+    }
+
+    /// The config used by the renderer.
+    pub fn config(&self) -> &'config Config {
+        self.config
     }
 
     fn chars(&self) -> &'config Chars {
@@ -154,13 +179,7 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         // error
         // ```
         self.set_color(self.styles().header(severity))?;
-        match severity {
-            Severity::Bug => write!(self, "bug")?,
-            Severity::Error => write!(self, "error")?,
-            Severity::Warning => write!(self, "warning")?,
-            Severity::Help => write!(self, "help")?,
-            Severity::Note => write!(self, "note")?,
-        }
+        write!(self, "{}", self.config.severity_names.name(severity))?;
 
         // Write error code
         //
@@ -177,7 +196,8 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         // : unexpected type in `+` application
         // ```
         self.set_color(&self.styles().header_message)?;
-        write!(self, ": {}", message)?;
+        write!(self, ": ")?;
+        self.message(message, Some(&self.styles().header_message))?;
         self.reset()?;
 
         write!(self, "\n")?;
@@ -185,6 +205,36 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         Ok(())
     }
 
+    /// The header of a sub-diagnostic, indented to line up with the snippets
+    /// of the diagnostic.
+    ///
+    /// ```text
+    ///   note: the previous definition is here
+    /// ```
+    pub fn render_child_header(
+        &mut self,
+        outer_padding: usize,
+        severity: Severity,
+        message: &str,
+    ) -> io::Result<()> {
+        self.outer_gutter(outer_padding)?;
+        self.render_header(None, severity, None, message)
+    }
+
+    /// A located line for a site that a file was included from, rendered
+    /// after a located header.
+    ///
+    /// ```text
+    /// main.c:1:1: included from here
+    /// ```
+    pub fn render_include_site(&mut self, locus: &Locus) -> io::Result<()> {
+        self.snippet_locus(locus)?;
+        write!(self, ": included from here")?;
+        write!(self, "\n")?;
+
+        Ok(())
+    }
+
     /// Empty line.
     pub fn render_empty(&mut self) -> io::Result<()> {
         write!(self, "\n")?;
@@ -213,6 +263,33 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         Ok(())
     }
 
+    /// A site that the file containing the snippet was included from,
+    /// rendered above the top left border.
+    ///
+    /// ```text
+    /// in file included from header.h:3:1
+    ///                  from main.c:1:1
+    /// ```
+    pub fn render_snippet_include(
+        &mut self,
+        outer_padding: usize,
+        is_first: bool,
+        locus: &Locus,
+    ) -> io::Result<()> {
+        self.outer_gutter(outer_padding)?;
+
+        if is_first {
+            write!(self, "in file included from ")?;
+        } else {
+            write!(self, "                 from ")?;
+        }
+        self.snippet_locus(locus)?;
+
+        write!(self, "\n")?;
+
+        Ok(())
+    }
+
     /// A line of source code.
     ///
     /// ```text
@@ -228,6 +305,8 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         single_labels: &[SingleLabel<'_>],
         num_multi_labels: usize,
         multi_labels: &[(usize, MultiLabel<'_>)],
+        tags: &[Tag],
+        tagged_ranges: &[Range<usize>],
     ) -> io::Result<()> {
         // Write source line
         //
@@ -259,8 +338,13 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
                 }
             }
 
-            // Write source
-            write!(self.config.source(self.writer), " {}", source.trim_end())?;
+            // Write source, marking any code covered by tagged labels
+            write!(self, " ")?;
+            let mut tag_style = ColorSpec::new();
+            for tag in tags {
+                merge_color_spec(&mut tag_style, self.styles().tag(*tag));
+            }
+            self.source_highlighted(source.trim_end(), tagged_ranges, &tag_style)?;
             write!(self, "\n")?;
         }
 
@@ -386,26 +470,245 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
     /// ```text
     /// = expected type `Int`
     ///      found type `String`
+    /// = help: use a string literal instead
     /// ```
-    pub fn render_snippet_note(&mut self, outer_padding: usize, message: &str) -> io::Result<()> {
-        for (note_line_index, line) in message.lines().enumerate() {
+    pub fn render_snippet_note(&mut self, outer_padding: usize, note: &Note) -> io::Result<()> {
+        let title = match &note.title {
+            Some(title) => Some(title.as_str()),
+            None => self.config.severity_names.note_title(note.kind),
+        };
+        // Continuation lines are aligned with the start of the message
+        let title_width = title.map_or(0, |title| self.config.width(title) + 2);
+
+        for (note_line_index, line) in note.message.lines().enumerate() {
             self.outer_gutter(outer_padding)?;
             match note_line_index {
                 0 => {
                     self.set_color(&self.styles().note_bullet)?;
                     write!(self, "{}", self.chars().note_bullet)?;
                     self.reset()?;
+                    if let Some(title) = title {
+                        write!(self, " ")?;
+                        self.set_color(self.styles().note_title(note.kind))?;
+                        write!(self, "{}:", title)?;
+                        self.reset()?;
+                    }
                 }
-                _ => write!(self, " ")?,
+                _ => write!(self, " {space: >width$}", space = "", width = title_width)?,
             }
             // Write line of message
-            write!(self, " {}", line)?;
+            write!(self, " ")?;
+            self.message(line, None)?;
+            write!(self, "\n")?;
+        }
+
+        Ok(())
+    }
+
+    /// A long-form explanation of a diagnostic code, written in Markdown.
+    ///
+    /// Headings, fenced code blocks, inline code, and emphasis are styled,
+    /// and all other text is written as-is.
+    ///
+    /// ```text
+    /// Expected type did not match the received type.
+    ///
+    /// Erroneous code example:
+    ///
+    ///     let x: i32 = "I am not a number!";
+    /// ```
+    pub fn render_explanation(&mut self, markdown: &str) -> io::Result<()> {
+        let mut in_code_block = false;
+
+        for line in markdown.lines() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") {
+                in_code_block = !in_code_block;
+                continue;
+            }
+
+            if in_code_block && line.is_empty() {
+                // Avoid writing trailing whitespace
+            } else if in_code_block {
+                write!(self, "    ")?;
+                self.set_color(&self.styles().explanation_code)?;
+                write!(self, "{}", line)?;
+                self.reset()?;
+            } else if trimmed.starts_with('#') {
+                self.set_color(&self.styles().explanation_heading)?;
+                write!(self, "{}", trimmed.trim_start_matches('#').trim())?;
+                self.reset()?;
+            } else {
+                self.explanation_text(line)?;
+            }
+            write!(self, "\n")?;
+        }
+
+        Ok(())
+    }
+
+    /// A line of text in an explanation, styling any inline code and emphasis.
+    /// The backticks around inline code are kept, so that it can still be
+    /// distinguished when colors are disabled.
+    fn explanation_text(&mut self, mut text: &str) -> io::Result<()> {
+        while let Some(start) = text.find(&['`', '*'][..]) {
+            let (delimiter, style) = match &text[start..] {
+                rest if rest.starts_with('`') => ("`", &self.styles().explanation_code),
+                rest if rest.starts_with("**") => ("**", &self.styles().explanation_emphasis),
+                _ => ("*", &self.styles().explanation_emphasis),
+            };
+            let content_start = start + delimiter.len();
+            let content = &text[content_start..];
+
+            // Delimiters without a matching closing delimiter, or that are
+            // followed by whitespace (like list bullets) are written as-is.
+            let content_len = match content.find(delimiter) {
+                Some(len) if len > 0 && !content.starts_with(char::is_whitespace) => len,
+                _ => {
+                    write!(self, "{}", &text[..content_start])?;
+                    text = content;
+                    continue;
+                }
+            };
+
+            let content_end = content_start + content_len;
+            let end = content_end + delimiter.len();
+            let styled = match delimiter {
+                "`" => &text[start..end],
+                _ => &text[content_start..content_end],
+            };
+
+            write!(self, "{}", &text[..start])?;
+            self.set_color(style)?;
+            write!(self, "{}", styled)?;
+            self.reset()?;
+            text = &text[end..];
+        }
+        write!(self, "{}", text)?;
+
+        Ok(())
+    }
+
+    /// A message, styling any marked up text on top of the style of the
+    /// surrounding message.
+    fn message(&mut self, message: &str, base: Option<&ColorSpec>) -> io::Result<()> {
+        if !self.markup {
+            return write!(self, "{}", message);
+        }
+
+        for (style, text) in markup::segments(message) {
+            match style {
+                None => write!(self, "{}", text)?,
+                Some(style) => {
+                    let mut spec = base.cloned().unwrap_or_default();
+                    merge_color_spec(&mut spec, self.styles().message(style));
+                    self.set_color(&spec)?;
+                    write!(self, "{}", text)?;
+                    match base {
+                        Some(base) => self.set_color(base)?,
+                        None => self.reset()?,
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A line of source code with a suggestion applied, marking the text that
+    /// was inserted.
+    ///
+    /// ```text
+    /// 2 │ let x = &foo;
+    ///   │         +
+    /// ```
+    pub fn render_snippet_suggestion_source(
+        &mut self,
+        outer_padding: usize,
+        line_number: usize,
+        source: &str,
+        insertions: &[Range<usize>],
+    ) -> io::Result<()> {
+        let source = source.trim_end();
+        let insertion_style = &self.styles().suggestion_insertion;
+
+        // Write source line
+        //
+        // ```text
+        // 2 │ let x = &foo;
+        // ```
+        self.outer_gutter_number(line_number, outer_padding)?;
+        self.border_left()?;
+        write!(self, " ")?;
+        self.source_highlighted(source, insertions, insertion_style)?;
+        write!(self, "\n")?;
+
+        // Write insertion markers underneath source
+        //
+        // ```text
+        //   │         +
+        // ```
+        if !insertions.is_empty() {
+            self.outer_gutter(outer_padding)?;
+            self.border_left()?;
+            write!(self, " ")?;
+
+            let mut column = 0;
+            for range in insertions {
+                let range = clamp_range(source, range.clone());
+                let start_column = self.config.width(&source[..range.start]);
+                let len = usize::max(self.config.width(&source[range]), 1);
+                let space_len = start_column.saturating_sub(column);
+                write!(self, "{space: >width$}", space = "", width = space_len)?;
+                self.set_color(insertion_style)?;
+                for _ in 0..len {
+                    write!(self, "{}", self.chars().suggestion_insertion)?;
+                }
+                self.reset()?;
+                column += space_len + len;
+            }
             write!(self, "\n")?;
         }
 
         Ok(())
     }
 
+    /// A line of source code that was removed or added by a suggestion,
+    /// highlighting the text that was changed.
+    ///
+    /// ```text
+    /// 2 - let x = foo.bar();
+    /// 2 + let x = foo.baz();
+    /// ```
+    pub fn render_snippet_suggestion_diff(
+        &mut self,
+        outer_padding: usize,
+        line_number: usize,
+        change: Change,
+        source: &str,
+        highlights: &[Range<usize>],
+    ) -> io::Result<()> {
+        let (style, marker) = match change {
+            Change::Deletion => (
+                &self.styles().suggestion_deletion,
+                self.chars().suggestion_deletion,
+            ),
+            Change::Insertion => (
+                &self.styles().suggestion_insertion,
+                self.chars().suggestion_insertion,
+            ),
+        };
+
+        self.outer_gutter_number(line_number, outer_padding)?;
+        self.set_color(style)?;
+        write!(self, "{}", marker)?;
+        self.reset()?;
+        write!(self, " ")?;
+        self.source_highlighted(source.trim_end(), highlights, style)?;
+        write!(self, "\n")?;
+
+        Ok(())
+    }
+
     /// Location focus.
     fn snippet_locus(&mut self, locus: &Locus) -> io::Result<()> {
         write!(
@@ -417,6 +720,38 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         )
     }
 
+    /// Source code, with the given ranges highlighted in a style.
+    fn source_highlighted(
+        &mut self,
+        source: &str,
+        highlights: &[Range<usize>],
+        style: &ColorSpec,
+    ) -> io::Result<()> {
+        let mut last_end = 0;
+        for range in highlights {
+            let range = clamp_range(source, usize::max(range.start, last_end)..range.end);
+            if range.start >= range.end {
+                continue;
+            }
+            write!(
+                self.config.source(self.writer),
+                "{}",
+                &source[last_end..range.start]
+            )?;
+            self.set_color(style)?;
+            write!(
+                self.config.source(self.writer),
+                "{}",
+                &source[range.clone()]
+            )?;
+            self.reset()?;
+            last_end = range.end;
+        }
+        write!(self.config.source(self.writer), "{}", &source[last_end..])?;
+
+        Ok(())
+    }
+
     /// The outer gutter of a source line.
     fn outer_gutter(&mut self, outer_padding: usize) -> io::Result<()> {
         write!(self, " ")?;
@@ -480,7 +815,8 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
             write!(self, "{}", self.chars().single_caret_char(label_style))?;
         }
         if !message.is_empty() {
-            write!(self, " {}", message)?;
+            write!(self, " ")?;
+            self.message(message, Some(self.styles().label(severity, label_style)))?;
         }
         self.reset()?;
         write!(self, "\n")?;
@@ -618,7 +954,8 @@ impl<'writer, 'config> Renderer<'writer, 'config> {
         }
         write!(self, "{}", self.chars().multi_caret_char_end(label_style))?;
         if !message.is_empty() {
-            write!(self, " {}", message)?;
+            write!(self, " ")?;
+            self.message(message, Some(self.styles().label(severity, label_style)))?;
         }
         self.reset()?;
         write!(self, "\n")?;
@@ -722,6 +1059,14 @@ fn closest_char_boundary_rev(s: &str, byte_index: usize) -> usize {
     0
 }
 
+/// Clamps a range to the bounds of the string, adjusting it outwards to valid
+/// unicode boundaries.
+fn clamp_range(s: &str, range: Range<usize>) -> Range<usize> {
+    let start = closest_char_boundary_rev(s, usize::min(range.start, s.len()));
+    let end = closest_char_boundary(s, usize::min(range.end, s.len()));
+    start..usize::max(start, end)
+}
+
 /// Finds a valid unicode boundaries looking from `range.start` towards the beginning of the string.
 /// From `range.end` towards the end of the string. Returning a `&str` of all characters
 /// that overlapping the range.
@@ -731,10 +1076,36 @@ fn slice_at_char_boundaries<'a>(s: &'a str, range: Range<usize>) -> &'a str {
     &s[start..end]
 }
 
+/// Overlay the set attributes of a color spec on top of another spec.
+fn merge_color_spec(spec: &mut ColorSpec, overlay: &ColorSpec) {
+    if let Some(fg) = overlay.fg() {
+        spec.set_fg(Some(*fg));
+    }
+    if let Some(bg) = overlay.bg() {
+        spec.set_bg(Some(*bg));
+    }
+    if overlay.bold() {
+        spec.set_bold(true);
+    }
+    if overlay.intense() {
+        spec.set_intense(true);
+    }
+    if overlay.underline() {
+        spec.set_underline(true);
+    }
+    if overlay.dimmed() {
+        spec.set_dimmed(true);
+    }
+    if overlay.strikethrough() {
+        spec.set_strikethrough(true);
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
     use std::iter::repeat;
+    use std::vec::Vec;
 
     #[test]
     fn test_boundary() {
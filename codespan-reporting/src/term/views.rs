@@ -1,9 +1,14 @@
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::io;
 use std::ops::Range;
+use std::string::{String, ToString};
+use std::vec;
+use std::vec::Vec;
 
-use crate::diagnostic::{Diagnostic, LabelStyle};
-use crate::files::{Files, Location};
-use crate::term::renderer::{Locus, MultiLabel, Renderer, SingleLabel};
+use crate::diagnostic::{Diagnostic, Edit, Label, LabelStyle, Note, Severity, Suggestion, Tag};
+use crate::files::{ExpansionKind, Files, Location};
+use crate::term::renderer::{Change, Locus, MultiLabel, Renderer, SingleLabel};
 
 /// Count the number of decimal digits in `n`.
 fn count_digits(mut n: usize) -> usize {
@@ -15,6 +20,734 @@ fn count_digits(mut n: usize) -> usize {
     count
 }
 
+/// A file containing labeled lines of source code.
+struct LabeledFile<'labels, FileId> {
+    file_id: FileId,
+    start: usize,
+    name: String,
+    location: Location,
+    num_multi_labels: usize,
+    tags: &'labels [Tag],
+    lines: BTreeMap<usize, Line<'labels>>,
+}
+
+impl<'labels, FileId> LabeledFile<'labels, FileId> {
+    fn get_or_insert_line(
+        &mut self,
+        line_index: usize,
+        line_range: Range<usize>,
+        line_number: usize,
+    ) -> &mut Line<'labels> {
+        self.lines.entry(line_index).or_insert_with(|| Line {
+            range: line_range,
+            number: line_number,
+            single_labels: vec![],
+            multi_labels: vec![],
+            tagged_ranges: vec![],
+        })
+    }
+}
+
+/// A labeled line of source code.
+struct Line<'labels> {
+    number: usize,
+    range: std::ops::Range<usize>,
+    // TODO: How do we reuse these allocations?
+    single_labels: Vec<SingleLabel<'labels>>,
+    multi_labels: Vec<(usize, MultiLabel<'labels>)>,
+    /// The ranges of the line covered by the primary labels of a tagged
+    /// diagnostic, sorted by their start.
+    tagged_ranges: Vec<Range<usize>>,
+}
+
+impl<'labels> Line<'labels> {
+    fn insert_tagged_range(&mut self, range: Range<usize>) {
+        let index = match self
+            .tagged_ranges
+            .binary_search_by_key(&range.start, |range| range.start)
+        {
+            Ok(index) | Err(index) => index,
+        };
+        self.tagged_ranges.insert(index, range);
+    }
+}
+
+/// Group labels by file, keeping track of the outer padding to use when
+/// rendering the snippets of source code. The source code covered by primary
+/// labels is marked with the given tags.
+fn label_files<'files, 'labels, FileId>(
+    files: &'files impl Files<'files, FileId = FileId>,
+    labels: impl IntoIterator<Item = &'labels Label<FileId>>,
+    tags: &'labels [Tag],
+    outer_padding: &mut usize,
+) -> Vec<LabeledFile<'labels, FileId>>
+where
+    FileId: 'files + 'labels + Copy + PartialEq,
+{
+    // TODO: Make this data structure external, to allow for allocation reuse
+    let mut labeled_files = Vec::<LabeledFile<'_, _>>::new();
+
+    for label in labels {
+        let source = files.source(label.file_id).unwrap();
+        let source = source.as_ref();
+
+        let start_line_index = files.line_index(label.file_id, label.range.start).unwrap();
+        let start_line_number = files.line_number(label.file_id, start_line_index).unwrap();
+        let start_line_range = files.line_range(label.file_id, start_line_index).unwrap();
+        let end_line_index = files.line_index(label.file_id, label.range.end).unwrap();
+        let end_line_number = files.line_number(label.file_id, end_line_index).unwrap();
+        let end_line_range = files.line_range(label.file_id, end_line_index).unwrap();
+
+        *outer_padding = std::cmp::max(*outer_padding, count_digits(start_line_number));
+        *outer_padding = std::cmp::max(*outer_padding, count_digits(end_line_number));
+
+        // NOTE: This could be made more efficient by using an associative
+        // data structure like a hashmap or B-tree,  but we use a vector to
+        // preserve the order that unique files appear in the list of labels.
+        let labeled_file = match labeled_files
+            .iter_mut()
+            .find(|labeled_file| label.file_id == labeled_file.file_id)
+        {
+            Some(labeled_file) => {
+                if labeled_file.start > label.range.start {
+                    labeled_file.start = label.range.start;
+                    labeled_file.location =
+                        files.location(label.file_id, label.range.start).unwrap();
+                }
+                labeled_file
+            }
+            None => {
+                labeled_files.push(LabeledFile {
+                    file_id: label.file_id,
+                    start: label.range.start,
+                    name: files.name(label.file_id).unwrap().to_string(),
+                    location: files.location(label.file_id, label.range.start).unwrap(),
+                    num_multi_labels: 0,
+                    tags,
+                    lines: BTreeMap::new(),
+                });
+                labeled_files.last_mut().unwrap()
+            }
+        };
+
+        let is_tagged = !tags.is_empty() && label.style == LabelStyle::Primary;
+
+        if start_line_index == end_line_index {
+            // Single line
+            //
+            // ```text
+            // 2 │ (+ test "")
+            //   │         ^^ expected `Int` but found `String`
+            // ```
+            let label_start = label.range.start - start_line_range.start;
+            let label_end = label.range.end - start_line_range.start;
+
+            let line = labeled_file.get_or_insert_line(
+                start_line_index,
+                start_line_range,
+                start_line_number,
+            );
+
+            // Ensure that the single line labels are lexicographically
+            // sorted by the range of source code that they cover.
+            let index = match line.single_labels.binary_search_by(|(_, range, _)| {
+                // `Range<usize>` doesn't implement `Ord`, so convert to `(usize, usize)`
+                // to piggyback off its lexicographic comparison implementation.
+                (range.start, range.end).cmp(&(label_start, label_end))
+            }) {
+                Ok(index) | Err(index) => index,
+            };
+
+            line.single_labels
+                .insert(index, (label.style, label_start..label_end, &label.message));
+            if is_tagged {
+                line.insert_tagged_range(label_start..label_end);
+            }
+        } else {
+            // Multiple lines
+            //
+            // ```text
+            // 4 │   fizz₁ num = case (mod num 5) (mod num 3) of
+            //   │ ╭─────────────^
+            // 5 │ │     0 0 => "FizzBuzz"
+            // 6 │ │     0 _ => "Fizz"
+            // 7 │ │     _ 0 => "Buzz"
+            // 8 │ │     _ _ => num
+            //   │ ╰──────────────^ `case` clauses have incompatible types
+            // ```
+
+            let label_index = labeled_file.num_multi_labels;
+            labeled_file.num_multi_labels += 1;
+
+            // First labeled line
+            let label_start = label.range.start - start_line_range.start;
+            let prefix_source = &source[start_line_range.start..label.range.start];
+            let start_line_len = start_line_range.len();
+
+            let line = labeled_file.get_or_insert_line(
+                start_line_index,
+                start_line_range,
+                start_line_number,
+            );
+            if is_tagged {
+                line.insert_tagged_range(label_start..start_line_len);
+            }
+            line.multi_labels
+                // TODO: Do this in the `Renderer`?
+                .push(match prefix_source.trim() {
+                    // Section is prefixed by empty space, so we don't need to take
+                    // up a new line.
+                    //
+                    // ```text
+                    // 4 │ ╭     case (mod num 5) (mod num 3) of
+                    // ```
+                    "" => (label_index, MultiLabel::TopLeft(label.style)),
+                    // There's source code in the prefix, so run a label
+                    // underneath it to get to the start of the range.
+                    //
+                    // ```text
+                    // 4 │   fizz₁ num = case (mod num 5) (mod num 3) of
+                    //   │ ╭─────────────^
+                    // ```
+                    _ => (label_index, MultiLabel::Top(label.style, ..label_start)),
+                });
+
+            // Marked lines
+            //
+            // ```text
+            // 5 │ │     0 0 => "FizzBuzz"
+            // 6 │ │     0 _ => "Fizz"
+            // 7 │ │     _ 0 => "Buzz"
+            // ```
+            // TODO(#125): If start line and end line are too far apart, add a source break.
+            for line_index in (start_line_index + 1)..end_line_index {
+                let line_range = files.line_range(label.file_id, line_index).unwrap();
+                let line_number = files.line_number(label.file_id, line_index).unwrap();
+
+                *outer_padding = std::cmp::max(*outer_padding, count_digits(line_number));
+
+                let line_len = line_range.len();
+                let line = labeled_file.get_or_insert_line(line_index, line_range, line_number);
+                if is_tagged {
+                    line.insert_tagged_range(0..line_len);
+                }
+                line.multi_labels
+                    .push((label_index, MultiLabel::Left(label.style)));
+            }
+
+            // Last labeled line
+            //
+            // ```text
+            // 8 │ │     _ _ => num
+            //   │ ╰──────────────^ `case` clauses have incompatible types
+            // ```
+            let label_end = label.range.end - end_line_range.start;
+
+            let line =
+                labeled_file.get_or_insert_line(end_line_index, end_line_range, end_line_number);
+            if is_tagged {
+                line.insert_tagged_range(0..label_end);
+            }
+            line.multi_labels.push((
+                label_index,
+                MultiLabel::Bottom(label.style, ..label_end, &label.message),
+            ));
+        }
+    }
+
+    labeled_files
+}
+
+/// Collect the loci of the sites that a file was included from, starting
+/// with the innermost include site.
+fn include_loci<'files, FileId>(
+    files: &'files impl Files<'files, FileId = FileId>,
+    file_id: FileId,
+) -> Vec<Locus>
+where
+    FileId: 'files + Copy + PartialEq,
+{
+    let mut loci = Vec::new();
+    let mut visited = vec![file_id];
+
+    let mut file_id = file_id;
+    while let Some((include_file_id, include_range)) = files.include_site(file_id) {
+        // Guard against files that (indirectly) include themselves.
+        if visited.contains(&include_file_id) {
+            break;
+        }
+        visited.push(include_file_id);
+
+        loci.push(Locus {
+            name: files.name(include_file_id).unwrap().to_string(),
+            location: files
+                .location(include_file_id, include_range.start)
+                .unwrap(),
+        });
+        file_id = include_file_id;
+    }
+
+    loci
+}
+
+/// Render the snippets of source code for each of the labeled files.
+///
+/// ```text
+///   in file included from main.c:1:1
+///   ┌─ test:2:9
+///   │
+/// 2 │ (+ test "")
+///   │         ^^ expected `Int` but found `String`
+///   │
+/// ```
+fn render_labeled_files<'files, FileId>(
+    files: &'files impl Files<'files, FileId = FileId>,
+    renderer: &mut Renderer<'_, '_>,
+    severity: Severity,
+    outer_padding: usize,
+    labeled_files: Vec<LabeledFile<'_, FileId>>,
+) -> io::Result<()>
+where
+    FileId: 'files + Copy + PartialEq,
+{
+    for labeled_file in labeled_files {
+        let source = files.source(labeled_file.file_id).unwrap();
+        let source = source.as_ref();
+
+        // Top left border and locus, preceded by the include sites.
+        //
+        // ```text
+        // in file included from header.h:3:1
+        //                  from main.c:1:1
+        // ┌─ test:2:9
+        // ```
+        if !labeled_file.lines.is_empty() {
+            let include_loci = include_loci(files, labeled_file.file_id);
+            for (index, locus) in include_loci.iter().enumerate() {
+                renderer.render_snippet_include(outer_padding, index == 0, locus)?;
+            }
+            renderer.render_snippet_start(
+                outer_padding,
+                &Locus {
+                    name: labeled_file.name,
+                    location: labeled_file.location,
+                },
+            )?;
+            renderer.render_snippet_empty(
+                outer_padding,
+                severity,
+                labeled_file.num_multi_labels,
+                &[],
+            )?;
+        }
+
+        let mut lines = labeled_file.lines.into_iter().peekable();
+        let current_labels = Vec::new();
+
+        while let Some((line_index, line)) = lines.next() {
+            renderer.render_snippet_source(
+                outer_padding,
+                line.number,
+                &source[line.range.clone()],
+                severity,
+                &line.single_labels,
+                labeled_file.num_multi_labels,
+                &line.multi_labels,
+                labeled_file.tags,
+                &line.tagged_ranges,
+            )?;
+
+            // Check to see if we need to render any intermediate stuff
+            // before rendering the next line.
+            if let Some((next_line_index, _)) = lines.peek() {
+                match next_line_index.checked_sub(line_index) {
+                    // Consecutive lines
+                    Some(1) => {}
+                    // One line between the current line and the next line
+                    Some(2) => {
+                        // Write a source line
+                        let file_id = labeled_file.file_id;
+                        renderer.render_snippet_source(
+                            outer_padding,
+                            files.line_number(file_id, line_index + 1).unwrap(),
+                            &source[files.line_range(file_id, line_index + 1).unwrap()],
+                            severity,
+                            &[],
+                            labeled_file.num_multi_labels,
+                            &current_labels,
+                            &[],
+                            &[],
+                        )?;
+                    }
+                    // More than one line between the current line and the next line.
+                    Some(_) | None => {
+                        // Source break
+                        //
+                        // ```text
+                        // ·
+                        // ```
+                        renderer.render_snippet_break(
+                            outer_padding,
+                            severity,
+                            labeled_file.num_multi_labels,
+                            &current_labels,
+                        )?;
+                    }
+                }
+            }
+        }
+        renderer.render_snippet_empty(
+            outer_padding,
+            severity,
+            labeled_file.num_multi_labels,
+            &current_labels,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Collect labels pointing at the call sites of the macro expansions that
+/// produced the primary labels of a diagnostic, up to the given depth.
+fn expansion_labels<'files, FileId>(
+    files: &'files impl Files<'files, FileId = FileId>,
+    labels: &[Label<FileId>],
+    max_depth: usize,
+) -> Vec<Label<FileId>>
+where
+    FileId: 'files + Copy + PartialEq,
+{
+    let mut expansion_labels = Vec::<Label<FileId>>::new();
+
+    let primary_labels = labels.iter().filter(|l| l.style == LabelStyle::Primary);
+    for label in primary_labels {
+        let mut file_id = label.file_id;
+        let mut range = label.range.clone();
+
+        for _ in 0..max_depth {
+            let expansion = match files.expansion(file_id, range) {
+                Some(expansion) => expansion,
+                None => break,
+            };
+            let (call_site_file_id, call_site_range) = expansion.call_site;
+
+            // Avoid repeating call sites that are shared by multiple labels.
+            if !expansion_labels.iter().any(|expansion_label| {
+                expansion_label.file_id == call_site_file_id
+                    && expansion_label.range == call_site_range
+            }) {
+                let message = match expansion.kind {
+                    ExpansionKind::Macro => "in this macro invocation",
+                    ExpansionKind::Attribute => "in this attribute macro expansion",
+                    ExpansionKind::Derive => "in this derive macro expansion",
+                    ExpansionKind::Desugaring => "in this desugaring",
+                };
+                expansion_labels.push(
+                    Label::secondary(call_site_file_id, call_site_range.clone())
+                        .with_message(message),
+                );
+            }
+
+            file_id = call_site_file_id;
+            range = call_site_range;
+        }
+    }
+
+    expansion_labels
+}
+
+/// A file containing the lines of source code changed by a suggestion.
+struct SuggestedFile {
+    name: String,
+    location: Location,
+    hunks: Vec<Hunk>,
+}
+
+/// A group of consecutive lines of source code changed by a suggestion.
+struct Hunk {
+    /// Whether the hunk only inserts text within its lines, in which case
+    /// only the patched lines need to be shown.
+    is_inline_insertion: bool,
+    /// The original lines, with their numbers and the ranges that were deleted.
+    deleted_lines: Vec<(usize, String, Vec<Range<usize>>)>,
+    /// The patched lines, with their numbers and the ranges that were inserted.
+    inserted_lines: Vec<(usize, String, Vec<Range<usize>>)>,
+}
+
+/// Group the edits of a suggestion by file, and into hunks of consecutive
+/// lines, keeping track of the outer padding to use when rendering the
+/// snippets of source code.
+///
+/// Edits that overlap an earlier edit are ignored.
+fn suggested_files<'files, FileId>(
+    files: &'files impl Files<'files, FileId = FileId>,
+    suggestion: &Suggestion<FileId>,
+    outer_padding: &mut usize,
+) -> Vec<SuggestedFile>
+where
+    FileId: 'files + Copy + PartialEq,
+{
+    // Preserve the order that unique files appear in the list of edits.
+    let mut file_ids = Vec::<FileId>::new();
+    for edit in &suggestion.edits {
+        if !file_ids.contains(&edit.file_id) {
+            file_ids.push(edit.file_id);
+        }
+    }
+
+    file_ids
+        .into_iter()
+        .map(|file_id| {
+            let mut edits = suggestion
+                .edits
+                .iter()
+                .filter(|edit| edit.file_id == file_id)
+                .collect::<Vec<_>>();
+            edits.sort_by_key(|edit| (edit.range.start, edit.range.end));
+
+            // Group the edits by the lines that they change, keeping track of
+            // the first and last line of each group
+            let mut line_edits = Vec::<(usize, usize, Vec<&Edit<FileId>>)>::new();
+            for edit in edits {
+                // Deleting a line terminator doesn't change the next line
+                let end = match edit.range.start == edit.range.end {
+                    true => edit.range.end,
+                    false => edit.range.end - 1,
+                };
+                let start_line_index = files.line_index(file_id, edit.range.start).unwrap();
+                let end_line_index = files.line_index(file_id, end).unwrap();
+
+                match line_edits.last_mut() {
+                    Some((_, _, hunk_edits))
+                        if edit.range.start < hunk_edits.last().unwrap().range.end => {}
+                    Some((_, last_line_index, hunk_edits))
+                        if start_line_index <= *last_line_index =>
+                    {
+                        *last_line_index = usize::max(*last_line_index, end_line_index);
+                        hunk_edits.push(edit);
+                    }
+                    Some(_) | None => {
+                        line_edits.push((start_line_index, end_line_index, vec![edit]));
+                    }
+                }
+            }
+
+            let hunks = line_edits
+                .into_iter()
+                .map(|(first, last, edits)| suggestion_hunk(files, file_id, first, last, &edits))
+                .collect::<Vec<_>>();
+
+            for hunk in &hunks {
+                let lines = hunk.deleted_lines.iter().chain(&hunk.inserted_lines);
+                for (line_number, _, _) in lines {
+                    *outer_padding = std::cmp::max(*outer_padding, count_digits(*line_number));
+                }
+            }
+
+            let start = suggestion
+                .edits
+                .iter()
+                .filter(|edit| edit.file_id == file_id)
+                .map(|edit| edit.range.start)
+                .min()
+                .unwrap();
+
+            SuggestedFile {
+                name: files.name(file_id).unwrap().to_string(),
+                location: files.location(file_id, start).unwrap(),
+                hunks,
+            }
+        })
+        .collect()
+}
+
+/// Apply the edits to the lines of a file, recording the ranges of text that
+/// were deleted from the original lines and inserted into the patched lines.
+fn suggestion_hunk<'files, FileId>(
+    files: &'files impl Files<'files, FileId = FileId>,
+    file_id: FileId,
+    first_line_index: usize,
+    last_line_index: usize,
+    edits: &[&Edit<FileId>],
+) -> Hunk
+where
+    FileId: 'files + Copy + PartialEq,
+{
+    let source = files.source(file_id).unwrap();
+    let source = source.as_ref();
+    let start = files.line_range(file_id, first_line_index).unwrap().start;
+    let end = files.line_range(file_id, last_line_index).unwrap().end;
+
+    let mut patched = String::new();
+    let mut deletions = Vec::new();
+    let mut insertions = Vec::new();
+    let mut last_end = start;
+    for edit in edits {
+        patched.push_str(&source[last_end..edit.range.start]);
+        if edit.range.start != edit.range.end {
+            deletions.push((edit.range.start - start)..(edit.range.end - start));
+        }
+        if !edit.replacement.is_empty() {
+            insertions.push(patched.len()..(patched.len() + edit.replacement.len()));
+        }
+        patched.push_str(&edit.replacement);
+        last_end = edit.range.end;
+    }
+    patched.push_str(&source[last_end..end]);
+
+    let start_line_number = files.line_number(file_id, first_line_index).unwrap();
+    let deleted_lines = split_lines(&source[start..end], &deletions)
+        .into_iter()
+        .zip(first_line_index..)
+        .map(|((line, ranges), line_index)| {
+            let line_number = files.line_number(file_id, line_index).unwrap();
+            (line_number, line, ranges)
+        })
+        .collect();
+    let inserted_lines = split_lines(&patched, &insertions)
+        .into_iter()
+        .enumerate()
+        .map(|(i, (line, ranges))| (start_line_number + i, line, ranges))
+        .collect();
+
+    Hunk {
+        is_inline_insertion: edits
+            .iter()
+            .all(|edit| edit.range.start == edit.range.end && !edit.replacement.contains('\n')),
+        deleted_lines,
+        inserted_lines,
+    }
+}
+
+/// Split some text into lines, along with the parts of the ranges that fall
+/// within each line, relative to the start of the line.
+fn split_lines(text: &str, ranges: &[Range<usize>]) -> Vec<(String, Vec<Range<usize>>)> {
+    let mut lines = Vec::new();
+    let mut line_start = 0;
+
+    while line_start < text.len() {
+        let line_end = match text[line_start..].find('\n') {
+            Some(offset) => line_start + offset + 1,
+            None => text.len(),
+        };
+        let line_ranges = ranges
+            .iter()
+            .filter(|range| range.start < line_end && range.end > line_start)
+            .map(|range| {
+                let start = usize::max(range.start, line_start) - line_start;
+                let end = usize::min(range.end, line_end) - line_start;
+                start..end
+            })
+            .collect();
+
+        lines.push((text[line_start..line_end].to_string(), line_ranges));
+        line_start = line_end;
+    }
+
+    lines
+}
+
+/// Render the snippets of source code changed by a suggestion.
+///
+/// ```text
+///   ┌─ test:2:9
+///   │
+/// 2 - let x = foo.bar();
+/// 2 + let x = foo.baz();
+///   │
+/// ```
+fn render_suggested_files(
+    renderer: &mut Renderer<'_, '_>,
+    outer_padding: usize,
+    suggested_files: Vec<SuggestedFile>,
+) -> io::Result<()> {
+    for suggested_file in suggested_files {
+        renderer.render_snippet_start(
+            outer_padding,
+            &Locus {
+                name: suggested_file.name,
+                location: suggested_file.location,
+            },
+        )?;
+        renderer.render_snippet_empty(outer_padding, Severity::Help, 0, &[])?;
+
+        for (hunk_index, hunk) in suggested_file.hunks.iter().enumerate() {
+            if hunk_index > 0 {
+                renderer.render_snippet_break(outer_padding, Severity::Help, 0, &[])?;
+            }
+
+            if hunk.is_inline_insertion {
+                for (line_number, line, insertions) in &hunk.inserted_lines {
+                    renderer.render_snippet_suggestion_source(
+                        outer_padding,
+                        *line_number,
+                        line,
+                        insertions,
+                    )?;
+                }
+            } else {
+                for (line_number, line, deletions) in &hunk.deleted_lines {
+                    renderer.render_snippet_suggestion_diff(
+                        outer_padding,
+                        *line_number,
+                        Change::Deletion,
+                        line,
+                        deletions,
+                    )?;
+                }
+                for (line_number, line, insertions) in &hunk.inserted_lines {
+                    renderer.render_snippet_suggestion_diff(
+                        outer_padding,
+                        *line_number,
+                        Change::Insertion,
+                        line,
+                        insertions,
+                    )?;
+                }
+            }
+        }
+
+        renderer.render_snippet_empty(outer_padding, Severity::Help, 0, &[])?;
+    }
+
+    Ok(())
+}
+
+/// Move the labels of a diagnostic and its sub-diagnostics that are in
+/// embedded files to the host files that they were extracted from, so that
+/// their snippets show the source code of the host files.
+pub fn host_labels<'files, 'diagnostic, F>(
+    files: &'files F,
+    diagnostic: &'diagnostic Diagnostic<F::FileId>,
+) -> Cow<'diagnostic, Diagnostic<F::FileId>>
+where
+    F: Files<'files>,
+{
+    let host_label = |label: &Label<F::FileId>| {
+        let (file_id, range) = files.host_range(label.file_id, label.range.clone())?;
+        Some(Label {
+            file_id,
+            range,
+            ..label.clone()
+        })
+    };
+
+    let mut diagnostic = Cow::Borrowed(diagnostic);
+    for label_index in 0..diagnostic.labels.len() {
+        if let Some(label) = host_label(&diagnostic.labels[label_index]) {
+            diagnostic.to_mut().labels[label_index] = label;
+        }
+    }
+    for child_index in 0..diagnostic.children.len() {
+        for label_index in 0..diagnostic.children[child_index].labels.len() {
+            if let Some(label) = host_label(&diagnostic.children[child_index].labels[label_index]) {
+                diagnostic.to_mut().children[child_index].labels[label_index] = label;
+            }
+        }
+    }
+    diagnostic
+}
+
 /// Output a richly formatted diagnostic, with source code previews.
 pub struct RichDiagnostic<'diagnostic, FileId> {
     diagnostic: &'diagnostic Diagnostic<FileId>,
@@ -36,197 +769,48 @@ where
     where
         FileId: 'files,
     {
-        use std::collections::BTreeMap;
-
-        struct LabeledFile<'diagnostic, FileId> {
-            file_id: FileId,
-            start: usize,
-            name: String,
-            location: Location,
-            num_multi_labels: usize,
-            lines: BTreeMap<usize, Line<'diagnostic>>,
-        }
-
-        impl<'diagnostic, FileId> LabeledFile<'diagnostic, FileId> {
-            fn get_or_insert_line(
-                &mut self,
-                line_index: usize,
-                line_range: Range<usize>,
-                line_number: usize,
-            ) -> &mut Line<'diagnostic> {
-                self.lines.entry(line_index).or_insert_with(|| Line {
-                    range: line_range,
-                    number: line_number,
-                    single_labels: vec![],
-                    multi_labels: vec![],
-                })
-            }
-        }
-
-        struct Line<'diagnostic> {
-            number: usize,
-            range: std::ops::Range<usize>,
-            // TODO: How do we reuse these allocations?
-            single_labels: Vec<SingleLabel<'diagnostic>>,
-            multi_labels: Vec<(usize, MultiLabel<'diagnostic>)>,
-        }
-
-        // TODO: Make this data structure external, to allow for allocation reuse
-        let mut labeled_files = Vec::<LabeledFile<'_, _>>::new();
         // Keep track of the outer padding to use when rendering the
         // snippets of source code.
         let mut outer_padding = 0;
 
-        // Group labels by file
-        for label in &self.diagnostic.labels {
-            let source = files.source(label.file_id).unwrap();
-            let source = source.as_ref();
-
-            let start_line_index = files.line_index(label.file_id, label.range.start).unwrap();
-            let start_line_number = files.line_number(label.file_id, start_line_index).unwrap();
-            let start_line_range = files.line_range(label.file_id, start_line_index).unwrap();
-            let end_line_index = files.line_index(label.file_id, label.range.end).unwrap();
-            let end_line_number = files.line_number(label.file_id, end_line_index).unwrap();
-            let end_line_range = files.line_range(label.file_id, end_line_index).unwrap();
-
-            outer_padding = std::cmp::max(outer_padding, count_digits(start_line_number));
-            outer_padding = std::cmp::max(outer_padding, count_digits(end_line_number));
-
-            // NOTE: This could be made more efficient by using an associative
-            // data structure like a hashmap or B-tree,  but we use a vector to
-            // preserve the order that unique files appear in the list of labels.
-            let labeled_file = match labeled_files
-                .iter_mut()
-                .find(|labeled_file| label.file_id == labeled_file.file_id)
-            {
-                Some(labeled_file) => {
-                    if labeled_file.start > label.range.start {
-                        labeled_file.start = label.range.start;
-                        labeled_file.location =
-                            files.location(label.file_id, label.range.start).unwrap();
-                    }
-                    labeled_file
-                }
-                None => {
-                    labeled_files.push(LabeledFile {
-                        file_id: label.file_id,
-                        start: label.range.start,
-                        name: files.name(label.file_id).unwrap().to_string(),
-                        location: files.location(label.file_id, label.range.start).unwrap(),
-                        num_multi_labels: 0,
-                        lines: BTreeMap::new(),
-                    });
-                    labeled_files.last_mut().unwrap()
-                }
-            };
+        let labeled_files = label_files(
+            files,
+            &self.diagnostic.labels,
+            &self.diagnostic.tags,
+            &mut outer_padding,
+        );
 
-            if start_line_index == end_line_index {
-                // Single line
-                //
-                // ```text
-                // 2 │ (+ test "")
-                //   │         ^^ expected `Int` but found `String`
-                // ```
-                let label_start = label.range.start - start_line_range.start;
-                let label_end = label.range.end - start_line_range.start;
-
-                let line = labeled_file.get_or_insert_line(
-                    start_line_index,
-                    start_line_range,
-                    start_line_number,
-                );
+        // Macro expansion backtraces are rendered as separate snippets,
+        // in the order that they were expanded.
+        let expansion_labels = expansion_labels(
+            files,
+            &self.diagnostic.labels,
+            renderer.config().max_expansion_depth,
+        );
+        let expansion_files = expansion_labels
+            .iter()
+            .map(|label| label_files(files, std::iter::once(label), &[], &mut outer_padding))
+            .collect::<Vec<_>>();
 
-                // Ensure that the single line labels are lexicographically
-                // sorted by the range of source code that they cover.
-                let index = match line.single_labels.binary_search_by(|(_, range, _)| {
-                    // `Range<usize>` doesn't implement `Ord`, so convert to `(usize, usize)`
-                    // to piggyback off its lexicographic comparison implementation.
-                    (range.start, range.end).cmp(&(label_start, label_end))
-                }) {
-                    Ok(index) | Err(index) => index,
-                };
+        let children = self
+            .diagnostic
+            .children
+            .iter()
+            .map(|child| {
+                let labeled_files = label_files(files, &child.labels, &[], &mut outer_padding);
+                (child, labeled_files)
+            })
+            .collect::<Vec<_>>();
 
-                line.single_labels
-                    .insert(index, (label.style, label_start..label_end, &label.message));
-            } else {
-                // Multiple lines
-                //
-                // ```text
-                // 4 │   fizz₁ num = case (mod num 5) (mod num 3) of
-                //   │ ╭─────────────^
-                // 5 │ │     0 0 => "FizzBuzz"
-                // 6 │ │     0 _ => "Fizz"
-                // 7 │ │     _ 0 => "Buzz"
-                // 8 │ │     _ _ => num
-                //   │ ╰──────────────^ `case` clauses have incompatible types
-                // ```
-
-                let label_index = labeled_file.num_multi_labels;
-                labeled_file.num_multi_labels += 1;
-
-                // First labeled line
-                let label_start = label.range.start - start_line_range.start;
-                let prefix_source = &source[start_line_range.start..label.range.start];
-
-                labeled_file
-                    .get_or_insert_line(start_line_index, start_line_range, start_line_number)
-                    .multi_labels
-                    // TODO: Do this in the `Renderer`?
-                    .push(match prefix_source.trim() {
-                        // Section is prefixed by empty space, so we don't need to take
-                        // up a new line.
-                        //
-                        // ```text
-                        // 4 │ ╭     case (mod num 5) (mod num 3) of
-                        // ```
-                        "" => (label_index, MultiLabel::TopLeft(label.style)),
-                        // There's source code in the prefix, so run a label
-                        // underneath it to get to the start of the range.
-                        //
-                        // ```text
-                        // 4 │   fizz₁ num = case (mod num 5) (mod num 3) of
-                        //   │ ╭─────────────^
-                        // ```
-                        _ => (label_index, MultiLabel::Top(label.style, ..label_start)),
-                    });
-
-                // Marked lines
-                //
-                // ```text
-                // 5 │ │     0 0 => "FizzBuzz"
-                // 6 │ │     0 _ => "Fizz"
-                // 7 │ │     _ 0 => "Buzz"
-                // ```
-                // TODO(#125): If start line and end line are too far apart, add a source break.
-                for line_index in (start_line_index + 1)..end_line_index {
-                    let line_range = files.line_range(label.file_id, line_index).unwrap();
-                    let line_number = files.line_number(label.file_id, line_index).unwrap();
-
-                    outer_padding = std::cmp::max(outer_padding, count_digits(line_number));
-
-                    labeled_file
-                        .get_or_insert_line(line_index, line_range, line_number)
-                        .multi_labels
-                        .push((label_index, MultiLabel::Left(label.style)));
-                }
-
-                // Last labeled line
-                //
-                // ```text
-                // 8 │ │     _ _ => num
-                //   │ ╰──────────────^ `case` clauses have incompatible types
-                // ```
-                let label_end = label.range.end - end_line_range.start;
-
-                labeled_file
-                    .get_or_insert_line(end_line_index, end_line_range, end_line_number)
-                    .multi_labels
-                    .push((
-                        label_index,
-                        MultiLabel::Bottom(label.style, ..label_end, &label.message),
-                    ));
-            }
-        }
+        let suggestions = self
+            .diagnostic
+            .suggestions
+            .iter()
+            .map(|suggestion| {
+                let suggested_files = suggested_files(files, suggestion, &mut outer_padding);
+                (suggestion, suggested_files)
+            })
+            .collect::<Vec<_>>();
 
         // TODO: Insert `None` spaces in `labeled_files`
 
@@ -254,88 +838,20 @@ where
         //   │         ^^ expected `Int` but found `String`
         //   │
         // ```
-        for labeled_file in labeled_files {
-            let source = files.source(labeled_file.file_id).unwrap();
-            let source = source.as_ref();
-
-            // Top left border and locus.
-            //
-            // ```text
-            // ┌─ test:2:9
-            // ```
-            if !labeled_file.lines.is_empty() {
-                renderer.render_snippet_start(
-                    outer_padding,
-                    &Locus {
-                        name: labeled_file.name,
-                        location: labeled_file.location,
-                    },
-                )?;
-                renderer.render_snippet_empty(
-                    outer_padding,
-                    self.diagnostic.severity,
-                    labeled_file.num_multi_labels,
-                    &[],
-                )?;
-            }
+        let severity = self.diagnostic.severity;
+        render_labeled_files(files, renderer, severity, outer_padding, labeled_files)?;
 
-            let mut lines = labeled_file.lines.into_iter().peekable();
-            let current_labels = Vec::new();
-
-            while let Some((line_index, line)) = lines.next() {
-                renderer.render_snippet_source(
-                    outer_padding,
-                    line.number,
-                    &source[line.range.clone()],
-                    self.diagnostic.severity,
-                    &line.single_labels,
-                    labeled_file.num_multi_labels,
-                    &line.multi_labels,
-                )?;
-
-                // Check to see if we need to render any intermediate stuff
-                // before rendering the next line.
-                if let Some((next_line_index, _)) = lines.peek() {
-                    match next_line_index.checked_sub(line_index) {
-                        // Consecutive lines
-                        Some(1) => {}
-                        // One line between the current line and the next line
-                        Some(2) => {
-                            // Write a source line
-                            let file_id = labeled_file.file_id;
-                            renderer.render_snippet_source(
-                                outer_padding,
-                                files.line_number(file_id, line_index + 1).unwrap(),
-                                &source[files.line_range(file_id, line_index + 1).unwrap()],
-                                self.diagnostic.severity,
-                                &[],
-                                labeled_file.num_multi_labels,
-                                &current_labels,
-                            )?;
-                        }
-                        // More than one line between the current line and the next line.
-                        Some(_) | None => {
-                            // Source break
-                            //
-                            // ```text
-                            // ·
-                            // ```
-                            renderer.render_snippet_break(
-                                outer_padding,
-                                self.diagnostic.severity,
-                                labeled_file.num_multi_labels,
-                                &current_labels,
-                            )?;
-                        }
-                    }
-                }
-            }
-            renderer.render_snippet_empty(
-                outer_padding,
-                self.diagnostic.severity,
-                labeled_file.num_multi_labels,
-                &current_labels,
-            )?;
+        // Macro expansion backtrace
+        //
+        // ```text
+        //   ┌─ test:5:1
+        //   │
+        // 5 │ add!(test, "")
+        //   │ -------------- in this macro invocation
+        //   │
+        // ```
+        for labeled_files in expansion_files {
+            render_labeled_files(files, renderer, severity, outer_padding, labeled_files)?;
         }
 
         // Additional notes
@@ -347,6 +863,56 @@ where
         for note in &self.diagnostic.notes {
             renderer.render_snippet_note(outer_padding, note)?;
         }
+
+        // Fingerprint
+        //
+        // ```text
+        // = fingerprint: 9c3e5ab2d1f04e67
+        // ```
+        if renderer.config().show_fingerprints {
+            let note = fingerprint_note(files, self.diagnostic);
+            renderer.render_snippet_note(outer_padding, &note)?;
+        }
+
+        // Sub-diagnostics
+        //
+        // ```text
+        //   note: the previous definition is here
+        //   ┌─ test:1:4
+        //   │
+        // 1 │ fn foo() {}
+        //   │    ^^^ previous definition of `foo`
+        //   │
+        // ```
+        for (child, labeled_files) in children {
+            renderer.render_child_header(outer_padding, child.severity, child.message.as_str())?;
+            render_labeled_files(
+                files,
+                renderer,
+                child.severity,
+                outer_padding,
+                labeled_files,
+            )?;
+        }
+
+        // Suggestions
+        //
+        // ```text
+        // help: use `baz` instead
+        //   ┌─ test:2:9
+        //   │
+        // 2 - let x = foo.bar();
+        // 2 + let x = foo.baz();
+        //   │
+        // ```
+        for (suggestion, suggested_files) in suggestions {
+            let message = match suggestion.message.as_str() {
+                "" => "try",
+                message => message,
+            };
+            renderer.render_header(None, Severity::Help, None, message)?;
+            render_suggested_files(renderer, outer_padding, suggested_files)?;
+        }
         renderer.render_empty()?;
 
         Ok(())
@@ -376,10 +942,11 @@ where
     where
         FileId: 'files,
     {
-        // Located headers
+        // Located headers, followed by the sites that the file was included from
         //
         // ```text
         // test:2:9: error[E0001]: unexpected type in `+` application
+        // main.c:1:1: included from here
         // ```
         let mut primary_labels_encountered = 0;
         let labels = self.diagnostic.labels.iter();
@@ -395,6 +962,10 @@ where
                 self.diagnostic.code.as_ref().map(String::as_str),
                 self.diagnostic.message.as_str(),
             )?;
+
+            for locus in include_loci(files, label.file_id) {
+                renderer.render_include_site(&locus)?;
+            }
         }
 
         // Fallback to printing a non-located header if no primary labels were encountered
@@ -411,6 +982,56 @@ where
             )?;
         }
 
+        // Located headers for the primary labels of the sub-diagnostics, or
+        // non-located headers for sub-diagnostics without primary labels
+        //
+        // ```text
+        // test:1:4: note: the previous definition is here
+        // help: consider renaming one of the functions
+        // ```
+        for child in &self.diagnostic.children {
+            let labels = child.labels.iter();
+            let mut primary_labels_encountered = 0;
+            for label in labels.filter(|label| label.style == LabelStyle::Primary) {
+                primary_labels_encountered += 1;
+
+                renderer.render_header(
+                    Some(&Locus {
+                        name: files.name(label.file_id).unwrap().to_string(),
+                        location: files.location(label.file_id, label.range.start).unwrap(),
+                    }),
+                    child.severity,
+                    None,
+                    child.message.as_str(),
+                )?;
+            }
+
+            if primary_labels_encountered == 0 {
+                renderer.render_header(None, child.severity, None, child.message.as_str())?;
+            }
+        }
+
+        // Fingerprint
+        //
+        // ```text
+        // = fingerprint: 9c3e5ab2d1f04e67
+        // ```
+        if renderer.config().show_fingerprints {
+            let note = fingerprint_note(files, self.diagnostic);
+            renderer.render_snippet_note(0, &note)?;
+        }
+
         Ok(())
     }
 }
+
+/// A note showing the fingerprint of a diagnostic.
+fn fingerprint_note<'files, FileId>(
+    files: &'files impl Files<'files, FileId = FileId>,
+    diagnostic: &Diagnostic<FileId>,
+) -> Note
+where
+    FileId: Copy,
+{
+    Note::plain(diagnostic.fingerprint(files).to_string()).with_title("fingerprint")
+}
@@ -17,6 +17,8 @@ use termcolor::{ColorSpec, WriteColor};
 /// - Bold as `bold`
 /// - Underline as `underline`
 /// - Intense as `bright`
+/// - Dimmed as `dimmed`
+/// - Strikethrough as `strikethrough`
 ///
 /// For example, the style "intense, bold red foreground" would be printed as:
 ///
@@ -119,6 +121,16 @@ impl WriteColor for ColorBuffer {
             write!(self, "bright")?;
         }
 
+        if spec.dimmed() {
+            first = write_first(first, self)?;
+            write!(self, "dimmed")?;
+        }
+
+        if spec.strikethrough() {
+            first = write_first(first, self)?;
+            write!(self, "strikethrough")?;
+        }
+
         write!(self, "}}")?;
 
         Ok(())
@@ -0,0 +1,73 @@
+use codespan_reporting::files::{Expansion, Files, SimpleFiles};
+use std::ops::Range;
+
+/// A set of files that can record macro expansions and include sites, for
+/// testing the rendering of expansion backtraces and include stacks.
+pub struct TestFiles {
+    files: SimpleFiles<&'static str, String>,
+    expansions: Vec<(usize, Range<usize>, Expansion<usize>)>,
+    include_sites: Vec<(usize, (usize, Range<usize>))>,
+}
+
+impl TestFiles {
+    pub fn new() -> TestFiles {
+        TestFiles {
+            files: SimpleFiles::new(),
+            expansions: Vec::new(),
+            include_sites: Vec::new(),
+        }
+    }
+
+    pub fn add(&mut self, name: &'static str, source: impl Into<String>) -> usize {
+        self.files.add(name, source.into())
+    }
+
+    /// Record that the code in the given range of a file was produced by an
+    /// expansion.
+    pub fn add_expansion(&mut self, id: usize, range: Range<usize>, expansion: Expansion<usize>) {
+        self.expansions.push((id, range, expansion));
+    }
+
+    /// Record that a file was included by the code in the given range of
+    /// another file.
+    pub fn set_include_site(&mut self, id: usize, site_id: usize, site_range: Range<usize>) {
+        self.include_sites.push((id, (site_id, site_range)));
+    }
+}
+
+impl<'a> Files<'a> for TestFiles {
+    type FileId = usize;
+    type Name = &'static str;
+    type Source = &'a str;
+
+    fn name(&self, id: usize) -> Option<&'static str> {
+        self.files.name(id)
+    }
+
+    fn source(&'a self, id: usize) -> Option<&'a str> {
+        self.files.source(id)
+    }
+
+    fn line_index(&self, id: usize, byte_index: usize) -> Option<usize> {
+        self.files.line_index(id, byte_index)
+    }
+
+    fn line_range(&self, id: usize, line_index: usize) -> Option<Range<usize>> {
+        self.files.line_range(id, line_index)
+    }
+
+    fn expansion(&self, id: usize, range: Range<usize>) -> Option<Expansion<usize>> {
+        let (_, _, expansion) = self.expansions.iter().find(|(file_id, expanded, _)| {
+            *file_id == id && expanded.start <= range.start && range.end <= expanded.end
+        })?;
+        Some(expansion.clone())
+    }
+
+    fn include_site(&self, id: usize) -> Option<(usize, Range<usize>)> {
+        let (_, include_site) = self
+            .include_sites
+            .iter()
+            .find(|(file_id, _)| *file_id == id)?;
+        Some(include_site.clone())
+    }
+}
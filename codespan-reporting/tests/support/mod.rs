@@ -1,12 +1,16 @@
 use codespan_reporting::diagnostic::Diagnostic;
 use codespan_reporting::files::Files;
-use codespan_reporting::term::{emit, Config};
+use codespan_reporting::registry::CodeInfo;
+use codespan_reporting::term::{emit, explain, Config};
 use termcolor::{Buffer, WriteColor};
 
 mod color_buffer;
+mod files;
 
 use self::color_buffer::ColorBuffer;
 
+pub use self::files::TestFiles;
+
 pub struct TestData<'files, F: Files<'files>> {
     pub files: F,
     pub diagnostics: Vec<Diagnostic<F::FileId>>,
@@ -29,3 +33,15 @@ impl<'files, F: Files<'files>> TestData<'files, F> {
         String::from_utf8_lossy(buffer.as_slice()).into_owned()
     }
 }
+
+pub fn explain_color(config: &Config, info: &CodeInfo) -> String {
+    let mut writer = ColorBuffer::new();
+    explain(&mut writer, config, info).unwrap();
+    writer.into_string()
+}
+
+pub fn explain_no_color(config: &Config, info: &CodeInfo) -> String {
+    let mut writer = Buffer::no_color();
+    explain(&mut writer, config, info).unwrap();
+    String::from_utf8_lossy(writer.as_slice()).into_owned()
+}
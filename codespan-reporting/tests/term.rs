@@ -1,9 +1,10 @@
+use codespan_reporting::diagnostic::{Diagnostic, Label};
 use codespan_reporting::files::{SimpleFile, SimpleFiles};
 use codespan_reporting::term::{termcolor::Color, Config, DisplayStyle, Styles};
 
 mod support;
 
-use self::support::TestData;
+use self::support::{TestData, TestFiles};
 
 lazy_static::lazy_static! {
     static ref TEST_CONFIG: Config = Config {
@@ -577,3 +578,677 @@ mod unicode_spans {
     test_emit!(rich_no_color);
     test_emit!(short_no_color);
 }
+
+mod macro_expansion {
+    use super::*;
+
+    use codespan_reporting::files::{Expansion, ExpansionKind};
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, TestFiles> = {
+            let mut files = TestFiles::new();
+
+            let main_id = files.add(
+                "main.rs",
+                unindent::unindent(
+                    r#"
+                        macro_rules! square {
+                            ($x:expr) => { $x * $x };
+                        }
+
+                        macro_rules! quad {
+                            ($x:expr) => { square!(square!($x)) };
+                        }
+
+                        fn main() {
+                            let s = quad!("two");
+                        }
+                    "#,
+                ),
+            );
+            let quad_id = files.add("<quad macros>", r#"square!(square!("two"))"#.to_owned());
+            let square_id = files.add("<square macros>", r#""two" * "two""#.to_owned());
+
+            files.add_expansion(quad_id, 0..23, Expansion {
+                kind: ExpansionKind::Macro,
+                call_site: (main_id, 145..157),
+                def_site: Some((main_id, 94..114)),
+            });
+            files.add_expansion(square_id, 0..13, Expansion {
+                kind: ExpansionKind::Macro,
+                call_site: (quad_id, 8..22),
+                def_site: Some((main_id, 41..48)),
+            });
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("cannot multiply `&str` by `&str`")
+                    .with_code("E0369")
+                    .with_labels(vec![
+                        Label::primary(square_id, 6..7).with_message("no implementation for `&str * &str`"),
+                        Label::secondary(square_id, 0..5).with_message("&str"),
+                        Label::secondary(square_id, 8..13).with_message("&str"),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+
+    #[test]
+    fn max_expansion_depth_1_no_color() {
+        let config = Config {
+            max_expansion_depth: 1,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
+
+mod included_files {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, TestFiles> = {
+            let mut files = TestFiles::new();
+
+            let main_id = files.add(
+                "main.txt",
+                "include \"util.inc\"\ninclude \"config.inc\"\n\nprint(answer)\n".to_owned(),
+            );
+            let config_id = files.add(
+                "config.inc",
+                "let debug = true\ninclude \"defaults.inc\"\n".to_owned(),
+            );
+            let defaults_id = files.add("defaults.inc", "let answer = fourty_two\n".to_owned());
+
+            files.set_include_site(config_id, main_id, 19..39);
+            files.set_include_site(defaults_id, config_id, 17..39);
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("unknown variable `fourty_two`")
+                    .with_labels(vec![
+                        Label::primary(defaults_id, 13..23).with_message("not found in this scope"),
+                    ]),
+                Diagnostic::warning()
+                    .with_message("unused variable `debug`")
+                    .with_labels(vec![
+                        Label::primary(config_id, 4..9).with_message("never used"),
+                    ]),
+                Diagnostic::error()
+                    .with_message("file not found: `util.inc`")
+                    .with_labels(vec![
+                        Label::primary(main_id, 8..18),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+}
+
+mod suggestions {
+    use super::*;
+
+    use codespan_reporting::diagnostic::{Edit, Suggestion};
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "main.rs",
+                unindent::unindent(
+                    r#"
+                        fn main() {
+                            let x = vec![1, 2, 3];
+                            takes_slice(x);
+                            let y = x.lenght();
+                        }
+                    "#,
+                ),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("mismatched types")
+                    .with_code("E0308")
+                    .with_labels(vec![
+                        Label::primary(file_id, 55..56).with_message("expected `&[i32]`, found `Vec<i32>`"),
+                    ])
+                    .with_suggestions(vec![
+                        Suggestion::machine_applicable()
+                            .with_message("consider borrowing here")
+                            .with_edits(vec![Edit::new(file_id, 55..55, "&")]),
+                    ]),
+                Diagnostic::error()
+                    .with_message("no method named `lenght` found for struct `Vec<i32>`")
+                    .with_code("E0599")
+                    .with_labels(vec![
+                        Label::primary(file_id, 73..79).with_message("method not found in `Vec<i32>`"),
+                    ])
+                    .with_suggestions(vec![
+                        Suggestion::maybe_incorrect()
+                            .with_message("there is a method with a similar name")
+                            .with_edits(vec![Edit::new(file_id, 73..79, "len")]),
+                    ]),
+                Diagnostic::warning()
+                    .with_message("unused variable: `y`")
+                    .with_labels(vec![
+                        Label::primary(file_id, 67..68),
+                    ])
+                    .with_suggestions(vec![
+                        Suggestion::machine_applicable()
+                            .with_message("if this is intentional, prefix it with an underscore")
+                            .with_edits(vec![Edit::new(file_id, 67..68, "_y")]),
+                        Suggestion::maybe_incorrect()
+                            .with_message("remove the unused variable")
+                            .with_edits(vec![Edit::new(file_id, 59..83, "")]),
+                    ]),
+                Diagnostic::error()
+                    .with_message("cannot find function `takes_slice` in this scope")
+                    .with_code("E0425")
+                    .with_labels(vec![
+                        Label::primary(file_id, 43..54).with_message("not found in this scope"),
+                    ])
+                    .with_suggestions(vec![
+                        Suggestion::has_placeholders()
+                            .with_edits(vec![
+                                Edit::new(file_id, 85..85, "\nfn takes_slice(xs: &[i32]) {\n    todo!()\n}\n"),
+                            ]),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+}
+
+mod children {
+    use super::*;
+
+    use codespan_reporting::diagnostic::SubDiagnostic;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "main.rs",
+                unindent::unindent(
+                    r#"
+                        fn foo() {}
+
+                        fn foo() {}
+
+                        fn main() {
+                            foo();
+                        }
+                    "#,
+                ),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("the name `foo` is defined multiple times")
+                    .with_code("E0428")
+                    .with_labels(vec![
+                        Label::primary(file_id, 16..19).with_message("`foo` redefined here"),
+                    ])
+                    .with_notes(vec![
+                        "`foo` must be defined only once in the value namespace of this module".to_owned(),
+                    ])
+                    .with_children(vec![
+                        SubDiagnostic::note()
+                            .with_message("the previous definition of `foo` is here")
+                            .with_labels(vec![
+                                Label::primary(file_id, 3..6).with_message("previous definition"),
+                            ]),
+                        SubDiagnostic::help()
+                            .with_message("consider renaming one of the functions"),
+                    ]),
+                Diagnostic::warning()
+                    .with_message("this call is ambiguous")
+                    .with_labels(vec![
+                        Label::primary(file_id, 42..45),
+                    ])
+                    .with_children(vec![
+                        SubDiagnostic::warning()
+                            .with_message("it could refer to either of these functions")
+                            .with_labels(vec![
+                                Label::primary(file_id, 3..6).with_message("this one"),
+                                Label::secondary(file_id, 16..19).with_message("or this one"),
+                            ]),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+}
+
+mod typed_notes {
+    use super::*;
+
+    use codespan_reporting::diagnostic::{Note, NoteKind};
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add("main.rs", "let x: i32 = \"hello\";\n".to_owned());
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("mismatched types")
+                    .with_code("E0308")
+                    .with_labels(vec![
+                        Label::primary(file_id, 13..20).with_message("expected `i32`, found `&str`"),
+                        Label::secondary(file_id, 7..10).with_message("expected due to this"),
+                    ])
+                    .with_typed_notes(vec![
+                        Note::plain("expected type `i32`\n   found type `&str`"),
+                        Note::note("string literals always have a type of `&'static str`"),
+                        Note::help("try parsing the string:\n\"hello\".parse::<i32>()"),
+                        Note::new(NoteKind::Note, "the literal is not a number").with_title("reason"),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+}
+
+mod explain {
+    use super::*;
+
+    use codespan_reporting::diagnostic::Severity;
+    use codespan_reporting::registry::CodeInfo;
+
+    lazy_static::lazy_static! {
+        static ref CODE_INFO: CodeInfo = CodeInfo::new("E0308", Severity::Error, "mismatched types")
+            .with_explanation(unindent::unindent(
+                r#"
+                    Expected type did not match the received type.
+
+                    Erroneous code example:
+
+                    ```compile_fail,E0308
+                    fn plus_one(x: i32) -> i32 {
+                        x + 1
+                    }
+
+                    plus_one("Not a number");
+                    //       ^^^^^^^^^^^^^^ expected `i32`, found `&str`
+                    ```
+
+                    # Causes
+
+                    This error occurs when an expression was used in a place where the compiler
+                    expected an expression of a **different type**. The type of `x` is *not* the
+                    type that `plus_one` expects.
+
+                    * Check the types of the arguments.
+                    * A lone ` or * is written as-is.
+                "#,
+            ));
+    }
+
+    #[test]
+    fn rich_color() {
+        insta::assert_snapshot!(support::explain_color(&TEST_CONFIG, &CODE_INFO));
+    }
+
+    #[test]
+    fn rich_no_color() {
+        insta::assert_snapshot!(support::explain_no_color(&TEST_CONFIG, &CODE_INFO));
+    }
+}
+
+mod styled_messages {
+    use super::*;
+
+    use codespan_reporting::diagnostic::Note;
+    use codespan_reporting::markup;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "Main.elm",
+                unindent::unindent(r#"
+                    main =
+                        add 1 "two"
+
+                    add x y =
+                        x + y
+                "#),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message(format!(
+                        "the 2nd argument to `add` is not what I *expected*: {}",
+                        markup::escape("{ x | y }"),
+                    ))
+                    .with_labels(vec![
+                        Label::primary(file_id, 17..22).with_message(format!(
+                            "expected {}, found {}",
+                            markup::expected("Int"),
+                            markup::found("String"),
+                        )),
+                        Label::secondary(file_id, 24..43)
+                            .with_message("`add` is defined here, taking *two* arguments"),
+                    ])
+                    .with_typed_notes(vec![
+                        Note::help("try converting with `String.toInt`, or write {+2+} instead of {-\"two\"-}"),
+                        Note::plain("a * b and `unclosed are left as-is"),
+                    ])
+                    .with_markup(),
+                Diagnostic::warning()
+                    .with_message("messages without markup are left as-is: `2*3*4` and \\{ x }")
+                    .with_labels(vec![
+                        Label::primary(file_id, 17..22).with_message("this is *not* emphasised"),
+                    ]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+}
+
+mod localized {
+    use super::*;
+
+    use codespan_reporting::catalog::{self, Bundle};
+    use codespan_reporting::diagnostic::Note;
+    use codespan_reporting::term::SeverityNames;
+
+    lazy_static::lazy_static! {
+        static ref CATALOGS: Vec<Bundle> = vec![
+            Bundle::parse("ja", unindent::unindent(r#"
+                severity-error = エラー
+                severity-help = ヘルプ
+                type-mismatch = 型が一致しません
+                expected-found = `{$expected}` を期待しましたが、`{$found}` が見つかりました
+            "#).as_str()).unwrap(),
+            Bundle::parse("en", unindent::unindent(r#"
+                type-mismatch = mismatched types
+                expected-found = expected `{$expected}`, found `{$found}`
+                expected-due-to = expected due to this
+                parse-help =
+                    try parsing the string:
+                    "{$literal}".parse::<{$expected}>()
+            "#).as_str()).unwrap(),
+        ];
+
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add("main.rs", "let x: i32 = \"hello\";\n".to_owned());
+
+            let diagnostics = vec![
+                Diagnostic::error()
+                    .with_message("type-mismatch")
+                    .with_code("E0308")
+                    .with_labels(vec![
+                        Label::primary(file_id, 13..20).with_message("expected-found"),
+                        Label::secondary(file_id, 7..10).with_message("expected-due-to"),
+                    ])
+                    .with_typed_notes(vec![Note::help("parse-help")])
+                    .with_args(vec![("expected", "i32"), ("found", "&str"), ("literal", "hello")]),
+            ];
+            let diagnostics = diagnostics
+                .into_iter()
+                .map(|diagnostic| catalog::localize(&*CATALOGS, diagnostic))
+                .collect();
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    #[test]
+    fn rich_no_color() {
+        let config = Config {
+            display_style: DisplayStyle::Rich,
+            severity_names: SeverityNames::from_catalog(&*CATALOGS),
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn short_no_color() {
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            severity_names: SeverityNames::from_catalog(&*CATALOGS),
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
+
+mod tags {
+    use super::*;
+
+    use codespan_reporting::diagnostic::Tag;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add(
+                "main.rs",
+                unindent::unindent(r#"
+                    fn main() {
+                        let unused = 1;
+                        std::mem::uninitialized::<u8>();
+                    }
+
+                    fn dead_code() {
+                        main();
+                    }
+                "#),
+            );
+
+            let diagnostics = vec![
+                Diagnostic::warning()
+                    .with_message("unused variable: `unused`")
+                    .with_labels(vec![Label::primary(file_id, 20..26)])
+                    .with_tags(vec![Tag::Unnecessary]),
+                Diagnostic::warning()
+                    .with_message("use of deprecated function `std::mem::uninitialized`")
+                    .with_labels(vec![
+                        Label::primary(file_id, 36..59),
+                        Label::secondary(file_id, 62..64).with_message("returns `u8`"),
+                    ])
+                    .with_tags(vec![Tag::Deprecated]),
+                Diagnostic::warning()
+                    .with_message("function is never used: `dead_code`")
+                    .with_labels(vec![
+                        Label::primary(file_id, 72..102).with_message("never used"),
+                    ])
+                    .with_tags(vec![Tag::Unnecessary, Tag::Deprecated]),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+}
+
+mod from_error {
+    use super::*;
+
+    use codespan_reporting::diagnostic::AsDiagnostic;
+    use std::ops::Range;
+    use std::{error, fmt, io};
+
+    #[derive(Debug)]
+    struct ParseError {
+        file_id: usize,
+        range: Range<usize>,
+    }
+
+    impl fmt::Display for ParseError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "expected `=` after key")
+        }
+    }
+
+    impl error::Error for ParseError {}
+
+    impl AsDiagnostic<usize> for ParseError {
+        fn code(&self) -> Option<String> {
+            Some("E0001".to_owned())
+        }
+
+        fn labels(&self) -> Vec<Label<usize>> {
+            vec![Label::primary(self.file_id, self.range.clone()).with_message("expected `=`")]
+        }
+    }
+
+    #[derive(Debug)]
+    struct ConfigError {
+        path: &'static str,
+        source: Box<dyn error::Error + 'static>,
+    }
+
+    impl fmt::Display for ConfigError {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "failed to load configuration from `{}`", self.path)
+        }
+    }
+
+    impl error::Error for ConfigError {
+        fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+            Some(&*self.source)
+        }
+    }
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add("config.toml", "name = \"test\"\nversion \"1.0\"\n".to_owned());
+
+            let parse_error = ConfigError {
+                path: "config.toml",
+                source: Box::new(ParseError { file_id, range: 22..23 }),
+            };
+            let io_error = ConfigError {
+                path: "missing.toml",
+                source: Box::new(io::Error::new(io::ErrorKind::NotFound, "No such file or directory")),
+            };
+
+            let diagnostics = vec![
+                Diagnostic::from_error_with(&parse_error, |error| {
+                    error.downcast_ref::<ParseError>().map(|error| error as _)
+                }),
+                Diagnostic::from_error(&io_error),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    test_emit!(rich_color);
+    test_emit!(short_color);
+    test_emit!(rich_no_color);
+    test_emit!(short_no_color);
+}
+
+mod fingerprints {
+    use super::*;
+
+    lazy_static::lazy_static! {
+        static ref TEST_DATA: TestData<'static, SimpleFiles<&'static str, String>> = {
+            let mut files = SimpleFiles::new();
+
+            let file_id = files.add("main.rs", "fn main() {\n    let x = 1;\n}\n".to_owned());
+
+            let diagnostics = vec![
+                Diagnostic::warning()
+                    .with_code("W0001")
+                    .with_message("unused variable")
+                    .with_labels(vec![Label::primary(file_id, 20..21)])
+                    .with_notes(vec!["prefix the name with an underscore to silence this warning".to_owned()]),
+                Diagnostic::error().with_message("no configuration file found"),
+            ];
+
+            TestData { files, diagnostics }
+        };
+    }
+
+    #[test]
+    fn rich_color() {
+        let config = Config {
+            display_style: DisplayStyle::Rich,
+            show_fingerprints: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_color(&config));
+    }
+
+    #[test]
+    fn rich_no_color() {
+        let config = Config {
+            display_style: DisplayStyle::Rich,
+            show_fingerprints: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+
+    #[test]
+    fn short_no_color() {
+        let config = Config {
+            display_style: DisplayStyle::Short,
+            show_fingerprints: true,
+            ..TEST_CONFIG.clone()
+        };
+
+        insta::assert_snapshot!(TEST_DATA.emit_no_color(&config));
+    }
+}
@@ -0,0 +1,271 @@
+#[cfg(feature = "reporting")]
+use alloc::{borrow::ToOwned, format, string::String, vec};
+#[cfg(feature = "reporting")]
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+
+use crate::{ByteIndex, ByteOffset, FileId, Span};
+
+/// A cursor over the source of a file, for use when writing lexers.
+///
+/// The cursor keeps track of two byte positions: the current position, which
+/// is advanced as characters are consumed, and the start of the token that is
+/// currently being scanned, which is set with [`Cursor::mark`].
+///
+/// ```rust
+/// use codespan::{Files, Span};
+///
+/// let mut files = Files::new();
+/// let file_id = files.add("test", "let foo = 42;");
+///
+/// let mut cursor = files.cursor(file_id);
+///
+/// cursor.mark();
+/// assert_eq!(cursor.eat_while(char::is_alphabetic), "let");
+/// assert_eq!(cursor.token_span(), Span::new(0, 3));
+///
+/// cursor.eat_while(char::is_whitespace);
+///
+/// cursor.mark();
+/// assert_eq!(cursor.eat_while(char::is_alphabetic), "foo");
+/// assert_eq!(cursor.token_span(), Span::new(4, 7));
+/// ```
+///
+/// [`Cursor::mark`]: Cursor::mark
+#[derive(Clone, Debug)]
+pub struct Cursor<'source> {
+    file_id: FileId,
+    source: &'source str,
+    start: ByteIndex,
+    pos: ByteIndex,
+}
+
+impl<'source> Cursor<'source> {
+    /// Create a new cursor at the start of the given source.
+    pub fn new(file_id: FileId, source: &'source str) -> Cursor<'source> {
+        Cursor {
+            file_id,
+            source,
+            start: ByteIndex::default(),
+            pos: ByteIndex::default(),
+        }
+    }
+
+    /// The file that this cursor is scanning.
+    pub fn file_id(&self) -> FileId {
+        self.file_id
+    }
+
+    /// The full source that this cursor is scanning.
+    pub fn source(&self) -> &'source str {
+        self.source
+    }
+
+    /// The byte index of the next character.
+    pub fn pos(&self) -> ByteIndex {
+        self.pos
+    }
+
+    /// The byte index of the start of the current token.
+    pub fn start(&self) -> ByteIndex {
+        self.start
+    }
+
+    /// Returns `true` if all of the source has been consumed.
+    pub fn is_eof(&self) -> bool {
+        self.pos.to_usize() >= self.source.len()
+    }
+
+    /// The source that has not yet been consumed.
+    ///
+    /// ```rust
+    /// use codespan::Files;
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "foo bar");
+    ///
+    /// let mut cursor = files.cursor(file_id);
+    /// cursor.eat_while(|ch| ch != ' ');
+    ///
+    /// assert_eq!(cursor.rest(), " bar");
+    /// ```
+    pub fn rest(&self) -> &'source str {
+        &self.source[self.pos.to_usize()..]
+    }
+
+    /// Look at the next character without consuming it.
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// Look `n` characters ahead of the next character without consuming
+    /// anything, where `peek_nth(0)` is the same as `peek()`.
+    ///
+    /// ```rust
+    /// use codespan::Files;
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "ab💣");
+    ///
+    /// let cursor = files.cursor(file_id);
+    ///
+    /// assert_eq!(cursor.peek_nth(0), Some('a'));
+    /// assert_eq!(cursor.peek_nth(2), Some('💣'));
+    /// assert_eq!(cursor.peek_nth(3), None);
+    /// ```
+    pub fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+
+    /// Consume the next character, returning it.
+    pub fn bump(&mut self) -> Option<char> {
+        let ch = self.peek()?;
+        self.pos += ByteOffset::from_char_len(ch);
+        Some(ch)
+    }
+
+    /// Consume the next character if it is equal to `expected`, returning
+    /// `true` if it was consumed.
+    pub fn eat(&mut self, expected: char) -> bool {
+        self.eat_if(|ch| ch == expected).is_some()
+    }
+
+    /// Consume the next character if it matches the predicate, returning it.
+    pub fn eat_if(&mut self, predicate: impl FnOnce(char) -> bool) -> Option<char> {
+        match self.peek() {
+            Some(ch) if predicate(ch) => self.bump(),
+            Some(_) | None => None,
+        }
+    }
+
+    /// Consume characters while they match the predicate, returning the
+    /// source that was consumed.
+    ///
+    /// ```rust
+    /// use codespan::Files;
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "1234abc");
+    ///
+    /// let mut cursor = files.cursor(file_id);
+    ///
+    /// assert_eq!(cursor.eat_while(|ch| ch.is_ascii_digit()), "1234");
+    /// assert_eq!(cursor.eat_while(|ch| ch.is_ascii_digit()), "");
+    /// assert_eq!(cursor.peek(), Some('a'));
+    /// ```
+    pub fn eat_while(&mut self, mut predicate: impl FnMut(char) -> bool) -> &'source str {
+        let start = self.pos;
+        while self.eat_if(&mut predicate).is_some() {}
+        &self.source[start.to_usize()..self.pos.to_usize()]
+    }
+
+    /// Mark the start of a new token at the current position.
+    pub fn mark(&mut self) {
+        self.start = self.pos;
+    }
+
+    /// The span of the current token, from the last call to [`Cursor::mark`]
+    /// up to the current position.
+    ///
+    /// [`Cursor::mark`]: Cursor::mark
+    pub fn token_span(&self) -> Span {
+        Span::new(self.start, self.pos)
+    }
+
+    /// The source of the current token, from the last call to [`Cursor::mark`]
+    /// up to the current position.
+    ///
+    /// [`Cursor::mark`]: Cursor::mark
+    pub fn token_source(&self) -> &'source str {
+        &self.source[self.start.to_usize()..self.pos.to_usize()]
+    }
+
+    /// The span of the next character, or an empty span at the end of the
+    /// source if all of it has been consumed.
+    pub fn peek_span(&self) -> Span {
+        let len = self
+            .peek()
+            .map_or(ByteOffset::default(), ByteOffset::from_char_len);
+        Span::new(self.pos, self.pos + len)
+    }
+}
+
+#[cfg(feature = "reporting")]
+impl<'source> Cursor<'source> {
+    /// Create an error diagnostic, with a primary label covering the current
+    /// token.
+    pub fn error(&self, message: impl Into<String>) -> Diagnostic<FileId> {
+        Diagnostic::error()
+            .with_message(message)
+            .with_labels(vec![Label::primary(self.file_id, self.token_span())])
+    }
+
+    /// Create an error diagnostic reporting that the next character was not
+    /// expected, or that the end of the file was reached unexpectedly.
+    ///
+    /// ```rust
+    /// use codespan::Files;
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "foo $ bar");
+    ///
+    /// let mut cursor = files.cursor(file_id);
+    /// cursor.eat_while(|ch| ch.is_alphabetic() || ch.is_whitespace());
+    ///
+    /// let diagnostic = cursor.unexpected_char();
+    ///
+    /// assert_eq!(diagnostic.message, "unexpected character `$`");
+    /// assert_eq!(diagnostic.labels[0].range, 4..5);
+    /// ```
+    pub fn unexpected_char(&self) -> Diagnostic<FileId> {
+        let (message, label_message) = match self.peek() {
+            Some(ch) => (
+                format!("unexpected character `{}`", ch.escape_default()),
+                "unexpected character",
+            ),
+            None => ("unexpected end of file".to_owned(), "end of file"),
+        };
+
+        Diagnostic::error()
+            .with_message(message)
+            .with_labels(vec![
+                Label::primary(self.file_id, self.peek_span()).with_message(label_message)
+            ])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{Files, Span};
+
+    #[test]
+    fn bump_multibyte() {
+        let mut files = Files::new();
+        let file_id = files.add("test", "ßℝ💣");
+
+        let mut cursor = files.cursor(file_id);
+        cursor.bump();
+        cursor.mark();
+        assert_eq!(cursor.bump(), Some('ℝ'));
+        assert_eq!(cursor.bump(), Some('💣'));
+        assert_eq!(cursor.bump(), None);
+
+        assert!(cursor.is_eof());
+        assert_eq!(cursor.token_span(), Span::new(2, 9));
+        assert_eq!(cursor.token_source(), "ℝ💣");
+    }
+
+    #[test]
+    #[cfg(feature = "reporting")]
+    fn unexpected_eof() {
+        let mut files = Files::new();
+        let file_id = files.add("test", "\"abc");
+
+        let mut cursor = files.cursor(file_id);
+        assert!(cursor.eat('"'));
+        cursor.eat_while(|ch| ch != '"');
+
+        let diagnostic = cursor.unexpected_char();
+        assert_eq!(diagnostic.message, "unexpected end of file");
+        assert_eq!(diagnostic.labels[0].range, 4..4);
+    }
+}
@@ -0,0 +1,42 @@
+use codespan_reporting::files::ExpansionKind;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use crate::FileSpan;
+
+/// A record of where some synthesized code came from.
+///
+/// Expansions are recorded against spans of code in a [`Files`] database with
+/// [`Files::add_expansion`]. The call site of an expansion may itself be the
+/// result of another expansion, forming a chain that leads back to the code
+/// that the user wrote.
+///
+/// [`Files`]: crate::Files
+/// [`Files::add_expansion`]: crate::Files::add_expansion
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+pub struct Expansion {
+    /// The kind of the expansion.
+    pub kind: ExpansionKind,
+    /// The code that invoked the macro.
+    pub call_site: FileSpan,
+    /// The definition of the macro, if known.
+    pub def_site: Option<FileSpan>,
+}
+
+impl Expansion {
+    /// Create a new expansion that was invoked at the given call site.
+    pub fn new(kind: ExpansionKind, call_site: FileSpan) -> Expansion {
+        Expansion {
+            kind,
+            call_site,
+            def_site: None,
+        }
+    }
+
+    /// Add the definition site of the macro.
+    pub fn with_def_site(mut self, def_site: FileSpan) -> Expansion {
+        self.def_site = Some(def_site);
+        self
+    }
+}
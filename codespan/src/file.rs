@@ -1,10 +1,21 @@
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::num::NonZeroU32;
 #[cfg(feature = "serialization")]
 use serde::{Deserialize, Serialize};
+#[cfg(feature = "std")]
+use std::error;
+#[cfg(feature = "std")]
 use std::ffi::{OsStr, OsString};
-use std::num::NonZeroU32;
-use std::{error, fmt};
+#[cfg(feature = "std")]
+use std::path::{Path, PathBuf};
 
-use crate::{ByteIndex, ColumnIndex, LineIndex, LineOffset, Location, RawIndex, Span};
+use crate::snap::{self, SnapBoundary, SnapDirection, Snapped};
+use crate::{ByteIndex, ColumnIndex, Cursor, Expansion, LineIndex, LineOffset, Location};
+use crate::{FileSpan, OffsetMap, RawIndex, Span};
 
 #[derive(Debug, PartialEq)]
 pub struct LineIndexOutOfBoundsError {
@@ -12,6 +23,7 @@ pub struct LineIndexOutOfBoundsError {
     pub max: LineIndex,
 }
 
+#[cfg(feature = "std")]
 impl error::Error for LineIndexOutOfBoundsError {}
 
 impl fmt::Display for LineIndexOutOfBoundsError {
@@ -30,6 +42,7 @@ pub enum LocationError {
     InvalidCharBoundary { given: ByteIndex },
 }
 
+#[cfg(feature = "std")]
 impl error::Error for LocationError {}
 
 impl fmt::Display for LocationError {
@@ -53,6 +66,7 @@ pub struct SpanOutOfBoundsError {
     pub span: Span,
 }
 
+#[cfg(feature = "std")]
 impl error::Error for SpanOutOfBoundsError {}
 
 impl fmt::Display for SpanOutOfBoundsError {
@@ -65,6 +79,136 @@ impl fmt::Display for SpanOutOfBoundsError {
     }
 }
 
+/// The name of a file in the database.
+///
+/// Names can always be created from strings. With the `std` feature, they
+/// are stored as [`OsString`]s, and can also be created from paths that are
+/// not valid UTF-8.
+///
+/// [`OsString`]: std::ffi::OsString
+#[derive(Clone, Debug, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+pub struct FileName(FileNameRepr);
+
+#[cfg(feature = "std")]
+type FileNameRepr = OsString;
+#[cfg(not(feature = "std"))]
+type FileNameRepr = String;
+
+impl FileName {
+    /// Get the name as a string slice, if it is valid UTF-8.
+    #[cfg(feature = "std")]
+    pub fn to_str(&self) -> Option<&str> {
+        self.0.to_str()
+    }
+
+    /// Get the name as a string slice, if it is valid UTF-8.
+    #[cfg(not(feature = "std"))]
+    pub fn to_str(&self) -> Option<&str> {
+        Some(&self.0)
+    }
+
+    /// Get the name as a string, replacing any invalid UTF-8 with
+    /// `U+FFFD REPLACEMENT CHARACTER`.
+    #[cfg(feature = "std")]
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        self.0.to_string_lossy()
+    }
+
+    /// Get the name as a string, replacing any invalid UTF-8 with
+    /// `U+FFFD REPLACEMENT CHARACTER`.
+    #[cfg(not(feature = "std"))]
+    pub fn to_string_lossy(&self) -> Cow<'_, str> {
+        Cow::Borrowed(&self.0)
+    }
+
+    /// Get the name as an [`OsStr`].
+    ///
+    /// [`OsStr`]: std::ffi::OsStr
+    #[cfg(feature = "std")]
+    pub fn as_os_str(&self) -> &OsStr {
+        &self.0
+    }
+}
+
+impl fmt::Display for FileName {
+    #[cfg(feature = "std")]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Path::new(&self.0).display().fmt(f)
+    }
+
+    #[cfg(not(feature = "std"))]
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl PartialEq<str> for FileName {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == *other
+    }
+}
+
+impl From<&str> for FileName {
+    fn from(name: &str) -> FileName {
+        FileName(name.into())
+    }
+}
+
+impl From<&String> for FileName {
+    fn from(name: &String) -> FileName {
+        FileName(name.into())
+    }
+}
+
+impl From<String> for FileName {
+    fn from(name: String) -> FileName {
+        FileName(name.into())
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<&OsStr> for FileName {
+    fn from(name: &OsStr) -> FileName {
+        FileName(name.into())
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<OsString> for FileName {
+    fn from(name: OsString) -> FileName {
+        FileName(name)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<&Path> for FileName {
+    fn from(name: &Path) -> FileName {
+        FileName(name.into())
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<PathBuf> for FileName {
+    fn from(name: PathBuf) -> FileName {
+        FileName(name.into())
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsRef<OsStr> for FileName {
+    fn as_ref(&self) -> &OsStr {
+        &self.0
+    }
+}
+
+#[cfg(feature = "std")]
+impl AsRef<Path> for FileName {
+    fn as_ref(&self) -> &Path {
+        Path::new(&self.0)
+    }
+}
+
 /// A handle that points to a file in the database.
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
@@ -92,9 +236,9 @@ impl FileId {
 /// `Files` take ownership of all source text. Smart pointer types such as [`Cow<'_, str>`],
 /// [`Rc<str>`] or [`Arc<str>`] can be used to share the source text with the rest of the program.
 ///
-/// [`Cow<'_, str>`]: std::borrow::Cow
-/// [`Rc<str>`]: std::rc::Rc
-/// [`Arc<str>`]: std::sync::Arc
+/// [`Cow<'_, str>`]: alloc::borrow::Cow
+/// [`Rc<str>`]: alloc::rc::Rc
+/// [`Arc<str>`]: alloc::sync::Arc
 #[derive(Clone, Debug, Hash, PartialEq, Eq)]
 pub struct Files<Source> {
     files: Vec<File<Source>>,
@@ -105,7 +249,7 @@ where
     Source: AsRef<str>,
 {
     fn default() -> Self {
-        Self { files: vec![] }
+        Self { files: Vec::new() }
     }
 }
 
@@ -120,18 +264,98 @@ where
 
     /// Add a file to the database, returning the handle that can be used to
     /// refer to it again.
-    pub fn add(&mut self, name: impl Into<OsString>, source: Source) -> FileId {
+    pub fn add(&mut self, name: impl Into<FileName>, source: Source) -> FileId {
+        self.add_file(File::new(name.into(), source))
+    }
+
+    fn add_file(&mut self, file: File<Source>) -> FileId {
         let file_id = FileId::new(self.files.len());
-        self.files.push(File::new(name.into(), source.into()));
+        self.files.push(file);
         file_id
     }
 
+    /// Add a file that was extracted from a region of a host file, such as
+    /// a query embedded in a string literal, or a code block in a Markdown
+    /// document, returning the handle that can be used to refer to it again.
+    ///
+    /// The extracted source may have been dedented or unescaped, so the
+    /// offset map is used to map byte indices in the extracted source back to
+    /// the host file. Embedded files share the name of their host, and report
+    /// the line and column numbers of the host when used with
+    /// `codespan_reporting`.
+    ///
+    /// Labels in embedded files are moved to the host file when diagnostics
+    /// are rendered by `codespan_reporting`, so their snippets show the
+    /// source code of the host file.
+    ///
+    /// ```rust
+    /// use codespan::{FileSpan, Files, OffsetMap};
+    ///
+    /// let host_source = r#"fn main() {
+    ///     let query = "
+    ///         SELECT name
+    ///           FROM userz;
+    ///     ";
+    /// }
+    /// "#;
+    ///
+    /// let mut files = Files::new();
+    /// let host_id = files.add("main.rs", host_source.to_owned());
+    ///
+    /// // Dedent the contents of the string literal
+    /// let contents_start = host_source.find('"').unwrap() + 1;
+    /// let mut offset_map = OffsetMap::new(contents_start as u32);
+    /// offset_map.push_replaced("\n        ", "");
+    /// offset_map.push_unchanged("SELECT name\n");
+    /// offset_map.push_replaced("        ", "");
+    /// offset_map.push_unchanged("  FROM userz;\n");
+    /// offset_map.push_replaced("    ", "");
+    ///
+    /// let query = "SELECT name\n  FROM userz;\n".to_owned();
+    /// let query_id = files.add_embedded(host_id, query, offset_map);
+    ///
+    /// assert_eq!(files.host(query_id), Some(host_id));
+    /// assert_eq!(files.host_span(query_id, 19..24), Ok(FileSpan::new(host_id, 65..70)));
+    /// ```
+    pub fn add_embedded(
+        &mut self,
+        host_id: FileId,
+        source: Source,
+        offset_map: OffsetMap,
+    ) -> FileId {
+        let mut file = File::new(self.name(host_id).clone(), source);
+        file.embedding = Some(Embedding {
+            host_id,
+            offset_map,
+        });
+        self.add_file(file)
+    }
+
     /// Update a source file in place.
     ///
     /// This will mean that any outstanding byte indexes will now point to
-    /// invalid locations.
+    /// invalid locations. Any expansions recorded against the file will
+    /// also be removed.
+    ///
+    /// Files that were embedded in the file with [`Files::add_embedded`], and
+    /// files that were included by code in the file, are detached from it,
+    /// because their offsets point into the old source. Embedded files should
+    /// be added again after their host file is updated.
+    ///
+    /// [`Files::add_embedded`]: Files::add_embedded
     pub fn update(&mut self, file_id: FileId, source: Source) {
-        self.get_mut(file_id).update(source.into())
+        self.get_mut(file_id).update(source);
+
+        for file in &mut self.files {
+            let embedding = file.embedding.as_ref();
+            if embedding.map_or(false, |embedding| embedding.host_id == file_id) {
+                file.embedding = None;
+            }
+            let include_site = file.include_site.as_ref();
+            if include_site.map_or(false, |include_site| include_site.file_id == file_id) {
+                file.include_site = None;
+            }
+        }
     }
 
     /// Get a the source file using the file id.
@@ -158,7 +382,7 @@ where
     ///
     /// assert_eq!(files.name(file_id), name);
     /// ```
-    pub fn name(&self, file_id: FileId) -> &OsStr {
+    pub fn name(&self, file_id: FileId) -> &FileName {
         self.get(file_id).name()
     }
 
@@ -242,6 +466,29 @@ where
         self.get(file_id).location(byte_index.into())
     }
 
+    /// Get the byte index at the given location in the source file. This is
+    /// the inverse of [`Files::location`], returning `None` if the location
+    /// is not in the file.
+    ///
+    /// ```rust
+    /// use codespan::{ByteIndex, Files, Location};
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "foo\nbär\r\n\nbaz");
+    ///
+    /// assert_eq!(files.byte_index(file_id, Location::new(0, 0)), Some(ByteIndex::from(0)));
+    /// assert_eq!(files.byte_index(file_id, Location::new(1, 2)), Some(ByteIndex::from(7)));
+    /// assert_eq!(files.byte_index(file_id, Location::new(1, 4)), Some(ByteIndex::from(9)));
+    /// assert_eq!(files.byte_index(file_id, Location::new(1, 5)), None);
+    /// assert_eq!(files.byte_index(file_id, Location::new(3, 3)), Some(ByteIndex::from(14)));
+    /// assert_eq!(files.byte_index(file_id, Location::new(4, 0)), None);
+    /// ```
+    ///
+    /// [`Files::location`]: Files::location
+    pub fn byte_index(&self, file_id: FileId, location: Location) -> Option<ByteIndex> {
+        self.get(file_id).byte_index(location)
+    }
+
     /// Get the source of the file.
     ///
     /// ```rust
@@ -292,6 +539,227 @@ where
     ) -> Result<&str, SpanOutOfBoundsError> {
         self.get(file_id).source_slice(span.into())
     }
+
+    /// Check that a span is within the bounds of the source file, and that it
+    /// starts and ends on `char` boundaries.
+    ///
+    /// ```rust
+    /// use codespan::{ByteIndex, Files, LocationError};
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "aß");
+    ///
+    /// assert_eq!(files.validate_span(file_id, 0..3), Ok(()));
+    /// assert_eq!(
+    ///     files.validate_span(file_id, 0..2),
+    ///     Err(LocationError::InvalidCharBoundary { given: ByteIndex::from(2) }),
+    /// );
+    /// ```
+    pub fn validate_span(
+        &self,
+        file_id: FileId,
+        span: impl Into<Span>,
+    ) -> Result<(), LocationError> {
+        let span = span.into();
+        let source = self.source(file_id).as_ref();
+
+        for &given in &[span.start(), span.end()] {
+            if given > self.source_span(file_id).end() {
+                let span = self.source_span(file_id);
+                return Err(LocationError::OutOfBounds { given, span });
+            } else if !source.is_char_boundary(given.to_usize()) {
+                return Err(LocationError::InvalidCharBoundary { given });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Snap a span to `char`, grapheme, word, or line boundaries in the source
+    /// file, either growing or shrinking it. This is useful for spans that
+    /// come from byte-oriented tools, which might not start or end on `char`
+    /// boundaries.
+    ///
+    /// ```rust
+    /// use codespan::{Files, SnapBoundary, SnapDirection, Span};
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "let 💣 = foo_bar;");
+    ///
+    /// let snapped = files.snap_span(file_id, 5..6, SnapBoundary::Char, SnapDirection::Outward);
+    /// assert_eq!(snapped.map(|s| s.span), Ok(Span::new(4, 8)));
+    ///
+    /// let snapped = files.snap_span(file_id, 13..14, SnapBoundary::Word, SnapDirection::Outward);
+    /// assert_eq!(snapped.map(|s| s.span), Ok(Span::new(11, 18)));
+    ///
+    /// let snapped = files.snap_span(file_id, 4..8, SnapBoundary::Char, SnapDirection::Inward);
+    /// assert_eq!(snapped.map(|s| s.adjusted), Ok(false));
+    /// ```
+    pub fn snap_span(
+        &self,
+        file_id: FileId,
+        span: impl Into<Span>,
+        boundary: SnapBoundary,
+        direction: SnapDirection,
+    ) -> Result<Snapped, SpanOutOfBoundsError> {
+        let span = span.into();
+        let source_span = self.source_span(file_id);
+        if span.end() > source_span.end() {
+            return Err(SpanOutOfBoundsError {
+                given: span,
+                span: source_span,
+            });
+        }
+
+        Ok(snap::snap_span(
+            self.source(file_id).as_ref(),
+            span,
+            boundary,
+            direction,
+        ))
+    }
+
+    /// Get the file that the given file was extracted from, if it was added
+    /// with [`Files::add_embedded`].
+    ///
+    /// [`Files::add_embedded`]: Files::add_embedded
+    pub fn host(&self, file_id: FileId) -> Option<FileId> {
+        Some(self.get(file_id).embedding.as_ref()?.host_id)
+    }
+
+    /// Map a span in the given file to the outermost host file that it was
+    /// extracted from. Spans in files that were not extracted from a host
+    /// file are returned unchanged.
+    pub fn host_span(
+        &self,
+        file_id: FileId,
+        span: impl Into<Span>,
+    ) -> Result<FileSpan, SpanOutOfBoundsError> {
+        let mut file_span = FileSpan::new(file_id, span);
+        while let Some(embedding) = &self.get(file_span.file_id).embedding {
+            let span = embedding.offset_map.to_original_span(file_span.span)?;
+            file_span = FileSpan::new(embedding.host_id, span);
+        }
+        Ok(file_span)
+    }
+
+    /// Record that the code at the given span was produced by a macro
+    /// expansion.
+    ///
+    /// ```rust
+    /// use codespan::{Expansion, ExpansionKind, FileSpan, Files, Span};
+    ///
+    /// let mut files = Files::new();
+    /// let main_id = files.add("main.rs", "fn main() { square!(2) }");
+    /// let expanded_id = files.add("<square!>", "2 * 2");
+    ///
+    /// let call_site = FileSpan::new(main_id, 12..22);
+    /// let expansion = Expansion::new(ExpansionKind::Macro, call_site);
+    /// files.add_expansion(expanded_id, 0..5, expansion);
+    ///
+    /// assert_eq!(files.expansion(expanded_id, 4..5), Some(&expansion));
+    /// assert_eq!(files.expansion(main_id, 12..22), None);
+    /// ```
+    pub fn add_expansion(&mut self, file_id: FileId, span: impl Into<Span>, expansion: Expansion) {
+        self.get_mut(file_id)
+            .expansions
+            .push((span.into(), expansion));
+    }
+
+    /// Get the innermost expansion that produced the code at the given span.
+    pub fn expansion(&self, file_id: FileId, span: impl Into<Span>) -> Option<&Expansion> {
+        self.get(file_id).expansion(span.into())
+    }
+
+    /// Get the chain of expansions that produced the code at the given span,
+    /// starting with the innermost expansion and ending with the expansion
+    /// that was invoked from code without an expansion.
+    ///
+    /// The chain stops before any expansion whose call site was already
+    /// visited, so that expansions that are (indirectly) invoked from their
+    /// own expanded code do not produce an endless chain.
+    ///
+    /// ```rust
+    /// use codespan::{Expansion, ExpansionKind, FileSpan, Files};
+    ///
+    /// let mut files = Files::new();
+    /// let main_id = files.add("main.rs", "fn main() { twice!(x) }");
+    /// let twice_id = files.add("<twice!>", "double!(x)");
+    /// let double_id = files.add("<double!>", "x + x");
+    ///
+    /// let outer = Expansion::new(ExpansionKind::Macro, FileSpan::new(main_id, 12..21));
+    /// let inner = Expansion::new(ExpansionKind::Macro, FileSpan::new(twice_id, 0..10));
+    /// files.add_expansion(twice_id, 0..10, outer);
+    /// files.add_expansion(double_id, 0..5, inner);
+    ///
+    /// let chain = files.expansion_chain(double_id, 4..5).collect::<Vec<_>>();
+    ///
+    /// assert_eq!(chain, [&inner, &outer]);
+    /// ```
+    pub fn expansion_chain(
+        &self,
+        file_id: FileId,
+        span: impl Into<Span>,
+    ) -> impl Iterator<Item = &Expansion> + '_ {
+        let mut expansion = self.expansion(file_id, span);
+        let mut visited = Vec::new();
+
+        core::iter::from_fn(move || {
+            let current = expansion?;
+            // Guard against expansions that are (indirectly) invoked from
+            // their own expanded code.
+            if visited.contains(&current.call_site) {
+                expansion = None;
+                return None;
+            }
+            visited.push(current.call_site);
+
+            let call_site = current.call_site;
+            expansion = self.expansion(call_site.file_id, call_site.span);
+            Some(current)
+        })
+    }
+
+    /// Record that the given file was textually included by the code at
+    /// another file span, for example by an `include` directive.
+    ///
+    /// ```rust
+    /// use codespan::{FileSpan, Files};
+    ///
+    /// let mut files = Files::new();
+    /// let main_id = files.add("main.txt", "include \"lib.txt\"\n");
+    /// let lib_id = files.add("lib.txt", "let x = 1;\n");
+    ///
+    /// files.set_include_site(lib_id, FileSpan::new(main_id, 0..17));
+    ///
+    /// assert_eq!(files.include_site(lib_id), Some(FileSpan::new(main_id, 0..17)));
+    /// assert_eq!(files.include_site(main_id), None);
+    /// ```
+    pub fn set_include_site(&mut self, file_id: FileId, include_site: FileSpan) {
+        self.get_mut(file_id).include_site = Some(include_site);
+    }
+
+    /// Get the file span that textually included the given file, if any.
+    pub fn include_site(&self, file_id: FileId) -> Option<FileSpan> {
+        self.get(file_id).include_site
+    }
+
+    /// Return a cursor over the source of the file, for use in lexers.
+    ///
+    /// ```rust
+    /// use codespan::Files;
+    ///
+    /// let mut files = Files::new();
+    /// let file_id = files.add("test", "hello world!");
+    ///
+    /// let mut cursor = files.cursor(file_id);
+    ///
+    /// assert_eq!(cursor.bump(), Some('h'));
+    /// assert_eq!(cursor.rest(), "ello world!");
+    /// ```
+    pub fn cursor(&self, file_id: FileId) -> Cursor<'_> {
+        Cursor::new(file_id, self.source(file_id).as_ref())
+    }
 }
 
 #[cfg(feature = "reporting")]
@@ -304,9 +772,9 @@ where
     type Source = &'a str;
 
     fn name(&self, id: FileId) -> Option<String> {
-        use std::path::PathBuf;
+        use alloc::string::ToString;
 
-        Some(PathBuf::from(self.name(id)).display().to_string())
+        Some(self.name(id).to_string())
     }
 
     fn source(&'a self, id: FileId) -> Option<&str> {
@@ -317,60 +785,162 @@ where
         Some(self.line_index(id, byte_index as u32).to_usize())
     }
 
-    fn line_range(&'a self, id: FileId, line_index: usize) -> Option<std::ops::Range<usize>> {
+    fn line_range(&'a self, id: FileId, line_index: usize) -> Option<core::ops::Range<usize>> {
         let span = self.line_span(id, line_index as u32).ok()?;
 
         Some(span.start().to_usize()..span.end().to_usize())
     }
+
+    fn line_number(&'a self, id: FileId, line_index: usize) -> Option<usize> {
+        if self.host(id).is_none() {
+            return Some(line_index + 1);
+        }
+
+        let line_start = self.line_span(id, line_index as u32).ok()?.start();
+        let host_span = self.host_span(id, Span::new(line_start, line_start)).ok()?;
+        let host_line_index = self.line_index(host_span.file_id, host_span.span.start());
+
+        Some(host_line_index.number().to_usize())
+    }
+
+    fn column_number(&'a self, id: FileId, line_index: usize, byte_index: usize) -> Option<usize> {
+        let (id, line_index, byte_index) = match self.host(id) {
+            None => (id, LineIndex::from(line_index as RawIndex), byte_index),
+            Some(_) => {
+                let byte_index = byte_index as RawIndex;
+                let host_span = self.host_span(id, Span::new(byte_index, byte_index)).ok()?;
+                let host_index = host_span.span.start();
+                let host_line_index = self.line_index(host_span.file_id, host_index);
+
+                (host_span.file_id, host_line_index, host_index.to_usize())
+            }
+        };
+        let line_span = self.line_span(id, line_index).ok()?;
+        let line_range = line_span.start().to_usize()..line_span.end().to_usize();
+        let source = self.source(id).as_ref();
+
+        Some(codespan_reporting::files::column_index(source, line_range, byte_index) + 1)
+    }
+
+    fn include_site(&'a self, id: FileId) -> Option<(FileId, core::ops::Range<usize>)> {
+        let include_site = self.include_site(id)?;
+
+        Some((include_site.file_id, include_site.span.into()))
+    }
+
+    fn host_range(
+        &'a self,
+        id: FileId,
+        range: core::ops::Range<usize>,
+    ) -> Option<(FileId, core::ops::Range<usize>)> {
+        self.host(id)?;
+        let span = Span::new(range.start as RawIndex, range.end as RawIndex);
+        let host_span = self.host_span(id, span).ok()?;
+
+        Some((host_span.file_id, host_span.span.into()))
+    }
+
+    fn expansion(
+        &'a self,
+        id: FileId,
+        range: core::ops::Range<usize>,
+    ) -> Option<codespan_reporting::files::Expansion<FileId>> {
+        let span = Span::new(range.start as RawIndex, range.end as RawIndex);
+        let expansion = self.expansion(id, span)?;
+        let site = |file_span: crate::FileSpan| (file_span.file_id, file_span.span.into());
+
+        Some(codespan_reporting::files::Expansion {
+            kind: expansion.kind,
+            call_site: site(expansion.call_site),
+            def_site: expansion.def_site.map(site),
+        })
+    }
 }
 
 /// A file that is stored in the database.
-#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[derive(Debug, Clone)]
 #[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
 struct File<Source> {
     /// The name of the file.
-    name: OsString,
+    name: FileName,
     /// The source code of the file.
     source: Source,
-    /// The starting byte indices in the source code.
-    line_starts: Vec<ByteIndex>,
+    /// The starting byte indices in the source code, computed on the first
+    /// lookup.
+    #[cfg_attr(feature = "serialization", serde(skip))]
+    line_starts: codespan_reporting::files::LineIndex,
+    /// The macro expansions that produced spans of the source code.
+    expansions: Vec<(Span, Expansion)>,
+    /// The host file that the source code was extracted from.
+    embedding: Option<Embedding>,
+    /// The code that textually included the file.
+    include_site: Option<FileSpan>,
+}
+
+// The line starts are computed from the source, so they are ignored when
+// comparing and hashing files.
+
+impl<Source: PartialEq> PartialEq for File<Source> {
+    fn eq(&self, other: &File<Source>) -> bool {
+        self.name == other.name
+            && self.source == other.source
+            && self.expansions == other.expansions
+            && self.embedding == other.embedding
+            && self.include_site == other.include_site
+    }
+}
+
+impl<Source: Eq> Eq for File<Source> {}
+
+impl<Source: Hash> Hash for File<Source> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.name.hash(state);
+        self.source.hash(state);
+        self.expansions.hash(state);
+        self.embedding.hash(state);
+        self.include_site.hash(state);
+    }
+}
+
+/// The region of a host file that an embedded file was extracted from.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+struct Embedding {
+    host_id: FileId,
+    offset_map: OffsetMap,
 }
 
 impl<Source> File<Source>
 where
     Source: AsRef<str>,
 {
-    fn new(name: OsString, source: Source) -> Self {
-        let line_starts = codespan_reporting::files::line_starts(source.as_ref())
-            .map(|i| ByteIndex::from(i as u32))
-            .collect();
-
+    fn new(name: FileName, source: Source) -> Self {
         File {
             name,
             source,
-            line_starts,
+            line_starts: codespan_reporting::files::LineIndex::lazy(),
+            expansions: Vec::new(),
+            embedding: None,
+            include_site: None,
         }
     }
 
     fn update(&mut self, source: Source) {
-        let line_starts = codespan_reporting::files::line_starts(source.as_ref())
-            .map(|i| ByteIndex::from(i as u32))
-            .collect();
         self.source = source;
-        self.line_starts = line_starts;
+        self.line_starts = codespan_reporting::files::LineIndex::lazy();
+        self.expansions.clear();
     }
 
-    fn name(&self) -> &OsStr {
+    fn name(&self) -> &FileName {
         &self.name
     }
 
     fn line_start(&self, line_index: LineIndex) -> Result<ByteIndex, LineIndexOutOfBoundsError> {
-        use std::cmp::Ordering;
+        let source = self.source.as_ref();
 
-        match line_index.cmp(&self.last_line_index()) {
-            Ordering::Less => Ok(self.line_starts[line_index.to_usize()]),
-            Ordering::Equal => Ok(self.source_span().end()),
-            Ordering::Greater => Err(LineIndexOutOfBoundsError {
+        match self.line_starts.line_start(source, line_index.to_usize()) {
+            Some(line_start) => Ok(ByteIndex::from(line_start as RawIndex)),
+            None => Err(LineIndexOutOfBoundsError {
                 given: line_index,
                 max: self.last_line_index(),
             }),
@@ -378,7 +948,8 @@ where
     }
 
     fn last_line_index(&self) -> LineIndex {
-        LineIndex::from(self.line_starts.len() as RawIndex)
+        let line_count = self.line_starts.line_count(self.source.as_ref());
+        LineIndex::from(line_count as RawIndex)
     }
 
     fn line_span(&self, line_index: LineIndex) -> Result<Span, LineIndexOutOfBoundsError> {
@@ -389,11 +960,9 @@ where
     }
 
     fn line_index(&self, byte_index: ByteIndex) -> LineIndex {
-        match self.line_starts.binary_search(&byte_index) {
-            // Found the start of a line
-            Ok(line) => LineIndex::from(line as u32),
-            Err(next_line) => LineIndex::from(next_line as u32 - 1),
-        }
+        let source = self.source.as_ref();
+        let line_index = self.line_starts.line_index(source, byte_index.to_usize());
+        LineIndex::from(line_index as RawIndex)
     }
 
     fn location(&self, byte_index: ByteIndex) -> Result<Location, LocationError> {
@@ -424,10 +993,41 @@ where
         })
     }
 
+    fn byte_index(&self, location: Location) -> Option<ByteIndex> {
+        let line_span = self.line_span(location.line).ok()?;
+        let line_src =
+            &self.source.as_ref()[line_span.start().to_usize()..line_span.end().to_usize()];
+        let column = location.column.to_usize();
+
+        // The end of the line can only be pointed to if it isn't followed by
+        // another line
+        let offset = match line_src.char_indices().nth(column) {
+            Some((offset, _)) => offset,
+            None if !line_src.ends_with('\n') && column == line_src.chars().count() => {
+                line_src.len()
+            }
+            None => return None,
+        };
+
+        let line_start = line_span.start().to_usize();
+        Some(ByteIndex::from((line_start + offset) as RawIndex))
+    }
+
     fn source(&self) -> &Source {
         &self.source
     }
 
+    fn expansion(&self, span: Span) -> Option<&Expansion> {
+        // Use the innermost expansion that contains the span
+        self.expansions
+            .iter()
+            .filter(|(expanded, _)| {
+                expanded.start() <= span.start() && span.end() <= expanded.end()
+            })
+            .min_by_key(|(expanded, _)| expanded.end() - expanded.start())
+            .map(|(_, expansion)| expansion)
+    }
+
     fn source_span(&self) -> Span {
         Span::from_str(self.source.as_ref())
     }
@@ -446,6 +1046,7 @@ where
 #[cfg(test)]
 mod test {
     use super::*;
+    use alloc::borrow::ToOwned;
 
     const TEST_SOURCE: &str = "foo\nbar\r\n\nbaz";
 
@@ -454,21 +1055,49 @@ mod test {
         let mut files = Files::<String>::new();
         let file_id = files.add("test", TEST_SOURCE.to_owned());
 
+        let file = files.get(file_id);
+
         assert_eq!(
-            files.get(file_id).line_starts,
+            file.line_starts
+                .line_starts(file.source())
+                .collect::<Vec<_>>(),
             [
-                ByteIndex::from(0),  // "foo\n"
-                ByteIndex::from(4),  // "bar\r\n"
-                ByteIndex::from(9),  // ""
-                ByteIndex::from(10), // "baz"
+                0,  // "foo\n"
+                4,  // "bar\r\n"
+                9,  // ""
+                10, // "baz"
             ],
         );
     }
 
+    #[test]
+    fn file_names() {
+        use alloc::string::ToString;
+
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test.txt", String::new());
+
+        assert_eq!(files.name(file_id), "test.txt");
+        assert_eq!(files.name(file_id).to_str(), Some("test.txt"));
+        assert_eq!(files.name(file_id).to_string(), "test.txt");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn file_names_from_paths() {
+        use std::path::Path;
+
+        let mut files = Files::<String>::new();
+        let file_id = files.add(Path::new("src/test.txt"), String::new());
+
+        assert_eq!(files.name(file_id), "src/test.txt");
+        assert_eq!(files.name(file_id).as_os_str(), "src/test.txt");
+    }
+
     #[test]
     fn line_span_sources() {
         // Also make sure we can use `Arc` for source
-        use std::sync::Arc;
+        use alloc::sync::Arc;
 
         let mut files = Files::<Arc<str>>::new();
         let file_id = files.add("test", TEST_SOURCE.into());
@@ -485,4 +1114,143 @@ mod test {
             [Ok("foo\n"), Ok("bar\r\n"), Ok("\n"), Ok("baz")],
         );
     }
+
+    #[test]
+    #[cfg(all(feature = "reporting", feature = "std"))]
+    fn embedded_locations() {
+        use codespan_reporting::diagnostic::{Diagnostic, Label};
+        use codespan_reporting::term::{self, termcolor::NoColor};
+        use std::vec;
+
+        let mut files = Files::<String>::new();
+        let host_id = files.add("doc.md", "# Query\n\n    SELECT\n      *\n".to_owned());
+
+        // Dedent the indented code block
+        let mut offset_map = OffsetMap::new(9);
+        offset_map.push_replaced("    ", "");
+        offset_map.push_unchanged("SELECT\n");
+        offset_map.push_replaced("    ", "");
+        offset_map.push_unchanged("  *\n");
+        let query_id = files.add_embedded(host_id, "SELECT\n  *\n".to_owned(), offset_map);
+
+        assert_eq!(files.name(query_id), "doc.md");
+        assert_eq!(
+            files.host_span(query_id, 9..10),
+            Ok(FileSpan::new(host_id, 26..27))
+        );
+
+        let diagnostic = Diagnostic::error()
+            .with_message("expected `FROM`")
+            .with_labels(vec![Label::primary(query_id, 9..10)]);
+
+        let mut writer = NoColor::new(Vec::<u8>::new());
+        term::emit(&mut writer, &term::Config::default(), &files, &diagnostic).unwrap();
+        let output = String::from_utf8(writer.into_inner()).unwrap();
+
+        // The location and the snippet are both in the host file
+        assert!(output.contains("doc.md:4:7"), "{}", output);
+        assert!(output.contains(" 4 │       *\n"), "{}", output);
+        assert!(output.contains("   │       ^\n"), "{}", output);
+    }
+
+    #[test]
+    fn recursive_expansion_chains() {
+        use crate::ExpansionKind;
+
+        let mut files = Files::<String>::new();
+        let main_id = files.add("main.rs", "loop!(loop!(x))".to_owned());
+        let a_id = files.add("<a!>", "b!(x)".to_owned());
+        let b_id = files.add("<b!>", "a!(x)".to_owned());
+
+        // An expansion whose call site is inside of its own expanded code
+        let recursive = Expansion::new(ExpansionKind::Macro, FileSpan::new(main_id, 6..14));
+        files.add_expansion(main_id, 0..15, recursive);
+        let chain = files.expansion_chain(main_id, 6..14).collect::<Vec<_>>();
+        assert_eq!(chain, [&recursive]);
+
+        // A pair of expansions that invoke each other
+        let a = Expansion::new(ExpansionKind::Macro, FileSpan::new(b_id, 0..5));
+        let b = Expansion::new(ExpansionKind::Macro, FileSpan::new(a_id, 0..5));
+        files.add_expansion(a_id, 0..5, a);
+        files.add_expansion(b_id, 0..5, b);
+        let chain = files.expansion_chain(a_id, 3..4).collect::<Vec<_>>();
+        assert_eq!(chain, [&a, &b]);
+    }
+
+    #[test]
+    fn updated_hosts() {
+        let mut files = Files::<String>::new();
+        let host_id = files.add("doc.md", "```\ninclude \"lib\"\n```\n".to_owned());
+        let mut offset_map = OffsetMap::new(4);
+        offset_map.push_unchanged("include \"lib\"\n");
+        let code_id = files.add_embedded(host_id, "include \"lib\"\n".to_owned(), offset_map);
+        let lib_id = files.add("lib", "x\n".to_owned());
+        files.set_include_site(lib_id, FileSpan::new(host_id, 4..17));
+
+        assert_eq!(
+            files.host_span(code_id, 0..7),
+            Ok(FileSpan::new(host_id, 4..11))
+        );
+
+        files.update(host_id, "```\n```\n".to_owned());
+
+        assert_eq!(files.host(code_id), None);
+        assert_eq!(
+            files.host_span(code_id, 0..7),
+            Ok(FileSpan::new(code_id, 0..7))
+        );
+        assert_eq!(files.include_site(lib_id), None);
+    }
+
+    #[test]
+    #[cfg(feature = "reporting")]
+    fn locations_inside_chars() {
+        let location = codespan_reporting::files::Files::location;
+
+        let mut files = Files::<String>::new();
+        let file_id = files.add("test", "λx\nλy\n".to_owned());
+
+        // Byte indices that are not on a `char` boundary are still counted
+        // towards the column of the `char` that they are in.
+        let first = location(&files, file_id, 1).unwrap();
+        assert_eq!((first.line_number, first.column_number), (1, 1));
+        let second = location(&files, file_id, 5).unwrap();
+        assert_eq!((second.line_number, second.column_number), (2, 1));
+    }
+
+    #[test]
+    #[cfg(feature = "reporting")]
+    fn embedded_byte_indices() {
+        use codespan_reporting::files::{Files as _, Location};
+
+        let byte_index = codespan_reporting::files::Files::byte_index;
+
+        let mut files = Files::<String>::new();
+        let host_id = files.add("doc.md", "# Query\n\n    SELECT\n      *\n".to_owned());
+
+        let mut offset_map = OffsetMap::new(9);
+        offset_map.push_replaced("    ", "");
+        offset_map.push_unchanged("SELECT\n");
+        offset_map.push_replaced("    ", "");
+        offset_map.push_unchanged("  *\n");
+        let query_id = files.add_embedded(host_id, "SELECT\n  *\n".to_owned(), offset_map);
+
+        // Line and column numbers are those of the host file
+        assert_eq!(byte_index(&files, query_id, 3, 5), Some(0));
+        assert_eq!(byte_index(&files, query_id, 4, 7), Some(9));
+        assert_eq!(byte_index(&files, query_id, 1, 1), None);
+
+        let start = Location {
+            line_number: 3,
+            column_number: 5,
+        };
+        let end = Location {
+            line_number: 4,
+            column_number: 8,
+        };
+        assert_eq!(
+            files.line_column_range_to_bytes(query_id, start..end),
+            Some(0..10)
+        );
+    }
 }
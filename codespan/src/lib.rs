@@ -6,15 +6,44 @@
 //! Extra functionality is accessible by enabling feature flags. The features
 //! currently available are:
 //!
+//! - **std** - Stores the names of files as [`OsString`]s, adding
+//!   conversions between [`FileName`]s and paths, and implements
+//!   [`std::error::Error`] for the error types. This is enabled by default,
+//!   and also enables the **std** feature of `codespan_reporting`. Without it,
+//!   the crate only depends on `core` and `alloc`, and the names of files are
+//!   stored as [`String`]s.
+//! - **reporting** - Implements `codespan_reporting::files::Files` for
+//!   [`Files`], and adds methods for creating diagnostics. This is enabled by
+//!   default.
 //! - **serialization** - Adds `Serialize` and `Deserialize` implementations
-//!   for use with `serde`
+//!   for use with `serde`. This currently requires **std**.
+//! - **source-map** - Adds `SourceMap`, for translating spans in generated
+//!   code back to the original sources using Source Map v3 files. This
+//!   requires **std**.
+//!
+//! [`OsString`]: std::ffi::OsString
+//! [`String`]: alloc::string::String
+
+#![no_std]
 
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+mod cursor;
+mod expansion;
 mod file;
 mod index;
 mod location;
+mod offset_map;
+mod snap;
+#[cfg(feature = "source-map")]
+mod source_map;
 mod span;
 
-pub use crate::file::{FileId, Files};
+pub use crate::cursor::Cursor;
+pub use crate::expansion::Expansion;
+pub use crate::file::{FileId, FileName, Files};
 pub use crate::file::{LineIndexOutOfBoundsError, LocationError, SpanOutOfBoundsError};
 pub use crate::index::{ByteIndex, ByteOffset};
 pub use crate::index::{ColumnIndex, ColumnNumber, ColumnOffset};
@@ -22,4 +51,10 @@ pub use crate::index::{Index, Offset};
 pub use crate::index::{LineIndex, LineNumber, LineOffset};
 pub use crate::index::{RawIndex, RawOffset};
 pub use crate::location::Location;
-pub use crate::span::Span;
+pub use crate::offset_map::OffsetMap;
+pub use crate::snap::{SnapBoundary, SnapDirection, Snapped};
+#[cfg(feature = "source-map")]
+pub use crate::source_map::{SourceMap, SourceMapError};
+pub use crate::span::{FileSpan, Span};
+
+pub use codespan_reporting::files::ExpansionKind;
@@ -0,0 +1,405 @@
+use alloc::vec::Vec;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+
+use crate::{ByteIndex, ByteOffset, LocationError, Span, SpanOutOfBoundsError};
+
+/// A mapping between byte indices in some transformed text, and the original
+/// source that it was produced from.
+///
+/// Offset maps are built up while transforming text, for example when
+/// unescaping a string literal, normalizing unicode, or expanding tabs. This
+/// allows errors found in the transformed text to be reported at the correct
+/// location in the original source.
+///
+/// Indices into the transformed text start at zero, and indices into the
+/// original source start at the index given to [`OffsetMap::new`].
+///
+/// ```rust
+/// use codespan::{ByteIndex, OffsetMap, Span};
+///
+/// // The string literal `"a\tb\u{1F600}"` starts at byte 10 in a file, so
+/// // its contents start at byte 11.
+/// // It decodes to a tab between `a` and `b`, followed by `😀`.
+/// let mut offset_map = OffsetMap::new(11);
+/// offset_map.push_unchanged("a");
+/// offset_map.push_replaced(r"\t", "\t");
+/// offset_map.push_unchanged("b");
+/// offset_map.push_replaced(r"\u{1F600}", "😀");
+///
+/// assert_eq!(offset_map.to_original(2), Ok(ByteIndex::from(14)));
+/// assert_eq!(offset_map.to_original_span(Span::new(2, 7)), Ok(Span::new(14, 24)));
+/// assert_eq!(offset_map.to_transformed_span(Span::new(12, 14)), Ok(Span::new(1, 2)));
+/// ```
+///
+/// [`OffsetMap::new`]: OffsetMap::new
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+pub struct OffsetMap {
+    /// The starting byte index of the original source.
+    original_start: ByteIndex,
+    /// Contiguous segments of the transformed text, in order.
+    segments: Vec<Segment>,
+}
+
+/// A segment of the transformed text, and the original source that it was
+/// produced from.
+#[derive(Copy, Clone, Debug, Hash, PartialEq, Eq)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+struct Segment {
+    transformed: Span,
+    original: Span,
+    /// Whether the segment was replaced. Indices inside of replaced segments
+    /// can not be mapped one-to-one, so are snapped to the segment bounds.
+    replaced: bool,
+}
+
+impl OffsetMap {
+    /// Create a new, empty offset map for text transformed from an original
+    /// source starting at the given byte index.
+    pub fn new(original_start: impl Into<ByteIndex>) -> OffsetMap {
+        OffsetMap {
+            original_start: original_start.into(),
+            segments: Vec::new(),
+        }
+    }
+
+    /// Record that some text was copied from the original source without
+    /// being changed.
+    pub fn push_unchanged(&mut self, text: &str) {
+        let len = ByteOffset::from_str_len(text);
+        if let Some(last) = self.segments.last_mut() {
+            if !last.replaced {
+                last.transformed =
+                    Span::new(last.transformed.start(), last.transformed.end() + len);
+                last.original = Span::new(last.original.start(), last.original.end() + len);
+                return;
+            }
+        }
+        self.push_segment(len, len, false);
+    }
+
+    /// Record that some text in the original source was replaced with a
+    /// different text in the transformed text, for example an escape sequence
+    /// that was replaced with the character it represents.
+    ///
+    /// Either of the texts can be empty to record insertions or deletions.
+    pub fn push_replaced(&mut self, original: &str, replacement: &str) {
+        let original_len = ByteOffset::from_str_len(original);
+        let transformed_len = ByteOffset::from_str_len(replacement);
+        self.push_segment(original_len, transformed_len, true);
+    }
+
+    fn push_segment(
+        &mut self,
+        original_len: ByteOffset,
+        transformed_len: ByteOffset,
+        replaced: bool,
+    ) {
+        let transformed_start = self.transformed_span().end();
+        let original_start = self.original_span().end();
+
+        self.segments.push(Segment {
+            transformed: Span::new(transformed_start, transformed_start + transformed_len),
+            original: Span::new(original_start, original_start + original_len),
+            replaced,
+        });
+    }
+
+    /// The span of the transformed text.
+    pub fn transformed_span(&self) -> Span {
+        match self.segments.last() {
+            Some(last) => Span::new(0, last.transformed.end()),
+            None => Span::initial(),
+        }
+    }
+
+    /// The span of the original source that was transformed.
+    pub fn original_span(&self) -> Span {
+        match self.segments.last() {
+            Some(last) => Span::new(self.original_start, last.original.end()),
+            None => Span::new(self.original_start, self.original_start),
+        }
+    }
+
+    /// Translate a byte index in the transformed text to a byte index in the
+    /// original source.
+    ///
+    /// Indices that fall inside of replaced text are mapped to the start of
+    /// the original text that it replaced.
+    pub fn to_original(&self, index: impl Into<ByteIndex>) -> Result<ByteIndex, LocationError> {
+        let index = index.into();
+        map_start(&self.segments, index, |s| s.transformed, |s| s.original)
+            .or_else(|| end(index, self.transformed_span(), self.original_span()))
+            .ok_or_else(|| LocationError::OutOfBounds {
+                given: index,
+                span: self.transformed_span(),
+            })
+    }
+
+    /// Translate a byte index in the original source to a byte index in the
+    /// transformed text.
+    ///
+    /// Indices that fall inside of replaced text are mapped to the start of
+    /// its replacement.
+    pub fn to_transformed(&self, index: impl Into<ByteIndex>) -> Result<ByteIndex, LocationError> {
+        let index = index.into();
+        map_start(&self.segments, index, |s| s.original, |s| s.transformed)
+            .or_else(|| end(index, self.original_span(), self.transformed_span()))
+            .ok_or_else(|| LocationError::OutOfBounds {
+                given: index,
+                span: self.original_span(),
+            })
+    }
+
+    /// Translate a span of the transformed text to a span of the original
+    /// source.
+    ///
+    /// Spans are widened to cover all of the original text that produced them.
+    pub fn to_original_span(&self, span: impl Into<Span>) -> Result<Span, SpanOutOfBoundsError> {
+        let span = span.into();
+        map_span(
+            &self.segments,
+            span,
+            |s| s.transformed,
+            |s| s.original,
+            self.transformed_span(),
+            self.original_span(),
+        )
+    }
+
+    /// Translate a span of the original source to a span of the transformed
+    /// text.
+    ///
+    /// Spans are widened to cover all of the transformed text that was
+    /// produced from them.
+    pub fn to_transformed_span(&self, span: impl Into<Span>) -> Result<Span, SpanOutOfBoundsError> {
+        let span = span.into();
+        map_span(
+            &self.segments,
+            span,
+            |s| s.original,
+            |s| s.transformed,
+            self.original_span(),
+            self.transformed_span(),
+        )
+    }
+}
+
+/// Map an index that is at the end of the source span to the end of the
+/// target span.
+fn end(index: ByteIndex, source: Span, target: Span) -> Option<ByteIndex> {
+    if index == source.end() {
+        Some(target.end())
+    } else {
+        None
+    }
+}
+
+/// The index of the first segment that does not match the predicate, assuming
+/// that the segments are partitioned by it.
+// NOTE: `<[T]>::partition_point` is not available on our minimum supported
+// Rust version.
+fn partition_point(segments: &[Segment], predicate: impl Fn(&Segment) -> bool) -> usize {
+    use core::cmp::Ordering;
+
+    segments
+        .binary_search_by(|s| match predicate(s) {
+            true => Ordering::Less,
+            false => Ordering::Greater,
+        })
+        .unwrap_or_else(|i| i)
+}
+
+/// Map an index to the start of the target segment that contains it.
+fn map_start(
+    segments: &[Segment],
+    index: ByteIndex,
+    source: impl Fn(&Segment) -> Span,
+    target: impl Fn(&Segment) -> Span,
+) -> Option<ByteIndex> {
+    // Find the first non-empty segment containing the index, or starting at
+    // the index.
+    let i = partition_point(segments, |s| source(s).end() <= index);
+    let segment = segments.get(i)?;
+    let (source, target) = (source(segment), target(segment));
+
+    if source.start() > index {
+        None
+    } else if segment.replaced {
+        Some(target.start())
+    } else {
+        Some(target.start() + (index - source.start()))
+    }
+}
+
+/// Map an index to the end of the target segment that contains it.
+fn map_end(
+    segments: &[Segment],
+    index: ByteIndex,
+    source: impl Fn(&Segment) -> Span,
+    target: impl Fn(&Segment) -> Span,
+) -> Option<ByteIndex> {
+    // Find the first segment containing the index, or ending at the index.
+    let i = partition_point(segments, |s| source(s).end() < index);
+    let segment = segments.get(i)?;
+    let (source, target) = (source(segment), target(segment));
+
+    if source.start() > index {
+        None
+    } else if source.start() == index {
+        Some(target.start())
+    } else if segment.replaced {
+        Some(target.end())
+    } else {
+        Some(target.start() + (index - source.start()))
+    }
+}
+
+fn map_span(
+    segments: &[Segment],
+    span: Span,
+    source: impl Fn(&Segment) -> Span + Copy,
+    target: impl Fn(&Segment) -> Span + Copy,
+    source_span: Span,
+    target_span: Span,
+) -> Result<Span, SpanOutOfBoundsError> {
+    let start = map_start(segments, span.start(), source, target)
+        .or_else(|| end(span.start(), source_span, target_span));
+    let end = if span.start() == span.end() {
+        start
+    } else {
+        map_end(segments, span.end(), source, target)
+    };
+
+    match (start, end) {
+        (Some(start), Some(end)) => Ok(Span::new(start, end)),
+        (_, _) => Err(SpanOutOfBoundsError {
+            given: span,
+            span: source_span,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::string::String;
+
+    /// Unescape a string, recording the offsets in an offset map.
+    fn unescape(original_start: u32, source: &str) -> (String, OffsetMap) {
+        let mut unescaped = String::new();
+        let mut offset_map = OffsetMap::new(original_start);
+        let mut chars = source.char_indices();
+
+        while let Some((start, ch)) = chars.next() {
+            match ch {
+                '\\' => {
+                    let (end, escaped) = chars.next().unwrap();
+                    let replacement = match escaped {
+                        'n' => '\n',
+                        't' => '\t',
+                        _ => escaped,
+                    };
+                    let end = end + escaped.len_utf8();
+                    offset_map
+                        .push_replaced(&source[start..end], replacement.encode_utf8(&mut [0; 4]));
+                    unescaped.push(replacement);
+                }
+                _ => {
+                    offset_map.push_unchanged(&source[start..start + ch.len_utf8()]);
+                    unescaped.push(ch);
+                }
+            }
+        }
+
+        (unescaped, offset_map)
+    }
+
+    #[test]
+    fn unescaped_spans() {
+        let source = r#"let x = "foo\tbar\\baz";"#;
+        let (unescaped, offset_map) = unescape(9, &source[9..22]);
+
+        assert_eq!(unescaped, "foo\tbar\\baz");
+        assert_eq!(offset_map.transformed_span(), Span::new(0, 11));
+        assert_eq!(offset_map.original_span(), Span::new(9, 22));
+
+        let original = |start: u32, end: u32| {
+            let span = offset_map.to_original_span(Span::new(start, end)).unwrap();
+            &source[span.start().to_usize()..span.end().to_usize()]
+        };
+
+        assert_eq!(original(0, 3), "foo");
+        assert_eq!(original(3, 4), r"\t");
+        assert_eq!(original(4, 7), "bar");
+        assert_eq!(original(2, 5), r"o\tb");
+        assert_eq!(original(7, 11), r"\\baz");
+        assert_eq!(original(11, 11), "");
+    }
+
+    #[test]
+    fn round_trip() {
+        let (_, offset_map) = unescape(0, r"a\nb");
+
+        for index in 0..=3 {
+            let original = offset_map.to_original(index).unwrap();
+            assert_eq!(
+                offset_map.to_transformed(original),
+                Ok(ByteIndex::from(index))
+            );
+        }
+
+        // Indices inside of an escape sequence are snapped to its start
+        assert_eq!(offset_map.to_transformed(2), Ok(ByteIndex::from(1)));
+        assert_eq!(
+            offset_map.to_transformed_span(Span::new(2, 3)),
+            Ok(Span::new(1, 2))
+        );
+    }
+
+    #[test]
+    fn deletions() {
+        // A line continuation that is removed from the transformed text
+        let mut offset_map = OffsetMap::new(0);
+        offset_map.push_unchanged("foo");
+        offset_map.push_replaced("\\\n", "");
+        offset_map.push_unchanged("bar");
+
+        assert_eq!(
+            offset_map.to_original_span(Span::new(0, 3)),
+            Ok(Span::new(0, 3))
+        );
+        assert_eq!(
+            offset_map.to_original_span(Span::new(3, 6)),
+            Ok(Span::new(5, 8))
+        );
+        assert_eq!(
+            offset_map.to_original_span(Span::new(2, 4)),
+            Ok(Span::new(2, 6))
+        );
+        assert_eq!(offset_map.to_original(3), Ok(ByteIndex::from(5)));
+        assert_eq!(offset_map.to_transformed(4), Ok(ByteIndex::from(3)));
+    }
+
+    #[test]
+    fn out_of_bounds() {
+        let (_, offset_map) = unescape(4, r"a\nb");
+
+        assert_eq!(
+            offset_map.to_original(4),
+            Err(LocationError::OutOfBounds {
+                given: ByteIndex::from(4),
+                span: Span::new(0, 3),
+            }),
+        );
+        assert_eq!(
+            offset_map.to_transformed(2),
+            Err(LocationError::OutOfBounds {
+                given: ByteIndex::from(2),
+                span: Span::new(4, 8),
+            }),
+        );
+        assert!(offset_map.to_original_span(Span::new(1, 4)).is_err());
+    }
+}
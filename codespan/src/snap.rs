@@ -0,0 +1,222 @@
+use alloc::boxed::Box;
+#[cfg(feature = "serialization")]
+use serde::{Deserialize, Serialize};
+use unicode_segmentation::UnicodeSegmentation;
+
+use crate::{RawIndex, Span};
+
+/// The kind of boundary to snap a span to.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+pub enum SnapBoundary {
+    /// The boundaries of `char`s.
+    Char,
+    /// The boundaries of extended grapheme clusters.
+    Grapheme,
+    /// The boundaries of words, as defined by [Unicode Standard Annex #29].
+    /// Identifiers like `foo_bar2` are treated as single words.
+    ///
+    /// [Unicode Standard Annex #29]: https://www.unicode.org/reports/tr29/
+    Word,
+    /// The boundaries of lines, including their line terminators.
+    Line,
+}
+
+/// The direction to snap a span in.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+pub enum SnapDirection {
+    /// Grow the span to cover every unit that it overlaps.
+    Outward,
+    /// Shrink the span to cover only the units that it fully contains. If
+    /// there are no such units, the span is collapsed to an empty span at the
+    /// start of the unit that contains it.
+    Inward,
+}
+
+/// A span that has been snapped to boundaries.
+///
+/// Returned by [`Files::snap_span`].
+///
+/// [`Files::snap_span`]: crate::Files::snap_span
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+pub struct Snapped {
+    /// The snapped span.
+    pub span: Span,
+    /// Whether the span differs from the one that was given.
+    pub adjusted: bool,
+}
+
+/// Snap a span of the source to the given boundaries. The span must be
+/// within the bounds of the source.
+pub(crate) fn snap_span(
+    source: &str,
+    span: Span,
+    boundary: SnapBoundary,
+    direction: SnapDirection,
+) -> Snapped {
+    let (start, end) = (span.start().to_usize(), span.end().to_usize());
+
+    let (start, end) = match direction {
+        SnapDirection::Outward => (
+            floor_boundary(source, start, boundary),
+            ceil_boundary(source, end, boundary),
+        ),
+        SnapDirection::Inward => {
+            let inner_start = ceil_boundary(source, start, boundary);
+            let inner_end = floor_boundary(source, end, boundary);
+            if inner_start <= inner_end {
+                (inner_start, inner_end)
+            } else {
+                // No boundaries between the start and the end, so `inner_end`
+                // is the start of the unit that contains the span.
+                (inner_end, inner_end)
+            }
+        }
+    };
+
+    let snapped = Span::new(start as RawIndex, end as RawIndex);
+
+    Snapped {
+        span: snapped,
+        adjusted: snapped != span,
+    }
+}
+
+/// The last boundary at or before the index.
+fn floor_boundary(source: &str, index: usize, boundary: SnapBoundary) -> usize {
+    let (line_start, line) = line_at(source, index);
+    boundaries(line, boundary)
+        .map(|offset| line_start + offset)
+        .take_while(|&offset| offset <= index)
+        .last()
+        .unwrap_or(line_start)
+}
+
+/// The first boundary at or after the index.
+fn ceil_boundary(source: &str, index: usize, boundary: SnapBoundary) -> usize {
+    let (line_start, line) = line_at(source, index);
+    boundaries(line, boundary)
+        .map(|offset| line_start + offset)
+        .find(|&offset| offset >= index)
+        .unwrap_or(line_start + line.len())
+}
+
+/// The line containing the byte index, along with its starting index.
+///
+/// None of the supported boundaries cross line terminators, so boundaries
+/// only need to be found within a single line.
+fn line_at(source: &str, index: usize) -> (usize, &str) {
+    let bytes = source.as_bytes();
+    let line_start = bytes[..index]
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .map_or(0, |newline| newline + 1);
+    let line_end = bytes[index..]
+        .iter()
+        .position(|&byte| byte == b'\n')
+        .map_or(source.len(), |newline| index + newline + 1);
+
+    // Only `\n` bytes are searched for, so these are always char boundaries
+    (line_start, &source[line_start..line_end])
+}
+
+/// The offsets of the boundaries within a line, in ascending order,
+/// including the start and end of the line.
+fn boundaries<'a>(line: &'a str, boundary: SnapBoundary) -> Box<dyn Iterator<Item = usize> + 'a> {
+    let end = core::iter::once(line.len());
+    match boundary {
+        SnapBoundary::Char => Box::new(line.char_indices().map(|(i, _)| i).chain(end)),
+        SnapBoundary::Grapheme => Box::new(line.grapheme_indices(true).map(|(i, _)| i).chain(end)),
+        SnapBoundary::Word => Box::new(line.split_word_bound_indices().map(|(i, _)| i).chain(end)),
+        SnapBoundary::Line => Box::new(core::iter::once(0).chain(end)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Snap a span outward and inward.
+    fn snap(source: &str, span: (u32, u32), boundary: SnapBoundary) -> [(u32, u32, bool); 2] {
+        let span = Span::new(span.0, span.1);
+        let snap = |direction| {
+            let snapped = snap_span(source, span, boundary, direction);
+            let (start, end) = (snapped.span.start(), snapped.span.end());
+            (
+                start.to_usize() as u32,
+                end.to_usize() as u32,
+                snapped.adjusted,
+            )
+        };
+
+        [snap(SnapDirection::Outward), snap(SnapDirection::Inward)]
+    }
+
+    #[test]
+    fn chars() {
+        // `ß` is 2 bytes, `💣` is 4 bytes
+        let source = "aß💣b";
+        assert_eq!(
+            snap(source, (2, 4), SnapBoundary::Char),
+            [(1, 7, true), (3, 3, true)],
+        );
+        assert_eq!(
+            snap(source, (1, 7), SnapBoundary::Char),
+            [(1, 7, false), (1, 7, false)],
+        );
+        assert_eq!(
+            snap(source, (4, 5), SnapBoundary::Char),
+            [(3, 7, true), (3, 3, true)],
+        );
+    }
+
+    #[test]
+    fn graphemes() {
+        // `e` followed by a 2 byte combining acute accent
+        let source = "ce\u{301}s";
+        assert_eq!(
+            snap(source, (1, 2), SnapBoundary::Grapheme),
+            [(1, 4, true), (1, 1, true)],
+        );
+        assert_eq!(
+            snap(source, (0, 3), SnapBoundary::Grapheme),
+            [(0, 4, true), (0, 1, true)],
+        );
+    }
+
+    #[test]
+    fn words() {
+        let source = "let foo_bar = 42;\nfoo";
+        assert_eq!(
+            snap(source, (5, 9), SnapBoundary::Word),
+            [(4, 11, true), (4, 4, true)],
+        );
+        assert_eq!(
+            snap(source, (2, 16), SnapBoundary::Word),
+            [(0, 16, true), (3, 16, true)],
+        );
+        assert_eq!(
+            snap(source, (14, 20), SnapBoundary::Word),
+            [(14, 21, true), (14, 18, true)],
+        );
+    }
+
+    #[test]
+    fn lines() {
+        let source = "foo\r\nbar\nbaz";
+        assert_eq!(
+            snap(source, (1, 6), SnapBoundary::Line),
+            [(0, 9, true), (5, 5, true)],
+        );
+        assert_eq!(
+            snap(source, (5, 12), SnapBoundary::Line),
+            [(5, 12, false), (5, 12, false)],
+        );
+        assert_eq!(
+            snap(source, (10, 11), SnapBoundary::Line),
+            [(9, 12, true), (9, 9, true)],
+        );
+    }
+}
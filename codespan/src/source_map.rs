@@ -0,0 +1,587 @@
+#[cfg(feature = "reporting")]
+use codespan_reporting::diagnostic::{Diagnostic, Label};
+use serde::Deserialize;
+use std::string::String;
+use std::vec::Vec;
+use std::{error, fmt, format};
+
+use crate::{ByteIndex, ByteOffset, FileId, FileSpan, Files, RawIndex, Span};
+
+/// A [Source Map Revision 3] that describes how the code in a generated file,
+/// for example the output of a compiler, minifier, or bundler, maps back to
+/// the original sources.
+///
+/// Columns in source maps are counted in UTF-16 code units, as is the
+/// convention for JavaScript tools. Positions within a mapped segment are
+/// assumed to correspond one-to-one with the original source, as is the case
+/// when tokens are copied verbatim.
+///
+/// ```rust
+/// use codespan::{FileSpan, Files, SourceMap};
+///
+/// let mut files = Files::<String>::new();
+/// let generated_id = files.add("add.js", "function add(a,b){return a+c}\n".to_owned());
+///
+/// let source_map = SourceMap::from_json(
+///     &mut files,
+///     generated_id,
+///     r#"{
+///         "version": 3,
+///         "file": "add.js",
+///         "sources": ["add.ts"],
+///         "sourcesContent": ["function add(a: number, b: number) {\n  return a + c;\n}\n"],
+///         "names": [],
+///         "mappings": "AAAA,SAAS,SACP,OAAO,EAAI"
+///     }"#,
+/// )
+/// .unwrap();
+///
+/// let original_id = source_map.source_id(0).unwrap();
+/// assert_eq!(files.name(original_id), "add.ts");
+///
+/// // The `c` in `a+c`
+/// assert_eq!(
+///     source_map.original_span(&files, 27..28),
+///     Some(FileSpan::new(original_id, 50..51)),
+/// );
+/// ```
+///
+/// [Source Map Revision 3]: https://sourcemaps.info/spec.html
+#[derive(Clone, Debug)]
+pub struct SourceMap {
+    /// The file that the source map describes.
+    generated_id: FileId,
+    /// The names of the original sources, including the source root.
+    sources: Vec<String>,
+    /// The files containing the original sources, if they are known.
+    source_ids: Vec<Option<FileId>>,
+    /// The decoded mappings, sorted by their generated position.
+    mappings: Vec<Mapping>,
+}
+
+/// A mapping from the start of a segment of the generated file.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct Mapping {
+    generated_line: u32,
+    generated_column: u32,
+    original: Option<OriginalPosition>,
+}
+
+/// A position in one of the original sources.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+struct OriginalPosition {
+    source: u32,
+    line: u32,
+    column: u32,
+}
+
+/// The JSON representation of a source map.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RawSourceMap {
+    version: u32,
+    #[serde(default)]
+    source_root: Option<String>,
+    sources: Vec<Option<String>>,
+    #[serde(default)]
+    sources_content: Vec<Option<String>>,
+    #[serde(default)]
+    names: Vec<String>,
+    mappings: String,
+}
+
+impl SourceMap {
+    /// Parse a source map for the given generated file.
+    ///
+    /// Any original sources that are embedded in the `sourcesContent` of the
+    /// source map are added to the database of files. Original sources that
+    /// are not embedded can be registered afterwards with
+    /// [`SourceMap::set_source_id`].
+    ///
+    /// [`SourceMap::set_source_id`]: SourceMap::set_source_id
+    pub fn from_json<Source>(
+        files: &mut Files<Source>,
+        generated_id: FileId,
+        json: &str,
+    ) -> Result<SourceMap, SourceMapError>
+    where
+        Source: AsRef<str> + From<String>,
+    {
+        let raw = serde_json::from_str::<RawSourceMap>(json).map_err(SourceMapError::Json)?;
+        if raw.version != 3 {
+            return Err(SourceMapError::UnsupportedVersion(raw.version));
+        }
+
+        let source_root = raw.source_root.unwrap_or_default();
+        let sources = raw
+            .sources
+            .into_iter()
+            .map(|source| {
+                let source = source.unwrap_or_default();
+                if source_root.is_empty() || source_root.ends_with('/') {
+                    format!("{}{}", source_root, source)
+                } else {
+                    format!("{}/{}", source_root, source)
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let mappings = decode_mappings(&raw.mappings, sources.len(), raw.names.len())?;
+
+        let mut sources_content = raw.sources_content.into_iter();
+        let source_ids = sources
+            .iter()
+            .map(|source| {
+                let content = sources_content.next().flatten()?;
+                Some(files.add(source.as_str(), Source::from(content)))
+            })
+            .collect();
+
+        Ok(SourceMap {
+            generated_id,
+            sources,
+            source_ids,
+            mappings,
+        })
+    }
+
+    /// The generated file that the source map describes.
+    pub fn generated_id(&self) -> FileId {
+        self.generated_id
+    }
+
+    /// The names of the original sources, including the source root.
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    /// The file containing the original source at the given index, if it is
+    /// known.
+    pub fn source_id(&self, index: usize) -> Option<FileId> {
+        self.source_ids.get(index).copied().flatten()
+    }
+
+    /// Set the file containing the original source at the given index, for
+    /// sources that were not embedded in the source map.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is out of bounds of [`SourceMap::sources`].
+    ///
+    /// [`SourceMap::sources`]: SourceMap::sources
+    pub fn set_source_id(&mut self, index: usize, file_id: FileId) {
+        self.source_ids[index] = Some(file_id);
+    }
+
+    /// Translate a span in the generated file to the original source that it
+    /// was produced from.
+    ///
+    /// Returns `None` if the start of the span is not mapped to a known
+    /// original source. If the end of the span is mapped to a different
+    /// source, or to a position before the start, an empty span is returned
+    /// at the start.
+    pub fn original_span<Source>(
+        &self,
+        files: &Files<Source>,
+        span: impl Into<Span>,
+    ) -> Option<FileSpan>
+    where
+        Source: AsRef<str>,
+    {
+        let span = span.into();
+        let (file_id, start) = self.original_index(files, span.start(), false)?;
+        let end = match self.original_index(files, span.end(), true) {
+            Some((end_file_id, end)) if end_file_id == file_id && end >= start => end,
+            Some(_) | None => start,
+        };
+
+        Some(FileSpan::new(file_id, Span::new(start, end)))
+    }
+
+    /// Translate the labels of a diagnostic that point at the generated file
+    /// to the original sources, adding secondary labels that point at the
+    /// generated code. Labels that can not be translated are left unchanged.
+    #[cfg(feature = "reporting")]
+    pub fn map_diagnostic<Source>(
+        &self,
+        files: &Files<Source>,
+        diagnostic: Diagnostic<FileId>,
+    ) -> Diagnostic<FileId>
+    where
+        Source: AsRef<str>,
+    {
+        let mut labels = Vec::with_capacity(diagnostic.labels.len());
+        let mut generated_labels = Vec::<Label<FileId>>::new();
+
+        for label in diagnostic.labels {
+            let original = if label.file_id == self.generated_id {
+                let span = Span::new(label.range.start as RawIndex, label.range.end as RawIndex);
+                self.original_span(files, span)
+            } else {
+                None
+            };
+
+            match original {
+                Some(original) => {
+                    if !generated_labels.iter().any(|l| l.range == label.range) {
+                        generated_labels.push(
+                            Label::secondary(self.generated_id, label.range.clone())
+                                .with_message("in the generated code"),
+                        );
+                    }
+                    labels.push(Label {
+                        file_id: original.file_id,
+                        range: original.span.into(),
+                        ..label
+                    });
+                }
+                None => labels.push(label),
+            }
+        }
+        labels.extend(generated_labels);
+
+        Diagnostic {
+            labels,
+            ..diagnostic
+        }
+    }
+
+    /// Translate a byte index in the generated file to the original source.
+    ///
+    /// If `is_end` is `true`, the index is treated as the end of a span, and
+    /// so is mapped using the segment that ends at the index, rather than
+    /// the segment that starts at it.
+    fn original_index<Source>(
+        &self,
+        files: &Files<Source>,
+        index: ByteIndex,
+        is_end: bool,
+    ) -> Option<(FileId, ByteIndex)>
+    where
+        Source: AsRef<str>,
+    {
+        let generated_source = files.source(self.generated_id).as_ref();
+        let line_index = files.line_index(self.generated_id, index);
+        let line_start = files.line_span(self.generated_id, line_index).ok()?.start();
+        let line_prefix = generated_source.get(line_start.to_usize()..index.to_usize())?;
+
+        let line = line_index.to_usize() as u32;
+        let column = line_prefix.encode_utf16().count() as u32;
+
+        // Find the last segment that starts before the position (or at it, if
+        // the position is not the end of a span).
+        let position = (line, column);
+        let mapping_count = self
+            .mappings
+            .binary_search_by(|mapping| {
+                let mapping_position = (mapping.generated_line, mapping.generated_column);
+                match mapping_position < position || (!is_end && mapping_position == position) {
+                    true => std::cmp::Ordering::Less,
+                    false => std::cmp::Ordering::Greater,
+                }
+            })
+            .unwrap_or_else(|i| i);
+        let mapping = self.mappings[..mapping_count].last()?;
+        if mapping.generated_line != line {
+            return None;
+        }
+
+        let original = mapping.original?;
+        let file_id = self.source_id(original.source as usize)?;
+        let original_line_span = files.line_span(file_id, original.line).ok()?;
+        let original_line = files.source_slice(file_id, original_line_span).ok()?;
+        let original_column = original.column + (column - mapping.generated_column);
+        let offset = utf16_column_to_offset(original_line, original_column);
+
+        Some((file_id, original_line_span.start() + offset))
+    }
+}
+
+/// Convert a column in UTF-16 code units to a byte offset into a line,
+/// clamping it to the end of the line.
+fn utf16_column_to_offset(line: &str, column: u32) -> ByteOffset {
+    let line = line.trim_end_matches(&['\n', '\r'][..]);
+
+    let mut units = 0;
+    for (offset, ch) in line.char_indices() {
+        if units >= column {
+            return ByteOffset::from(offset as i64);
+        }
+        units += ch.len_utf16() as u32;
+    }
+
+    ByteOffset::from_str_len(line)
+}
+
+/// Decode the `mappings` field of a source map, sorted by generated position.
+fn decode_mappings(
+    mappings: &str,
+    num_sources: usize,
+    num_names: usize,
+) -> Result<Vec<Mapping>, SourceMapError> {
+    let mut decoded = Vec::new();
+
+    // The source, original line, original column and name fields are
+    // relative to the previous occurrence of the field in the mappings.
+    let mut source = 0;
+    let mut original_line = 0;
+    let mut original_column = 0;
+    let mut name = 0;
+
+    let mut index = 0;
+    for (generated_line, line) in mappings.split(';').enumerate() {
+        // The generated column is relative to the previous segment on the
+        // same line.
+        let mut generated_column = 0;
+
+        for segment in line.split(',') {
+            let invalid = {
+                let index = index;
+                move || SourceMapError::InvalidMappings { index }
+            };
+            index += segment.len() + 1;
+            if segment.is_empty() {
+                continue;
+            }
+
+            let fields = decode_vlq(segment).ok_or_else(invalid)?;
+            generated_column = add_field(generated_column, fields[0], core::u32::MAX as usize)
+                .ok_or_else(invalid)?;
+
+            let original = match fields.len() {
+                1 => None,
+                4 | 5 => {
+                    source = add_field(source, fields[1], num_sources.saturating_sub(1))
+                        .ok_or_else(invalid)?;
+                    original_line = add_field(original_line, fields[2], core::u32::MAX as usize)
+                        .ok_or_else(invalid)?;
+                    original_column =
+                        add_field(original_column, fields[3], core::u32::MAX as usize)
+                            .ok_or_else(invalid)?;
+                    if num_sources == 0 {
+                        return Err(invalid());
+                    }
+                    if let Some(&field) = fields.get(4) {
+                        name = add_field(name, field, num_names.saturating_sub(1))
+                            .ok_or_else(invalid)?;
+                        if num_names == 0 {
+                            return Err(invalid());
+                        }
+                    }
+
+                    Some(OriginalPosition {
+                        source: source as u32,
+                        line: original_line as u32,
+                        column: original_column as u32,
+                    })
+                }
+                _ => return Err(invalid()),
+            };
+
+            decoded.push(Mapping {
+                generated_line: generated_line as u32,
+                generated_column: generated_column as u32,
+                original,
+            });
+        }
+    }
+
+    // Segments are usually in order already, so this is cheap.
+    decoded.sort_by_key(|mapping| (mapping.generated_line, mapping.generated_column));
+
+    Ok(decoded)
+}
+
+/// Add a relative field to its previous value, checking that the result is
+/// in the range `0..=max`.
+fn add_field(previous: usize, relative: i64, max: usize) -> Option<usize> {
+    let value = previous as i64 + relative;
+    if value < 0 || value as u64 > max as u64 {
+        None
+    } else {
+        Some(value as usize)
+    }
+}
+
+/// Decode a segment of base64 VLQ encoded values.
+fn decode_vlq(segment: &str) -> Option<Vec<i64>> {
+    const CONTINUATION_BIT: i64 = 0b10_0000;
+    const VALUE_MASK: i64 = 0b01_1111;
+
+    let mut values = Vec::new();
+    let mut value = 0;
+    let mut shift = 0;
+
+    for byte in segment.bytes() {
+        let digit = match byte {
+            b'A'..=b'Z' => byte - b'A',
+            b'a'..=b'z' => byte - b'a' + 26,
+            b'0'..=b'9' => byte - b'0' + 52,
+            b'+' => 62,
+            b'/' => 63,
+            _ => return None,
+        } as i64;
+
+        value += (digit & VALUE_MASK) << shift;
+
+        if digit & CONTINUATION_BIT == 0 {
+            // The lowest bit is the sign
+            values.push(match value & 1 {
+                0 => value >> 1,
+                _ => -(value >> 1),
+            });
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 5;
+            if shift > 30 {
+                return None;
+            }
+        }
+    }
+
+    match shift {
+        0 => Some(values),
+        // The last value was not terminated
+        _ => None,
+    }
+}
+
+/// An error that occurred while parsing a source map.
+#[derive(Debug)]
+pub enum SourceMapError {
+    /// The source map was not valid JSON, or was missing required fields.
+    Json(serde_json::Error),
+    /// The source map was not version 3.
+    UnsupportedVersion(u32),
+    /// The `mappings` field was invalid at the given byte index.
+    InvalidMappings { index: usize },
+}
+
+impl error::Error for SourceMapError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            SourceMapError::Json(error) => Some(error),
+            SourceMapError::UnsupportedVersion(_) | SourceMapError::InvalidMappings { .. } => None,
+        }
+    }
+}
+
+impl fmt::Display for SourceMapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SourceMapError::Json(error) => write!(f, "Invalid source map - {}", error),
+            SourceMapError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported source map version - given: {}", version)
+            }
+            SourceMapError::InvalidMappings { index } => {
+                write!(f, "Invalid source map mappings - index: {}", index)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::borrow::ToOwned;
+    use std::vec;
+
+    #[test]
+    fn vlq() {
+        assert_eq!(decode_vlq("AAAA"), Some(vec![0, 0, 0, 0]));
+        assert_eq!(decode_vlq("SACP"), Some(vec![9, 0, 1, -7]));
+        assert_eq!(decode_vlq("2Hx+BD"), Some(vec![123, -1000, -1]));
+        assert_eq!(decode_vlq("g"), None);
+        assert_eq!(decode_vlq("A*"), None);
+    }
+
+    #[test]
+    fn invalid_mappings() {
+        let mut files = Files::<String>::new();
+        let generated_id = files.add("out.js", "x\n".to_owned());
+
+        let json = |mappings: &str| {
+            format!(
+                r#"{{"version": 3, "sources": ["in.js"], "names": [], "mappings": "{}"}}"#,
+                mappings,
+            )
+        };
+
+        // Negative generated column
+        let error = SourceMap::from_json(&mut files, generated_id, &json("AAAA,DAAA"));
+        assert!(matches_invalid(error, 5));
+        // Source index out of bounds
+        let error = SourceMap::from_json(&mut files, generated_id, &json("AAAA;ACAA"));
+        assert!(matches_invalid(error, 5));
+        // Name index out of bounds
+        let error = SourceMap::from_json(&mut files, generated_id, &json("AAAAA"));
+        assert!(matches_invalid(error, 0));
+        // Wrong number of fields
+        let error = SourceMap::from_json(&mut files, generated_id, &json("AA"));
+        assert!(matches_invalid(error, 0));
+    }
+
+    fn matches_invalid(result: Result<SourceMap, SourceMapError>, expected: usize) -> bool {
+        match result {
+            Err(SourceMapError::InvalidMappings { index }) => index == expected,
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn unknown_sources() {
+        let mut files = Files::<String>::new();
+        let generated_id = files.add("out.js", "let π=1;\n".to_owned());
+        let json = r#"{
+            "version": 3,
+            "sourceRoot": "src",
+            "sources": ["in.js"],
+            "mappings": "AAAE,IAAI"
+        }"#;
+
+        let mut source_map = SourceMap::from_json(&mut files, generated_id, json).unwrap();
+        assert_eq!(source_map.sources(), ["src/in.js"]);
+        assert_eq!(source_map.source_id(0), None);
+        assert_eq!(source_map.original_span(&files, 4..6), None);
+
+        let original_id = files.add("src/in.js", "  let π = 1;\n".to_owned());
+        source_map.set_source_id(0, original_id);
+
+        // `π` is one UTF-16 code unit, but two bytes
+        assert_eq!(
+            source_map.original_span(&files, 4..6),
+            Some(FileSpan::new(original_id, 6..8)),
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "reporting")]
+    fn map_diagnostic() {
+        let mut files = Files::<String>::new();
+        let generated_id = files.add("out.js", "a();b()\n".to_owned());
+        let json = r#"{
+            "version": 3,
+            "sources": ["in.js"],
+            "sourcesContent": ["a();\nb();\n"],
+            "mappings": "AAAA,IACA"
+        }"#;
+        let source_map = SourceMap::from_json(&mut files, generated_id, json).unwrap();
+        let original_id = source_map.source_id(0).unwrap();
+
+        let diagnostic = Diagnostic::error().with_labels(vec![
+            Label::primary(generated_id, 4..5).with_message("not a function"),
+            Label::secondary(original_id, 0..1),
+        ]);
+        let diagnostic = source_map.map_diagnostic(&files, diagnostic);
+
+        assert_eq!(
+            diagnostic.labels,
+            [
+                Label::primary(original_id, 5..6).with_message("not a function"),
+                Label::secondary(original_id, 0..1),
+                Label::secondary(generated_id, 4..5).with_message("in the generated code"),
+            ]
+        );
+    }
+}
@@ -1,9 +1,9 @@
+use core::fmt;
+use core::ops::Range;
 #[cfg(feature = "serialization")]
 use serde::{Deserialize, Serialize};
-use std::fmt;
-use std::ops::Range;
 
-use crate::{ByteIndex, RawIndex};
+use crate::{ByteIndex, FileId, RawIndex};
 
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
@@ -60,7 +60,7 @@ impl Span {
     /// assert_eq!(Span::merge(span1, span2), Span::new(0, 16));
     /// ```
     pub fn merge(self, other: Span) -> Span {
-        use std::cmp::{max, min};
+        use core::cmp::{max, min};
 
         let start = min(self.start, other.start);
         let end = max(self.end, other.end);
@@ -149,6 +149,38 @@ impl From<Span> for Range<RawIndex> {
     }
 }
 
+/// A span in a specific file.
+///
+/// ```rust
+/// use codespan::{FileSpan, Files, Span};
+///
+/// let mut files = Files::new();
+/// let file_id = files.add("test", "hello world!");
+///
+/// let file_span = FileSpan::new(file_id, 6..11);
+///
+/// assert_eq!(file_span.span, Span::new(6, 11));
+/// assert_eq!(files.source_slice(file_span.file_id, file_span.span), Ok("world"));
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serialization", derive(Deserialize, Serialize))]
+pub struct FileSpan {
+    /// The file that the span is in.
+    pub file_id: FileId,
+    /// The span in the file.
+    pub span: Span,
+}
+
+impl FileSpan {
+    /// Create a new span in the given file.
+    pub fn new(file_id: FileId, span: impl Into<Span>) -> FileSpan {
+        FileSpan {
+            file_id,
+            span: span.into(),
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
     #[test]